@@ -0,0 +1,92 @@
+use std::rc::Rc;
+use crate::geometry::{Matrix3, Quaternion, Vector3};
+use crate::orbit;
+
+// User control law: (time, attitude, position and velocity relative to the primary)
+// -> torque in the body frame, N m
+pub type ControlTorque = Rc<dyn Fn(f64, &AttitudeState, &Vector3, &Vector3) -> Vector3>;
+
+const MAX_SUBSTEP: f64 = 1.0;              // s, upper bound on the attitude integration step
+const MAX_ROTATION_PER_SUBSTEP: f64 = 0.01;  // rad, keeps fast spinners accurate
+
+#[derive(Clone, Debug)]
+pub struct AttitudeState {
+    pub orientation: Quaternion,        // body frame to inertial frame
+    pub angular_velocity: Vector3,      // rad/s, body frame
+    pub inertia: Matrix3,               // kg m^2, body frame
+}
+
+// Inputs that stay fixed while the attitude is propagated over one orbit step
+pub struct AttitudeEnvironment<'a> {
+    pub primary_mu: f64,                // G M of the body being orbited, 0 for none
+    pub position: &'a Vector3,          // relative to the primary at the start of the step
+    pub velocity: &'a Vector3,
+    pub control: Option<&'a ControlTorque>,
+}
+
+impl AttitudeState {
+    pub fn new(orientation: Quaternion, angular_velocity: Vector3, inertia: Matrix3) -> Self {
+        AttitudeState { orientation: orientation.norm(), angular_velocity, inertia }
+    }
+
+    pub fn angular_momentum(&self) -> Vector3 {
+        self.orientation.rotate(&self.inertia.multiply_vector(&self.angular_velocity))
+    }
+
+    // Gravity-gradient torque in the body frame for a primary at `position` (inertial,
+    // from the primary to the spacecraft)
+    pub fn gravity_gradient_torque(&self, primary_mu: f64, position: &Vector3) -> Vector3 {
+        let distance = position.magnitude();
+        if primary_mu <= 0.0 || distance == 0.0 {
+            return Vector3::new(0.0, 0.0, 0.0);
+        }
+        let direction = self.orientation.conjugate().rotate(&position.scale(1.0 / distance));
+        direction.cross(&self.inertia.multiply_vector(&direction)).scale(3.0 * primary_mu / distance.powi(3))
+    }
+
+    // Propagates Euler's equations and the quaternion kinematics with RK4 from `time`
+    // over `timestep`, following the orbit as a two-body conic about the primary
+    pub fn propagate(&mut self, time: f64, timestep: f64, environment: &AttitudeEnvironment) {
+        let rate = self.angular_velocity.magnitude();
+        let max_substep = if rate > 0.0 { (MAX_ROTATION_PER_SUBSTEP / rate).min(MAX_SUBSTEP) } else { MAX_SUBSTEP };
+        let substeps = (timestep / max_substep).ceil().max(1.0) as usize;
+        let h = timestep / substeps as f64;
+        let inverse_inertia = self.inertia.inverse();
+
+        for n in 0..substeps {
+            let t = time + n as f64 * h;
+            let derivative = |state: &AttitudeState, offset: f64| -> (Quaternion, Vector3) {
+                let (position, velocity) = orbit::propagate_kepler(environment.position, environment.velocity, environment.primary_mu, n as f64 * h + offset);
+                let mut torque = state.gravity_gradient_torque(environment.primary_mu, &position);
+                if let Some(control) = environment.control {
+                    torque = torque.add(&control(t + offset, state, &position, &velocity));
+                }
+
+                let omega = &state.angular_velocity;
+                let gyroscopic = omega.cross(&state.inertia.multiply_vector(omega));
+                let angular_acceleration = inverse_inertia.multiply_vector(&torque.subtract(&gyroscopic));
+                let quaternion_rate = state.orientation.multiply(&Quaternion::new(0.0, omega.x, omega.y, omega.z)).scale(0.5);
+                (quaternion_rate, angular_acceleration)
+            };
+
+            let k1 = derivative(self, 0.0);
+            let k2 = derivative(&self.advanced(&k1, h / 2.0), h / 2.0);
+            let k3 = derivative(&self.advanced(&k2, h / 2.0), h / 2.0);
+            let k4 = derivative(&self.advanced(&k3, h), h);
+
+            self.orientation = self.orientation
+                .add(&k1.0.add(&k2.0.scale(2.0)).add(&k3.0.scale(2.0)).add(&k4.0).scale(h / 6.0))
+                .norm();
+            self.angular_velocity = self.angular_velocity
+                .add(&k1.1.add(&k2.1.scale(2.0)).add(&k3.1.scale(2.0)).add(&k4.1).scale(h / 6.0));
+        }
+    }
+
+    fn advanced(&self, derivative: &(Quaternion, Vector3), timestep: f64) -> AttitudeState {
+        AttitudeState {
+            orientation: self.orientation.add(&derivative.0.scale(timestep)).norm(),
+            angular_velocity: self.angular_velocity.add(&derivative.1.scale(timestep)),
+            inertia: self.inertia.clone(),
+        }
+    }
+}
@@ -1,29 +1,156 @@
+use crate::attitude::{AttitudeState, ControlTorque};
 use crate::geometry;
+use crate::mission;
+use crate::orbit;
+use crate::rotation::RotationModel;
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub enum BodyType {
     Star,
     Planet,
     Moon,
     Satellite,
+    Asteroid,
+    Comet,
+    Dust,
 }
 
+#[derive(Clone)]
 pub struct CelestialBody {
+    pub id: usize,              // assigned by the solar system, stable while the body list changes
     pub name: String,
     pub body_type: BodyType,
     pub position: geometry::Vector3,
     pub km_radius: f64,
     pub mass: f64,
     pub velocity: geometry::Vector3,
-    pub color: [f32; 3]
+    pub color: [f32; 3],
+    pub area_to_mass: f64,      // m^2/kg, illuminated cross-section over mass
+    pub reflectivity: f64,      // radiation pressure coefficient Cr (1 = absorbing, 2 = mirror)
+    pub burns: Vec<mission::ImpulsiveBurn>,
+    pub thrust_arcs: Vec<mission::ThrustArc>,
+    pub dry_mass: f64,          // kg, thrusting stops once the mass drops to this; 0 means no propellant
+    pub love_number: f64,       // k2, 0 for a rigid body
+    pub tidal_q: f64,           // tidal quality factor Q
+    pub inertia_factor: f64,    // polar moment of inertia over M R^2, 0.4 for a uniform sphere
+    pub nongravitational: [f64; 3],     // Marsden A1, A2, A3 in AU/day^2
+    pub beta: f64,              // radiation pressure to stellar gravity ratio
+    pub rotation: Option<RotationModel>,
+    pub attitude: Option<AttitudeState>,
+    pub attitude_control: Option<ControlTorque>,
+    pub covariance: Option<geometry::Matrix>,   // 6x6 state uncertainty, carried along by the simulation
+    pub sgp4: Option<orbit::Sgp4>,     // element set that replaces the integrated state around Earth
 }
 
 impl CelestialBody {
     pub fn new(name: String, body_type: BodyType, position: geometry::Vector3, km_radius: f64, mass: f64, velocity: geometry::Vector3, color: [f32; 3]) -> CelestialBody {
-        CelestialBody {name, body_type, position, km_radius, mass, velocity, color}
+        CelestialBody {
+            id: 0,
+            name, body_type, position, km_radius, mass, velocity, color,
+            area_to_mass: 0.0,
+            reflectivity: 1.0,
+            burns: Vec::new(),
+            thrust_arcs: Vec::new(),
+            dry_mass: 0.0,
+            love_number: 0.0,
+            tidal_q: 0.0,
+            inertia_factor: 0.4,
+            nongravitational: [0.0; 3],
+            beta: 0.0,
+            rotation: None,
+            attitude: None,
+            attitude_control: None,
+            covariance: None,
+            sgp4: None,
+        }
+    }
+
+    pub fn with_radiation_pressure(mut self, area_to_mass: f64, reflectivity: f64) -> CelestialBody {
+        self.area_to_mass = area_to_mass;
+        self.reflectivity = reflectivity;
+        self
+    }
+
+    pub fn schedule_burn(&mut self, burn: mission::ImpulsiveBurn) {
+        self.burns.push(burn);
+    }
+
+    pub fn with_dry_mass(mut self, dry_mass: f64) -> CelestialBody {
+        self.dry_mass = dry_mass;
+        self
+    }
+
+    pub fn with_tides(mut self, love_number: f64, tidal_q: f64) -> CelestialBody {
+        self.love_number = love_number;
+        self.tidal_q = tidal_q;
+        self
+    }
+
+    pub fn with_inertia_factor(mut self, inertia_factor: f64) -> CelestialBody {
+        self.inertia_factor = inertia_factor;
+        self
+    }
+
+    pub fn with_nongravitational(mut self, a1: f64, a2: f64, a3: f64) -> CelestialBody {
+        self.nongravitational = [a1, a2, a3];
+        self
+    }
+
+    pub fn with_beta(mut self, beta: f64) -> CelestialBody {
+        self.beta = beta;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: RotationModel) -> CelestialBody {
+        self.rotation = Some(rotation);
+        self
+    }
+
+    pub fn with_attitude(mut self, attitude: AttitudeState) -> CelestialBody {
+        self.attitude = Some(attitude);
+        self
+    }
+
+    pub fn with_attitude_control(mut self, control: ControlTorque) -> CelestialBody {
+        self.attitude_control = Some(control);
+        self
+    }
+
+    pub fn with_covariance(mut self, covariance: geometry::Matrix) -> CelestialBody {
+        self.covariance = Some(covariance);
+        self
+    }
+
+    pub fn with_sgp4(mut self, sgp4: orbit::Sgp4) -> CelestialBody {
+        self.sgp4 = Some(sgp4);
+        self
+    }
+
+    // Thrust needs propellant, so the arc is refused (returning false) unless a positive
+    // dry mass below the current mass has been set
+    pub fn schedule_thrust_arc(&mut self, arc: mission::ThrustArc) -> bool {
+        if self.dry_mass <= 0.0 || self.dry_mass >= self.mass {
+            return false;
+        }
+        self.thrust_arcs.push(arc);
+        true
     }
 
     pub fn calculate_display_size(&self) -> f32 {
         ((self.km_radius.ln() - 10.0) / 2.0) as f32
     }
-}
\ No newline at end of file
+}
+
+// The body exerting the strongest gravitational pull on `bodies[index]`, i.e. the one it orbits
+pub fn find_primary(bodies: &[CelestialBody], index: usize) -> Option<usize> {
+    let target = &bodies[index];
+    bodies.iter()
+        .enumerate()
+        .filter(|(i, body)| *i != index && body.mass > 0.0)
+        .map(|(i, body)| {
+            let distance = body.position.subtract(&target.position);
+            (i, body.mass / distance.dot(&distance))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i)
+}
@@ -0,0 +1,179 @@
+use std::f64::consts::PI;
+use std::fmt;
+use crate::body::{BodyType, CelestialBody};
+use crate::geometry::Vector3;
+use crate::physics;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CollisionPolicy {
+    Ignore,
+    Merge,
+    Bounce { restitution: f64 },    // 1 = elastic, 0 = perfectly inelastic
+    Fragment { count: usize },      // the smaller body shatters into `count` pieces
+}
+
+#[derive(Clone, Debug)]
+pub struct CollisionEvent {
+    pub time: f64,
+    pub first: String,
+    pub second: String,
+    pub policy: CollisionPolicy,
+    pub position: Vector3,
+    pub relative_speed: f64,        // m/s
+}
+
+impl fmt::Display for CollisionEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let outcome = match self.policy {
+            CollisionPolicy::Ignore => "passed through",
+            CollisionPolicy::Merge => "merged",
+            CollisionPolicy::Bounce { .. } => "bounced",
+            CollisionPolicy::Fragment { .. } => "fragmented",
+        };
+        write!(f, "t={:.0} s: {} and {} {} at {:.1} m/s", self.time, self.first, self.second, outcome, self.relative_speed)
+    }
+}
+
+// First pair of bodies whose spheres touched while moving (linearly) from
+// `start_positions` to their current positions
+pub fn find_collision(bodies: &[CelestialBody], start_positions: &[Vector3]) -> Option<(usize, usize)> {
+    // Point particles such as dust never collide
+    let solid: Vec<usize> = (0..bodies.len()).filter(|&i| bodies[i].km_radius > 0.0).collect();
+
+    for (n, &i) in solid.iter().enumerate() {
+        for &j in &solid[n + 1..] {
+            let contact_distance = (bodies[i].km_radius + bodies[j].km_radius) * 1000.0;
+            if contact_distance <= 0.0 {
+                continue;
+            }
+
+            let start = start_positions[j].subtract(&start_positions[i]);
+            let end = bodies[j].position.subtract(&bodies[i].position);
+            if closest_approach(&start, &end) < contact_distance {
+                return Some((i, j));
+            }
+        }
+    }
+    None
+}
+
+fn closest_approach(start: &Vector3, end: &Vector3) -> f64 {
+    let motion = end.subtract(start);
+    let motion_squared = motion.dot(&motion);
+    if motion_squared == 0.0 {
+        return start.magnitude();
+    }
+
+    let s = (-start.dot(&motion) / motion_squared).clamp(0.0, 1.0);
+    start.add(&motion.scale(s)).magnitude()
+}
+
+// Combines `other` into `body` conserving mass and momentum. The survivor keeps
+// the identity of the more massive body and the merged radius its bulk density.
+pub fn merge(body: &mut CelestialBody, other: CelestialBody) {
+    let total_mass = body.mass + other.mass;
+    let (larger, smaller) = if other.mass > body.mass { (other, body.clone()) } else { (body.clone(), other) };
+
+    let (position, velocity) = if total_mass > 0.0 {
+        (
+            larger.position.scale(larger.mass).add(&smaller.position.scale(smaller.mass)).scale(1.0 / total_mass),
+            larger.velocity.scale(larger.mass).add(&smaller.velocity.scale(smaller.mass)).scale(1.0 / total_mass),
+        )
+    } else {
+        (larger.position.clone(), larger.velocity.clone())
+    };
+
+    let km_radius = if larger.mass > 0.0 {
+        let density = larger.mass / sphere_volume(larger.km_radius);
+        (3.0 * total_mass / (4.0 * PI * density)).cbrt() / 1000.0
+    } else {
+        (larger.km_radius.powi(3) + smaller.km_radius.powi(3)).cbrt()
+    };
+
+    *body = CelestialBody { position, velocity, km_radius, mass: total_mass, ..larger };
+}
+
+// Reflects the normal component of the relative velocity, scaled by the restitution
+// coefficient, and pushes the bodies apart so they just touch
+pub fn bounce(body: &mut CelestialBody, other: &mut CelestialBody, restitution: f64) {
+    let total_mass = body.mass + other.mass;
+    if total_mass <= 0.0 {
+        return;
+    }
+
+    let normal = other.position.subtract(&body.position).norm();
+    let approach_speed = other.velocity.subtract(&body.velocity).dot(&normal);
+    if approach_speed < 0.0 {
+        let impulse = (1.0 + restitution) * approach_speed / total_mass;
+        body.velocity = body.velocity.add(&normal.scale(impulse * other.mass));
+        other.velocity = other.velocity.subtract(&normal.scale(impulse * body.mass));
+    }
+
+    let centre = body.position.scale(body.mass).add(&other.position.scale(other.mass)).scale(1.0 / total_mass);
+    let contact_distance = (body.km_radius + other.km_radius) * 1000.0;
+    body.position = centre.subtract(&normal.scale(contact_distance * other.mass / total_mass));
+    other.position = centre.add(&normal.scale(contact_distance * body.mass / total_mass));
+}
+
+// Shatters the smaller of the two bodies into `count` equal fragments thrown out at
+// the pair's escape speed. The larger body recoils so that momentum is conserved.
+pub fn fragment(body: &mut CelestialBody, other: CelestialBody, count: usize) -> Vec<CelestialBody> {
+    let (mut larger, smaller) = if other.mass > body.mass { (other, body.clone()) } else { (body.clone(), other) };
+    let count = count.max(1);
+    let total_mass = larger.mass + smaller.mass;
+    let momentum = larger.velocity.scale(larger.mass).add(&smaller.velocity.scale(smaller.mass));
+    let centre_velocity = if total_mass > 0.0 { momentum.scale(1.0 / total_mass) } else { larger.velocity.clone() };
+
+    let normal = smaller.position.subtract(&larger.position).norm();
+    let (u, w) = perpendicular_basis(&normal);
+    let fragment_mass = smaller.mass / count as f64;
+    let fragment_radius = smaller.km_radius / (count as f64).cbrt();
+    let escape_speed = (2.0 * physics::GRAVITATIONAL_CONST * total_mass / ((larger.km_radius + smaller.km_radius) * 1000.0)).sqrt();
+
+    // Fragments leave on a cone about the impact normal. The shell they start on is
+    // wide enough that they clear the larger body and, around the cone, each other.
+    let cone = 0.5_f64.atan();
+    let clear_of_larger = (larger.km_radius + fragment_radius) * 1000.0 * 1.01;
+    let clear_of_neighbours = if count > 1 {
+        fragment_radius * 1000.0 * 1.01 / (cone.sin() * (PI / count as f64).sin())
+    } else {
+        0.0
+    };
+    let offset = clear_of_larger.max(clear_of_neighbours);
+
+    let mut fragments = Vec::with_capacity(count);
+    let mut fragment_momentum = Vector3::new(0.0, 0.0, 0.0);
+    for k in 0..count {
+        let angle = 2.0 * PI * k as f64 / count as f64;
+        let direction = normal.add(&u.scale(cone.tan() * angle.cos())).add(&w.scale(cone.tan() * angle.sin())).norm();
+        let velocity = centre_velocity.add(&direction.scale(escape_speed));
+        fragment_momentum = fragment_momentum.add(&velocity.scale(fragment_mass));
+
+        fragments.push(CelestialBody::new(
+            format!("{} fragment {}", smaller.name, k + 1),
+            BodyType::Asteroid,
+            larger.position.add(&direction.scale(offset)),
+            fragment_radius,
+            fragment_mass,
+            velocity,
+            smaller.color,
+        ));
+    }
+
+    if larger.mass > 0.0 {
+        larger.velocity = momentum.subtract(&fragment_momentum).scale(1.0 / larger.mass);
+    }
+    *body = larger;
+    fragments
+}
+
+fn sphere_volume(km_radius: f64) -> f64 {
+    4.0 / 3.0 * PI * (km_radius * 1000.0).powi(3)
+}
+
+fn perpendicular_basis(normal: &Vector3) -> (Vector3, Vector3) {
+    let helper = if normal.x.abs() < 0.9 { Vector3::new(1.0, 0.0, 0.0) } else { Vector3::new(0.0, 1.0, 0.0) };
+    let u = normal.cross(&helper).norm();
+    let w = normal.cross(&u);
+    (u, w)
+}
@@ -0,0 +1,111 @@
+use crate::geometry::{Matrix, Vector3};
+use super::ObservationKind;
+
+#[derive(Clone, Debug)]
+pub struct BatchOptions {
+    pub max_iterations: usize,
+    pub tolerance: f64,                 // relative change of the weighted RMS that ends the fit
+    pub a_priori_covariance: Option<Matrix>,    // 6x6, of the initial guess
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BatchOptions {
+    pub fn new() -> Self {
+        BatchOptions { max_iterations: 10, tolerance: 1e-4, a_priori_covariance: None }
+    }
+
+    pub fn with_a_priori(mut self, covariance: Matrix) -> Self {
+        self.a_priori_covariance = Some(covariance);
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Residual {
+    pub time: f64,
+    pub kind: ObservationKind,
+    pub values: Vec<f64>,       // observed minus computed
+    pub normalized: Vec<f64>,   // divided by the observation sigmas
+}
+
+#[derive(Clone, Debug)]
+pub struct BatchSolution {
+    pub epoch: f64,
+    pub position: Vector3,
+    pub velocity: Vector3,
+    pub covariance: Matrix,     // 6x6, position then velocity
+    pub residuals: Vec<Residual>,
+    pub rms: f64,               // of the normalized residuals
+    pub iterations: usize,
+    pub converged: bool,
+}
+
+impl BatchSolution {
+    // One-sigma uncertainties of the six state components
+    pub fn sigmas(&self) -> [f64; 6] {
+        let mut sigmas = [0.0; 6];
+        for (n, sigma) in sigmas.iter_mut().enumerate() {
+            *sigma = self.covariance.rows[n][n].max(0.0).sqrt();
+        }
+        sigmas
+    }
+
+    pub fn position_uncertainty(&self) -> f64 {
+        let sigmas = self.sigmas();
+        (sigmas[0] * sigmas[0] + sigmas[1] * sigmas[1] + sigmas[2] * sigmas[2]).sqrt()
+    }
+}
+
+// Weighted normal equations (H^T W H) dx = H^T W y of a linearised fit, accumulated one
+// measurement at a time
+#[derive(Clone, Debug)]
+pub struct NormalEquations {
+    pub information: Matrix,
+    pub right_hand_side: Vec<f64>,
+}
+
+impl NormalEquations {
+    pub fn new(size: usize) -> Self {
+        NormalEquations { information: Matrix::zeros(size, size), right_hand_side: vec![0.0; size] }
+    }
+
+    // Prior knowledge that the state deviation is `deviation` with `covariance`
+    pub fn add_a_priori(&mut self, covariance: &Matrix, deviation: &[f64]) -> Option<()> {
+        let information = covariance.inverse()?;
+        let weighted = information.multiply_vector(deviation);
+        self.information = self.information.add(&information);
+        self.right_hand_side.iter_mut().zip(weighted).for_each(|(total, value)| *total += value);
+        Some(())
+    }
+
+    pub fn add(&mut self, partials: &[f64], residual: f64, sigma: f64) {
+        let weight = 1.0 / (sigma * sigma);
+        for (i, a) in partials.iter().enumerate() {
+            self.right_hand_side[i] += a * weight * residual;
+            for (j, b) in partials.iter().enumerate() {
+                self.information.rows[i][j] += a * weight * b;
+            }
+        }
+    }
+
+    // State correction and its covariance, `None` when the observations do not determine
+    // every component
+    pub fn solve(&self) -> Option<(Vec<f64>, Matrix)> {
+        // Position and velocity partials differ by orders of magnitude, so the matrix is
+        // equilibrated by its diagonal before inverting
+        let scales: Vec<f64> = (0..self.right_hand_side.len())
+            .map(|n| {
+                let diagonal = self.information.rows[n][n];
+                if diagonal > 0.0 { 1.0 / diagonal.sqrt() } else { 1.0 }
+            })
+            .collect();
+        let scaling = Matrix::diagonal(&scales);
+        let covariance = scaling.multiply(&scaling.multiply(&self.information).multiply(&scaling).inverse()?).multiply(&scaling);
+        Some((covariance.multiply_vector(&self.right_hand_side), covariance))
+    }
+}
@@ -0,0 +1,119 @@
+use crate::geometry::Matrix;
+use super::ObservationKind;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FilterKind {
+    Extended,
+    // Scaled unscented transform with 2n + 1 sigma points
+    Unscented { alpha: f64, beta: f64, kappa: f64 },
+}
+
+impl FilterKind {
+    pub fn unscented() -> Self {
+        FilterKind::Unscented { alpha: 1.0, beta: 2.0, kappa: 0.0 }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FilterOptions {
+    pub kind: FilterKind,
+    pub process_noise: f64,         // m^2/s^3, spectral density of a white-noise acceleration
+    pub rejection_threshold: f64,   // innovation sigmas beyond which a measurement is edited out
+}
+
+impl FilterOptions {
+    pub fn new(kind: FilterKind) -> Self {
+        FilterOptions { kind, process_noise: 0.0, rejection_threshold: 5.0 }
+    }
+
+    pub fn with_process_noise(mut self, spectral_density: f64) -> Self {
+        self.process_noise = spectral_density;
+        self
+    }
+}
+
+// Outcome of one measurement update
+#[derive(Clone, Debug)]
+pub struct FilterUpdate {
+    pub time: f64,
+    pub kind: ObservationKind,
+    pub prefit_residuals: Vec<f64>,     // observed minus predicted
+    pub postfit_residuals: Vec<f64>,    // observed minus updated, equal to prefit if rejected
+    pub innovation_sigmas: Vec<f64>,    // square roots of the innovation covariance diagonal
+    pub accepted: bool,
+    pub position_sigma: f64,            // m, root sum square after the update
+}
+
+// Covariance added over `interval` by a white-noise acceleration of `spectral_density`
+// in every axis
+pub fn process_noise(spectral_density: f64, interval: f64) -> Matrix {
+    let interval = interval.abs();
+    let mut noise = Matrix::zeros(6, 6);
+    for k in 0..3 {
+        noise.rows[k][k] = spectral_density * interval.powi(3) / 3.0;
+        noise.rows[k][k + 3] = spectral_density * interval.powi(2) / 2.0;
+        noise.rows[k + 3][k] = spectral_density * interval.powi(2) / 2.0;
+        noise.rows[k + 3][k + 3] = spectral_density * interval;
+    }
+    noise
+}
+
+// Kalman gain from the cross covariance between state and measurement and the
+// innovation covariance
+pub fn kalman_gain(cross_covariance: &Matrix, innovation_covariance: &Matrix) -> Option<Matrix> {
+    Some(cross_covariance.multiply(&innovation_covariance.inverse()?))
+}
+
+// Linearised update in Joseph form, which keeps the covariance symmetric and positive
+// definite: returns the Kalman gain, the updated covariance and the innovation covariance
+pub fn extended_update(covariance: &Matrix, partials: &Matrix, noise: &Matrix) -> Option<(Matrix, Matrix, Matrix)> {
+    let cross_covariance = covariance.multiply(&partials.transpose());
+    let innovation_covariance = partials.multiply(&cross_covariance).add(noise);
+    let gain = kalman_gain(&cross_covariance, &innovation_covariance)?;
+
+    let reduction = Matrix::identity(covariance.row_count()).subtract(&gain.multiply(partials));
+    let updated = reduction.multiply(covariance).multiply(&reduction.transpose())
+        .add(&gain.multiply(noise).multiply(&gain.transpose()));
+    Some((gain, updated, innovation_covariance))
+}
+
+#[derive(Clone, Debug)]
+pub struct SigmaPoints {
+    pub points: Vec<Vec<f64>>,
+    pub mean_weights: Vec<f64>,
+    pub covariance_weights: Vec<f64>,
+}
+
+// Sigma points of the scaled unscented transform about `mean` with `covariance`
+pub fn sigma_points(mean: &[f64], covariance: &Matrix, alpha: f64, beta: f64, kappa: f64) -> Option<SigmaPoints> {
+    let size = mean.len() as f64;
+    let lambda = alpha * alpha * (size + kappa) - size;
+    let root = covariance.scale(size + lambda).cholesky()?;
+
+    let mut points = vec![mean.to_vec()];
+    for sign in [1.0, -1.0] {
+        for column in 0..mean.len() {
+            points.push(mean.iter().enumerate().map(|(row, value)| value + sign * root.rows[row][column]).collect());
+        }
+    }
+
+    let weight = 1.0 / (2.0 * (size + lambda));
+    let mut mean_weights = vec![weight; points.len()];
+    let mut covariance_weights = mean_weights.clone();
+    mean_weights[0] = lambda / (size + lambda);
+    covariance_weights[0] = mean_weights[0] + 1.0 - alpha * alpha + beta;
+    Some(SigmaPoints { points, mean_weights, covariance_weights })
+}
+
+// Weighted covariance between two sets of deviations from their means
+pub fn weighted_covariance(a: &[Vec<f64>], b: &[Vec<f64>], weights: &[f64]) -> Matrix {
+    let mut covariance = Matrix::zeros(a[0].len(), b[0].len());
+    for ((a, b), weight) in a.iter().zip(b).zip(weights) {
+        for (row, x) in covariance.rows.iter_mut().zip(a) {
+            for (value, y) in row.iter_mut().zip(b) {
+                *value += weight * x * y;
+            }
+        }
+    }
+    covariance
+}
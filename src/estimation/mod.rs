@@ -0,0 +1,7 @@
+mod observation;
+mod batch;
+mod filter;
+
+pub use self::observation::{observations_to_csv, parse_observations, read_observations, write_observations, Observation, ObservationKind, Observer, Station};
+pub use self::batch::{BatchOptions, BatchSolution, NormalEquations, Residual};
+pub use self::filter::{extended_update, kalman_gain, process_noise, sigma_points, weighted_covariance, FilterKind, FilterOptions, FilterUpdate, SigmaPoints};
@@ -0,0 +1,205 @@
+use std::f64::consts::PI;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use crate::geometry::{self, Vector3};
+
+// Tracking site fixed to the surface of a body
+#[derive(Clone, Debug)]
+pub struct Station {
+    pub name: String,
+    pub body: usize,            // id of the body it stands on
+    pub position: Vector3,      // m, body-fixed
+}
+
+impl Station {
+    pub fn new(name: &str, body: usize, position: Vector3) -> Self {
+        Station { name: name.to_string(), body, position }
+    }
+
+    // Site at planetocentric `latitude` and `longitude` (radians) and `altitude` above a
+    // sphere of `radius` (m)
+    pub fn from_spherical(name: &str, body: usize, radius: f64, latitude: f64, longitude: f64, altitude: f64) -> Self {
+        let distance = radius + altitude;
+        Station::new(name, body, Vector3::new(
+            distance * latitude.cos() * longitude.cos(),
+            distance * latitude.cos() * longitude.sin(),
+            distance * latitude.sin(),
+        ))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Observer {
+    Station(Station),
+    Body(usize),        // id, observing from the centre of the body
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ObservationKind {
+    Range,                      // m
+    RangeRate,                  // m/s
+    RightAscensionDeclination,  // rad, J2000 equator
+    Position,                   // m, simulation frame, like a GNSS fix relative to the observer
+}
+
+impl ObservationKind {
+    pub fn dimension(&self) -> usize {
+        match self {
+            ObservationKind::RightAscensionDeclination => 2,
+            ObservationKind::Position => 3,
+            _ => 1,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ObservationKind::Range => "range",
+            ObservationKind::RangeRate => "range_rate",
+            ObservationKind::RightAscensionDeclination => "ra_dec",
+            ObservationKind::Position => "position",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "range" => Some(ObservationKind::Range),
+            "range_rate" => Some(ObservationKind::RangeRate),
+            "ra_dec" => Some(ObservationKind::RightAscensionDeclination),
+            "position" => Some(ObservationKind::Position),
+            _ => None,
+        }
+    }
+
+    // Modelled values for a target at `position` and `velocity` relative to the observer,
+    // geometric (no light time or aberration), with their partials with respect to the
+    // target's position and velocity
+    pub fn model(&self, position: &Vector3, velocity: &Vector3) -> (Vec<f64>, Vec<[f64; 6]>) {
+        let range = position.magnitude();
+        let direction = position.scale(1.0 / range);
+        let row = |position_partial: &Vector3, velocity_partial: &Vector3| [
+            position_partial.x, position_partial.y, position_partial.z,
+            velocity_partial.x, velocity_partial.y, velocity_partial.z,
+        ];
+        let zero = Vector3::new(0.0, 0.0, 0.0);
+
+        match self {
+            ObservationKind::Range => (vec![range], vec![row(&direction, &zero)]),
+            ObservationKind::Position => (
+                vec![position.x, position.y, position.z],
+                vec![
+                    row(&Vector3::new(1.0, 0.0, 0.0), &zero),
+                    row(&Vector3::new(0.0, 1.0, 0.0), &zero),
+                    row(&Vector3::new(0.0, 0.0, 1.0), &zero),
+                ],
+            ),
+            ObservationKind::RangeRate => {
+                let range_rate = direction.dot(velocity);
+                let position_partial = velocity.subtract(&direction.scale(range_rate)).scale(1.0 / range);
+                (vec![range_rate], vec![row(&position_partial, &direction)])
+            },
+            ObservationKind::RightAscensionDeclination => {
+                // Gradients are taken in the equatorial frame and rotated back
+                let equatorial = geometry::ecliptic_to_equatorial(position);
+                let (x, y, z) = (equatorial.x, equatorial.y, equatorial.z);
+                let projected_squared = x * x + y * y;
+                let projected = projected_squared.sqrt();
+                let right_ascension = y.atan2(x).rem_euclid(2.0 * PI);
+                let declination = (z / range).asin();
+
+                let ra_partial = Vector3::new(-y / projected_squared, x / projected_squared, 0.0);
+                let dec_partial = Vector3::new(-x * z, -y * z, projected_squared).scale(1.0 / (range * range * projected));
+                (
+                    vec![right_ascension, declination],
+                    vec![
+                        row(&geometry::equatorial_to_ecliptic(&ra_partial), &zero),
+                        row(&geometry::equatorial_to_ecliptic(&dec_partial), &zero),
+                    ],
+                )
+            },
+        }
+    }
+
+    // Observed minus computed, with right ascension differences wrapped to (-pi, pi]
+    pub fn residual(&self, observed: &[f64], computed: &[f64]) -> Vec<f64> {
+        observed.iter()
+            .zip(computed)
+            .enumerate()
+            .map(|(n, (o, c))| {
+                let difference = o - c;
+                if *self == ObservationKind::RightAscensionDeclination && n == 0 {
+                    PI - (PI - difference).rem_euclid(2.0 * PI)
+                } else {
+                    difference
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Observation {
+    pub time: f64,
+    pub kind: ObservationKind,
+    pub observer: Observer,
+    pub values: Vec<f64>,
+    pub sigmas: Vec<f64>,       // one standard deviation per value
+}
+
+impl Observation {
+    pub fn new(time: f64, kind: ObservationKind, observer: Observer, values: Vec<f64>, sigmas: Vec<f64>) -> Self {
+        Observation { time, kind, observer, values, sigmas }
+    }
+}
+
+// Tracking data as CSV, one observation per line:
+// time,kind,observer,values...,sigmas...
+// where kind is one of the `ObservationKind` names and the observer is a station name
+// or `body:<id>`. Lines starting with '#' are comments.
+pub fn observations_to_csv(observations: &[Observation]) -> String {
+    let mut csv = String::from("# time_s,kind,observer,values,sigmas\n");
+    for observation in observations {
+        let observer = match &observation.observer {
+            Observer::Station(station) => station.name.clone(),
+            Observer::Body(id) => format!("body:{}", id),
+        };
+        let _ = write!(csv, "{:.6},{},{}", observation.time, observation.kind.name(), observer);
+        for value in observation.values.iter().chain(&observation.sigmas) {
+            let _ = write!(csv, ",{:e}", value);
+        }
+        csv.push('\n');
+    }
+    csv
+}
+
+pub fn write_observations(path: &str, observations: &[Observation]) -> io::Result<()> {
+    fs::write(path, observations_to_csv(observations))
+}
+
+// Observations in the format of `observations_to_csv`, with station names looked up in
+// `stations`. Lines that do not parse, or name an unknown station, are skipped.
+pub fn parse_observations(text: &str, stations: &[Station]) -> Vec<Observation> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let time = fields.first()?.parse().ok()?;
+            let kind = ObservationKind::from_name(fields.get(1)?)?;
+            let observer = match fields.get(2)?.strip_prefix("body:") {
+                Some(id) => Observer::Body(id.parse().ok()?),
+                None => Observer::Station(stations.iter().find(|station| station.name == fields[2])?.clone()),
+            };
+            let numbers: Vec<f64> = fields[3..].iter().map(|field| field.parse().ok()).collect::<Option<_>>()?;
+            if numbers.len() != 2 * kind.dimension() {
+                return None;
+            }
+            let (values, sigmas) = numbers.split_at(kind.dimension());
+            Some(Observation::new(time, kind, observer, values.to_vec(), sigmas.to_vec()))
+        })
+        .collect()
+}
+
+pub fn read_observations(path: &str, stations: &[Station]) -> io::Result<Vec<Observation>> {
+    Ok(parse_observations(&fs::read_to_string(path)?, stations))
+}
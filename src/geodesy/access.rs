@@ -0,0 +1,141 @@
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use crate::time::{self, CalendarDate};
+
+// Interval during which a target stays above a station's elevation mask. Windows open
+// at the start of the search span or close at its end are cut off there.
+#[derive(Clone, Debug)]
+pub struct AccessWindow {
+    pub station: String,
+    pub target: String,
+    pub rise: f64,
+    pub set: f64,
+    pub max_elevation: f64,         // rad, highest sampled
+    pub max_elevation_time: f64,
+}
+
+impl AccessWindow {
+    pub fn duration(&self) -> f64 {
+        self.set - self.rise
+    }
+}
+
+fn utc_string(time: f64) -> String {
+    let date = time::utc_calendar_date(time);
+    format!("{}T{:02}:{:02}:{:02}Z", date.date_string(), date.hour, date.minute, date.second as u32)
+}
+
+fn icalendar_time(date: CalendarDate) -> String {
+    format!("{:04}{:02}{:02}T{:02}{:02}{:02}Z", date.year, date.month, date.day, date.hour, date.minute, date.second as u32)
+}
+
+// Text values escaped as iCalendar requires
+fn icalendar_text(text: &str) -> String {
+    text.replace('\\', "\\\\").replace(';', "\\;").replace(',', "\\,").replace('\n', "\\n")
+}
+
+// One line per window: station,target,rise_s,set_s,rise_utc,set_utc,duration_s,max_elevation_deg
+pub fn access_windows_to_csv(windows: &[AccessWindow]) -> String {
+    let mut csv = String::from("# station,target,rise_s,set_s,rise_utc,set_utc,duration_s,max_elevation_deg\n");
+    for window in windows {
+        let _ = writeln!(csv, "{},{},{:.3},{:.3},{},{},{:.1},{:.3}",
+            window.station.replace(',', " "), window.target.replace(',', " "), window.rise, window.set,
+            utc_string(window.rise), utc_string(window.set), window.duration(), window.max_elevation.to_degrees());
+    }
+    csv
+}
+
+// Appends one iCalendar content line, folded after every 75 octets onto continuation
+// lines starting with a space, without splitting a UTF-8 character
+fn push_content_line(calendar: &mut String, line: &str) {
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            calendar.push_str("\r\n ");
+            length = 1;
+        }
+        calendar.push(c);
+        length += c.len_utf8();
+    }
+    calendar.push_str("\r\n");
+}
+
+// iCalendar (RFC 5545) with one event per window, in UTC. DTSTAMP is the rise time so
+// that the same schedule always gives the same file.
+pub fn access_windows_to_icalendar(windows: &[AccessWindow]) -> String {
+    let mut calendar = String::new();
+    for line in ["BEGIN:VCALENDAR", "VERSION:2.0", "PRODID:-//satellite//access windows//EN"] {
+        push_content_line(&mut calendar, line);
+    }
+    for window in windows {
+        let rise = icalendar_time(time::utc_calendar_date(window.rise));
+        let uid: String = format!("{}-{}-{}", rise, window.station, window.target)
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+            .collect();
+        for line in [
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}@satellite", uid),
+            format!("DTSTAMP:{}", rise),
+            format!("DTSTART:{}", rise),
+            format!("DTEND:{}", icalendar_time(time::utc_calendar_date(window.set))),
+            format!("SUMMARY:{}", icalendar_text(&format!("{} pass over {}", window.target, window.station))),
+            format!("LOCATION:{}", icalendar_text(&window.station)),
+            format!("DESCRIPTION:{}", icalendar_text(&format!("Maximum elevation {:.1} deg at {}",
+                window.max_elevation.to_degrees(), utc_string(window.max_elevation_time)))),
+            "END:VEVENT".to_string(),
+        ] {
+            push_content_line(&mut calendar, &line);
+        }
+    }
+    push_content_line(&mut calendar, "END:VCALENDAR");
+    calendar
+}
+
+pub fn write_access_windows_csv(path: &str, windows: &[AccessWindow]) -> io::Result<()> {
+    fs::write(path, access_windows_to_csv(windows))
+}
+
+pub fn write_access_windows_icalendar(path: &str, windows: &[AccessWindow]) -> io::Result<()> {
+    fs::write(path, access_windows_to_icalendar(windows))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn icalendar_follows_rfc_5545() {
+        let date = CalendarDate { year: 2024, month: 3, day: 1, hour: 12, minute: 0, second: 0.0 };
+        let rise = time::from_utc_julian_date(time::to_julian_date(date.to_time()));
+        let window = AccessWindow {
+            station: "Goldstone; DSS-14, \\Mojave\\ Deep Space Communications Complex, California".to_string(),
+            target: "Sat\n1".to_string(),
+            rise,
+            set: rise + 600.0,
+            max_elevation: 0.5,
+            max_elevation_time: rise + 300.0,
+        };
+        let calendar = access_windows_to_icalendar(&[window]);
+
+        // Every line ends in CRLF, none is longer than 75 octets, and continuation lines
+        // start with a space
+        assert!(calendar.ends_with("END:VCALENDAR\r\n"));
+        assert!(!calendar.replace("\r\n", "").contains(['\r', '\n']));
+        let lines: Vec<&str> = calendar.trim_end_matches("\r\n").split("\r\n").collect();
+        assert!(lines.iter().all(|line| line.len() <= 75));
+        assert_eq!(lines[..3], ["BEGIN:VCALENDAR", "VERSION:2.0", "PRODID:-//satellite//access windows//EN"]);
+        assert!(lines.contains(&"DTSTART:20240301T120000Z"));
+        assert!(lines.contains(&"DTEND:20240301T121000Z"));
+
+        // Unfolded, the text values have their backslashes, semicolons, commas and
+        // newlines escaped
+        let unfolded = calendar.replace("\r\n ", "");
+        let location = "LOCATION:Goldstone\\; DSS-14\\, \\\\Mojave\\\\ Deep Space Communications Complex\\, California\r\n";
+        assert!(unfolded.contains(location));
+        assert!(lines.iter().any(|line| line.starts_with(' ')));
+        assert!(unfolded.contains("SUMMARY:Sat\\n1 pass over Goldstone\\; DSS-14\\, "));
+        assert!(unfolded.contains("DESCRIPTION:Maximum elevation 28.6 deg at 2024-03-01T12:05:00Z\r\n"));
+    }
+}
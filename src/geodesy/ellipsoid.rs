@@ -0,0 +1,116 @@
+use crate::geometry::Vector3;
+
+// Geodetic coordinates: latitude of the ellipsoid normal and longitude in radians,
+// east positive, and height above the ellipsoid in metres
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Geodetic {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: f64,
+}
+
+impl Geodetic {
+    pub fn new(latitude: f64, longitude: f64, altitude: f64) -> Self {
+        Geodetic { latitude, longitude, altitude }
+    }
+
+    pub fn from_degrees(latitude: f64, longitude: f64, altitude: f64) -> Self {
+        Geodetic::new(latitude.to_radians(), longitude.to_radians(), altitude)
+    }
+}
+
+// Oblate reference ellipsoid of revolution about the body-fixed z axis
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Ellipsoid {
+    pub equatorial_radius: f64,     // m
+    pub flattening: f64,
+}
+
+impl Ellipsoid {
+    pub fn new(equatorial_radius: f64, flattening: f64) -> Self {
+        Ellipsoid { equatorial_radius, flattening }
+    }
+
+    pub fn wgs84() -> Self {
+        Ellipsoid::new(6_378_137.0, 1.0 / 298.257_223_563)
+    }
+
+    pub fn polar_radius(&self) -> f64 {
+        self.equatorial_radius * (1.0 - self.flattening)
+    }
+
+    pub fn eccentricity_squared(&self) -> f64 {
+        self.flattening * (2.0 - self.flattening)
+    }
+
+    // Radius of curvature in the prime vertical at `latitude`
+    fn normal_radius(&self, latitude: f64) -> f64 {
+        self.equatorial_radius / (1.0 - self.eccentricity_squared() * latitude.sin().powi(2)).sqrt()
+    }
+
+    // Body-fixed position (m) of a point given geodetically
+    pub fn from_geodetic(&self, point: &Geodetic) -> Vector3 {
+        let normal = self.normal_radius(point.latitude);
+        let (sin_latitude, cos_latitude) = point.latitude.sin_cos();
+        let (sin_longitude, cos_longitude) = point.longitude.sin_cos();
+        Vector3::new(
+            (normal + point.altitude) * cos_latitude * cos_longitude,
+            (normal + point.altitude) * cos_latitude * sin_longitude,
+            (normal * (1.0 - self.eccentricity_squared()) + point.altitude) * sin_latitude,
+        )
+    }
+
+    // Geodetic coordinates of a body-fixed position, by fixed-point iteration on the
+    // latitude; the height formula stays well behaved over the poles
+    pub fn to_geodetic(&self, position: &Vector3) -> Geodetic {
+        let eccentricity_squared = self.eccentricity_squared();
+        let projected = (position.x * position.x + position.y * position.y).sqrt();
+        let longitude = position.y.atan2(position.x);
+
+        let mut latitude = position.z.atan2(projected * (1.0 - eccentricity_squared));
+        let mut altitude = 0.0;
+        for _ in 0..10 {
+            let normal = self.normal_radius(latitude);
+            let (sin_latitude, cos_latitude) = latitude.sin_cos();
+            altitude = projected * cos_latitude + (position.z + eccentricity_squared * normal * sin_latitude) * sin_latitude - normal;
+            let next = position.z.atan2(projected * (1.0 - eccentricity_squared * normal / (normal + altitude)));
+            let change = (next - latitude).abs();
+            latitude = next;
+            if change < 1e-13 {
+                break;
+            }
+        }
+        Geodetic::new(latitude, longitude, altitude)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn geodetic_round_trips() {
+        let wgs84 = Ellipsoid::wgs84();
+        for (latitude, longitude, altitude) in [
+            (0.0, 0.0, 0.0),
+            (0.0, -120.0, 35_786_000.0),
+            (45.0, 45.0, 400_000.0),
+            (-33.9, 151.2, 58.0),
+            (90.0, 0.0, 1000.0),
+            (-90.0, 0.0, 0.0),
+            (31.5, 35.5, -430.0),     // Dead Sea shore
+            (-60.0, -70.0, -5000.0),
+        ] {
+            let point = Geodetic::from_degrees(latitude, longitude, altitude);
+            let back = wgs84.to_geodetic(&wgs84.from_geodetic(&point));
+            assert!((back.latitude - point.latitude).abs() < 1e-12, "{latitude} {longitude} {altitude}");
+            assert!((back.longitude - point.longitude).abs() < 1e-12, "{latitude} {longitude} {altitude}");
+            assert!((back.altitude - point.altitude).abs() < 1e-6, "{latitude} {longitude} {altitude}");
+        }
+
+        // The poles lie on the axis at the polar radius
+        let north = wgs84.from_geodetic(&Geodetic::from_degrees(90.0, 0.0, 0.0));
+        assert!(north.x.abs() < 1e-6 && north.y.abs() < 1e-6);
+        assert!((north.z - 6_356_752.314_245).abs() < 1e-6);
+    }
+}
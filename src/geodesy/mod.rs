@@ -0,0 +1,9 @@
+mod ellipsoid;
+mod track;
+mod station;
+mod access;
+
+pub use self::ellipsoid::{Ellipsoid, Geodetic};
+pub use self::track::{ground_tracks_to_csv, ground_tracks_to_geojson, sub_satellite_point, write_ground_tracks_csv, write_ground_tracks_geojson, GroundPoint, GroundTrack};
+pub use self::station::{GroundStation, LookAngles};
+pub use self::access::{access_windows_to_csv, access_windows_to_icalendar, write_access_windows_csv, write_access_windows_icalendar, AccessWindow};
@@ -0,0 +1,103 @@
+use std::f64::consts::PI;
+use crate::estimation::Station;
+use crate::geometry::Vector3;
+use super::{Ellipsoid, Geodetic};
+
+// Topocentric direction and distance of a target as seen from a ground station.
+// Azimuth is measured from north through east.
+#[derive(Clone, Copy, Debug)]
+pub struct LookAngles {
+    pub azimuth: f64,       // rad, [0, 2pi)
+    pub elevation: f64,     // rad
+    pub range: f64,         // m
+    pub range_rate: f64,    // m/s, positive when receding
+}
+
+// Site on the reference ellipsoid of a body, WGS84 unless given otherwise
+#[derive(Clone, Debug)]
+pub struct GroundStation {
+    pub name: String,
+    pub body: usize,            // id of the body it stands on
+    pub location: Geodetic,
+    pub ellipsoid: Ellipsoid,
+    pub elevation_mask: f64,    // rad, lowest elevation at which a target counts as visible
+}
+
+impl GroundStation {
+    pub fn new(name: &str, body: usize, location: Geodetic) -> Self {
+        GroundStation { name: name.to_string(), body, location, ellipsoid: Ellipsoid::wgs84(), elevation_mask: 0.0 }
+    }
+
+    pub fn with_ellipsoid(mut self, ellipsoid: Ellipsoid) -> Self {
+        self.ellipsoid = ellipsoid;
+        self
+    }
+
+    pub fn with_elevation_mask(mut self, elevation_mask: f64) -> Self {
+        self.elevation_mask = elevation_mask;
+        self
+    }
+
+    // Body-fixed position, m
+    pub fn position(&self) -> Vector3 {
+        self.ellipsoid.from_geodetic(&self.location)
+    }
+
+    // The same site as a tracking station for observations
+    pub fn station(&self) -> Station {
+        Station::new(&self.name, self.body, self.position())
+    }
+
+    // East, north and up components of a body-fixed vector at the site
+    pub fn to_topocentric(&self, vector: &Vector3) -> Vector3 {
+        let (sin_latitude, cos_latitude) = self.location.latitude.sin_cos();
+        let (sin_longitude, cos_longitude) = self.location.longitude.sin_cos();
+        Vector3::new(
+            -sin_longitude * vector.x + cos_longitude * vector.y,
+            -sin_latitude * cos_longitude * vector.x - sin_latitude * sin_longitude * vector.y + cos_latitude * vector.z,
+            cos_latitude * cos_longitude * vector.x + cos_latitude * sin_longitude * vector.y + sin_latitude * vector.z,
+        )
+    }
+
+    // Look angles along a body-fixed line of sight from the site
+    pub fn look_angles(&self, line_of_sight: &Vector3, range_rate: f64) -> LookAngles {
+        let topocentric = self.to_topocentric(line_of_sight);
+        let range = topocentric.magnitude();
+        LookAngles {
+            azimuth: topocentric.x.atan2(topocentric.y).rem_euclid(2.0 * PI),
+            elevation: (topocentric.z / range).asin(),
+            range,
+            range_rate,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn look_angles_overhead_and_on_the_horizon() {
+        let station = GroundStation::new("Site", 0, Geodetic::from_degrees(35.0, -116.9, 1000.0));
+        let site = station.position();
+
+        // Straight up the ellipsoid normal
+        let above = station.ellipsoid.from_geodetic(&Geodetic::from_degrees(35.0, -116.9, 501_000.0));
+        let overhead = station.look_angles(&above.subtract(&site), 0.0);
+        assert!((overhead.elevation - PI / 2.0).abs() < 1e-9);
+        assert!((overhead.range - 500_000.0).abs() < 1e-6);
+
+        // Along the local east and north directions, 2000 km away
+        let (sin_latitude, cos_latitude) = station.location.latitude.sin_cos();
+        let (sin_longitude, cos_longitude) = station.location.longitude.sin_cos();
+        let east = Vector3::new(-sin_longitude, cos_longitude, 0.0);
+        let north = Vector3::new(-sin_latitude * cos_longitude, -sin_latitude * sin_longitude, cos_latitude);
+        for (direction, azimuth) in [(east.scale(1.0), PI / 2.0), (north.scale(1.0), 0.0), (east.scale(-1.0), 1.5 * PI), (north.scale(-1.0), PI)] {
+            let horizon = station.look_angles(&direction.scale(2.0e6), -150.0);
+            assert!(horizon.elevation.abs() < 1e-12);
+            assert!((horizon.azimuth - azimuth).abs() < 1e-12);
+            assert!((horizon.range - 2.0e6).abs() < 1e-6);
+            assert_eq!(horizon.range_rate, -150.0);
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use crate::geometry::Vector3;
+use crate::rotation::RotationModel;
+use super::{Ellipsoid, Geodetic};
+
+// Point on the WGS84 ellipsoid beneath a satellite at `position` relative to Earth's
+// centre in the simulation frame
+pub fn sub_satellite_point(position: &Vector3, rotation: &RotationModel, time: f64) -> Geodetic {
+    Ellipsoid::wgs84().to_geodetic(&rotation.to_body_fixed(position, time))
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GroundPoint {
+    pub time: f64,
+    pub point: Geodetic,
+}
+
+#[derive(Clone, Debug)]
+pub struct GroundTrack {
+    pub name: String,
+    pub points: Vec<GroundPoint>,
+}
+
+impl GroundTrack {
+    pub fn new(name: &str) -> Self {
+        GroundTrack { name: name.to_string(), points: Vec::new() }
+    }
+
+    // Track as [longitude, latitude, altitude] positions in degrees and metres, split
+    // where it crosses the antimeridian so that no segment wraps around the map. The
+    // crossing itself is interpolated onto both sides.
+    pub fn segments(&self) -> Vec<Vec<[f64; 3]>> {
+        let mut segments: Vec<Vec<[f64; 3]>> = Vec::new();
+        let mut current: Vec<[f64; 3]> = Vec::new();
+        for ground in &self.points {
+            let position = [ground.point.longitude.to_degrees(), ground.point.latitude.to_degrees(), ground.point.altitude];
+            if let Some(last) = current.last().copied() {
+                if (position[0] - last[0]).abs() > 180.0 {
+                    let side = last[0].signum() * 180.0;
+                    let unwrapped = position[0] + 2.0 * side;
+                    let fraction = (side - last[0]) / (unwrapped - last[0]);
+                    let latitude = last[1] + fraction * (position[1] - last[1]);
+                    let altitude = last[2] + fraction * (position[2] - last[2]);
+                    current.push([side, latitude, altitude]);
+                    segments.push(std::mem::take(&mut current));
+                    current.push([-side, latitude, altitude]);
+                }
+            }
+            current.push(position);
+        }
+        if !current.is_empty() {
+            segments.push(current);
+        }
+        segments
+    }
+
+    // GeoJSON feature with a MultiLineString geometry
+    pub fn to_geojson(&self) -> String {
+        let mut json = String::from("{\"type\":\"Feature\",\"properties\":{");
+        let _ = write!(json, "\"name\":\"{}\"", self.name.replace('\\', "\\\\").replace('"', "\\\""));
+        if let (Some(first), Some(last)) = (self.points.first(), self.points.last()) {
+            let _ = write!(json, ",\"start_time\":{},\"end_time\":{}", first.time, last.time);
+        }
+        json.push_str("},\"geometry\":{\"type\":\"MultiLineString\",\"coordinates\":[");
+        for (n, segment) in self.segments().iter().enumerate() {
+            json.push_str(if n == 0 { "[" } else { ",[" });
+            for (m, [longitude, latitude, altitude]) in segment.iter().enumerate() {
+                let _ = write!(json, "{}[{:.6},{:.6},{:.1}]", if m == 0 { "" } else { "," }, longitude, latitude, altitude);
+            }
+            json.push(']');
+        }
+        json.push_str("]}}");
+        json
+    }
+}
+
+// One line per sample: name,time_s,latitude_deg,longitude_deg,altitude_m
+pub fn ground_tracks_to_csv(tracks: &[GroundTrack]) -> String {
+    let mut csv = String::from("# name,time_s,latitude_deg,longitude_deg,altitude_m\n");
+    for track in tracks {
+        for ground in &track.points {
+            let _ = writeln!(csv, "{},{:.6},{:.6},{:.6},{:.1}", track.name.replace(',', " "), ground.time,
+                ground.point.latitude.to_degrees(), ground.point.longitude.to_degrees(), ground.point.altitude);
+        }
+    }
+    csv
+}
+
+// GeoJSON feature collection with one feature per track
+pub fn ground_tracks_to_geojson(tracks: &[GroundTrack]) -> String {
+    let features: Vec<String> = tracks.iter().map(GroundTrack::to_geojson).collect();
+    format!("{{\"type\":\"FeatureCollection\",\"features\":[{}]}}\n", features.join(","))
+}
+
+pub fn write_ground_tracks_csv(path: &str, tracks: &[GroundTrack]) -> io::Result<()> {
+    fs::write(path, ground_tracks_to_csv(tracks))
+}
+
+pub fn write_ground_tracks_geojson(path: &str, tracks: &[GroundTrack]) -> io::Result<()> {
+    fs::write(path, ground_tracks_to_geojson(tracks))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sub_satellite_point_matches_wgs84_reference() {
+        // ECEF (3194419.145, 3194419.145, 4487348.409) m is 45N 45E on the WGS84 ellipsoid
+        let earth = RotationModel::earth();
+        let time = 7.5e8;
+        let position = earth.from_body_fixed(&Vector3::new(3_194_419.145, 3_194_419.145, 4_487_348.409), time);
+        let point = sub_satellite_point(&position, &earth, time);
+        assert!((point.latitude.to_degrees() - 45.0).abs() < 1e-7);
+        assert!((point.longitude.to_degrees() - 45.0).abs() < 1e-7);
+        assert!(point.altitude.abs() < 1e-3);
+    }
+
+    #[test]
+    fn tracks_split_at_the_antimeridian() {
+        let mut track = GroundTrack::new("ISS");
+        for (time, latitude, longitude) in [(0.0, 0.0, 160.0), (60.0, 0.0, 170.0), (120.0, 10.0, -170.0), (180.0, 20.0, 170.0)] {
+            track.points.push(GroundPoint { time, point: Geodetic::from_degrees(latitude, longitude, 400_000.0) });
+        }
+
+        // Eastward across +180 and back westward across -180, each crossing interpolated
+        // half way onto both sides
+        let segments = track.segments();
+        assert_eq!(segments.len(), 3);
+        let approx = |a: [f64; 3], b: [f64; 3]| a.iter().zip(b).all(|(x, y)| (x - y).abs() < 1e-6);
+        assert!(approx(*segments[0].last().unwrap(), [180.0, 5.0, 400_000.0]));
+        assert!(approx(segments[1][0], [-180.0, 5.0, 400_000.0]));
+        assert!(approx(*segments[1].last().unwrap(), [-180.0, 15.0, 400_000.0]));
+        assert!(approx(segments[2][0], [180.0, 15.0, 400_000.0]));
+        for segment in &segments {
+            assert!(segment.windows(2).all(|pair| (pair[1][0] - pair[0][0]).abs() <= 180.0));
+        }
+
+        let json = track.to_geojson();
+        assert!(json.contains("\"coordinates\":[[[160.000000,0.000000,400000.0],[170.000000,0.000000,400000.0],[180.000000,5.000000,400000.0]],"));
+        assert!(json.contains("[[-180.000000,5.000000,400000.0],[-170.000000,10.000000,400000.0],[-180.000000,15.000000,400000.0]]"));
+        assert!(json.ends_with("[[180.000000,15.000000,400000.0],[170.000000,20.000000,400000.0]]]}}"));
+    }
+}
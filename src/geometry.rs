@@ -28,7 +28,7 @@ impl Vector3 {
 
     pub fn cross(&self, other: &Vector3) -> Vector3 {
         Vector3::new(self.y * other.z - self.z * other.y, 
-                     self.z * other.x - self.x - other.z, 
+                     self.z * other.x - self.x * other.z, 
                      self.x * other.y - self.y * other.x)
     }
 
@@ -46,3 +46,285 @@ impl Vector3 {
     }
 
 }
+
+pub const J2000_OBLIQUITY: f64 = 0.409_092_804_222_329;   // rad, mean obliquity of the ecliptic at J2000
+
+#[derive(Debug, Clone)]
+pub struct Matrix3 {
+    pub rows: [[f64; 3]; 3],
+}
+
+impl Matrix3 {
+    pub fn new(rows: [[f64; 3]; 3]) -> Self {
+        Matrix3 { rows }
+    }
+
+    pub fn identity() -> Self {
+        Matrix3::new([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]])
+    }
+
+    // Frame rotations: they express a vector in axes turned by `angle` about x, y or z
+    pub fn rotation_x(angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Matrix3::new([[1.0, 0.0, 0.0], [0.0, cos, sin], [0.0, -sin, cos]])
+    }
+
+    pub fn rotation_y(angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Matrix3::new([[cos, 0.0, -sin], [0.0, 1.0, 0.0], [sin, 0.0, cos]])
+    }
+
+    pub fn rotation_z(angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Matrix3::new([[cos, sin, 0.0], [-sin, cos, 0.0], [0.0, 0.0, 1.0]])
+    }
+
+    pub fn transpose(&self) -> Matrix3 {
+        let r = &self.rows;
+        Matrix3::new([
+            [r[0][0], r[1][0], r[2][0]],
+            [r[0][1], r[1][1], r[2][1]],
+            [r[0][2], r[1][2], r[2][2]],
+        ])
+    }
+
+    pub fn multiply(&self, other: &Matrix3) -> Matrix3 {
+        let mut rows = [[0.0; 3]; 3];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..3).map(|k| self.rows[i][k] * other.rows[k][j]).sum();
+            }
+        }
+        Matrix3::new(rows)
+    }
+
+    pub fn determinant(&self) -> f64 {
+        let r = &self.rows;
+        r[0][0] * (r[1][1] * r[2][2] - r[1][2] * r[2][1])
+            - r[0][1] * (r[1][0] * r[2][2] - r[1][2] * r[2][0])
+            + r[0][2] * (r[1][0] * r[2][1] - r[1][1] * r[2][0])
+    }
+
+    pub fn inverse(&self) -> Matrix3 {
+        let r = &self.rows;
+        let inverse_determinant = 1.0 / self.determinant();
+        let cofactor = |a: usize, b: usize, c: usize, d: usize| r[a][b] * r[c][d] - r[a][d] * r[c][b];
+        Matrix3::new([
+            [cofactor(1, 1, 2, 2), -cofactor(0, 1, 2, 2), cofactor(0, 1, 1, 2)],
+            [-cofactor(1, 0, 2, 2), cofactor(0, 0, 2, 2), -cofactor(0, 0, 1, 2)],
+            [cofactor(1, 0, 2, 1), -cofactor(0, 0, 2, 1), cofactor(0, 0, 1, 1)],
+        ]).scale(inverse_determinant)
+    }
+
+    pub fn scale(&self, scalar: f64) -> Matrix3 {
+        Matrix3::new(self.rows.map(|row| row.map(|value| value * scalar)))
+    }
+
+    pub fn multiply_vector(&self, vector: &Vector3) -> Vector3 {
+        let r = &self.rows;
+        Vector3::new(
+            r[0][0] * vector.x + r[0][1] * vector.y + r[0][2] * vector.z,
+            r[1][0] * vector.x + r[1][1] * vector.y + r[1][2] * vector.z,
+            r[2][0] * vector.x + r[2][1] * vector.y + r[2][2] * vector.z,
+        )
+    }
+}
+
+// Dense matrix of any size, for the estimation code
+#[derive(Debug, Clone)]
+pub struct Matrix {
+    pub rows: Vec<Vec<f64>>,
+}
+
+impl Matrix {
+    pub fn new(rows: Vec<Vec<f64>>) -> Self {
+        Matrix { rows }
+    }
+
+    pub fn zeros(rows: usize, columns: usize) -> Self {
+        Matrix::new(vec![vec![0.0; columns]; rows])
+    }
+
+    pub fn identity(size: usize) -> Self {
+        let mut matrix = Matrix::zeros(size, size);
+        for i in 0..size {
+            matrix.rows[i][i] = 1.0;
+        }
+        matrix
+    }
+
+    pub fn diagonal(values: &[f64]) -> Self {
+        let mut matrix = Matrix::zeros(values.len(), values.len());
+        for (i, &value) in values.iter().enumerate() {
+            matrix.rows[i][i] = value;
+        }
+        matrix
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn column_count(&self) -> usize {
+        self.rows.first().map_or(0, |row| row.len())
+    }
+
+    pub fn transpose(&self) -> Matrix {
+        Matrix::new((0..self.column_count()).map(|j| self.rows.iter().map(|row| row[j]).collect()).collect())
+    }
+
+    pub fn multiply(&self, other: &Matrix) -> Matrix {
+        let columns = other.column_count();
+        Matrix::new(self.rows.iter()
+            .map(|row| (0..columns).map(|j| row.iter().zip(&other.rows).map(|(a, other_row)| a * other_row[j]).sum()).collect())
+            .collect())
+    }
+
+    pub fn add(&self, other: &Matrix) -> Matrix {
+        Matrix::new(self.rows.iter()
+            .zip(&other.rows)
+            .map(|(a, b)| a.iter().zip(b).map(|(x, y)| x + y).collect())
+            .collect())
+    }
+
+    pub fn subtract(&self, other: &Matrix) -> Matrix {
+        self.add(&other.scale(-1.0))
+    }
+
+    pub fn scale(&self, scalar: f64) -> Matrix {
+        Matrix::new(self.rows.iter().map(|row| row.iter().map(|value| value * scalar).collect()).collect())
+    }
+
+    pub fn multiply_vector(&self, vector: &[f64]) -> Vec<f64> {
+        self.rows.iter().map(|row| row.iter().zip(vector).map(|(a, b)| a * b).sum()).collect()
+    }
+
+    // Lower-triangular L with L L^T equal to this symmetric matrix; `None` unless it is
+    // positive definite
+    pub fn cholesky(&self) -> Option<Matrix> {
+        let size = self.row_count();
+        let mut lower = Matrix::zeros(size, size);
+        for i in 0..size {
+            for j in 0..=i {
+                let sum: f64 = (0..j).map(|k| lower.rows[i][k] * lower.rows[j][k]).sum();
+                if i == j {
+                    let diagonal = self.rows[i][i] - sum;
+                    if diagonal <= 0.0 || !diagonal.is_finite() {
+                        return None;
+                    }
+                    lower.rows[i][j] = diagonal.sqrt();
+                } else {
+                    lower.rows[i][j] = (self.rows[i][j] - sum) / lower.rows[j][j];
+                }
+            }
+        }
+        Some(lower)
+    }
+
+    // Gauss-Jordan elimination with partial pivoting; `None` if singular
+    pub fn inverse(&self) -> Option<Matrix> {
+        let size = self.row_count();
+        let mut work = self.rows.clone();
+        let mut inverse = Matrix::identity(size).rows;
+
+        for column in 0..size {
+            let pivot = (column..size).max_by(|&a, &b| work[a][column].abs().total_cmp(&work[b][column].abs()))?;
+            if work[pivot][column] == 0.0 || !work[pivot][column].is_finite() {
+                return None;
+            }
+            work.swap(column, pivot);
+            inverse.swap(column, pivot);
+
+            let scale = 1.0 / work[column][column];
+            work[column].iter_mut().for_each(|value| *value *= scale);
+            inverse[column].iter_mut().for_each(|value| *value *= scale);
+
+            for row in 0..size {
+                let factor = work[row][column];
+                if row == column || factor == 0.0 {
+                    continue;
+                }
+                for k in 0..size {
+                    work[row][k] -= factor * work[column][k];
+                    inverse[row][k] -= factor * inverse[column][k];
+                }
+            }
+        }
+        Some(Matrix::new(inverse))
+    }
+}
+
+// The simulation frame is the J2000 ecliptic; IAU and station data come in the equatorial frame
+pub fn ecliptic_to_equatorial(vector: &Vector3) -> Vector3 {
+    Matrix3::rotation_x(-J2000_OBLIQUITY).multiply_vector(vector)
+}
+
+pub fn equatorial_to_ecliptic(vector: &Vector3) -> Vector3 {
+    Matrix3::rotation_x(J2000_OBLIQUITY).multiply_vector(vector)
+}
+
+// Unit quaternion, scalar first, Hamilton convention
+#[derive(Debug, Clone)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Quaternion {
+    pub fn new(w: f64, x: f64, y: f64, z: f64) -> Self {
+        Quaternion { w, x, y, z }
+    }
+
+    pub fn identity() -> Self {
+        Quaternion::new(1.0, 0.0, 0.0, 0.0)
+    }
+
+    pub fn from_axis_angle(axis: &Vector3, angle: f64) -> Self {
+        let axis = axis.norm();
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        Quaternion::new(cos, axis.x * sin, axis.y * sin, axis.z * sin)
+    }
+
+    pub fn multiply(&self, other: &Quaternion) -> Quaternion {
+        Quaternion::new(
+            self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+            self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+        )
+    }
+
+    pub fn add(&self, other: &Quaternion) -> Quaternion {
+        Quaternion::new(self.w + other.w, self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+
+    pub fn scale(&self, scalar: f64) -> Quaternion {
+        Quaternion::new(self.w * scalar, self.x * scalar, self.y * scalar, self.z * scalar)
+    }
+
+    pub fn conjugate(&self) -> Quaternion {
+        Quaternion::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    pub fn norm(&self) -> Quaternion {
+        let magnitude = (self.w.powi(2) + self.x.powi(2) + self.y.powi(2) + self.z.powi(2)).sqrt();
+        self.scale(1.0 / magnitude)
+    }
+
+    pub fn rotate(&self, vector: &Vector3) -> Vector3 {
+        let rotated = self.multiply(&Quaternion::new(0.0, vector.x, vector.y, vector.z)).multiply(&self.conjugate());
+        Vector3::new(rotated.x, rotated.y, rotated.z)
+    }
+
+    // Matrix whose columns are the rotated x, y and z axes
+    pub fn to_matrix(&self) -> Matrix3 {
+        let (w, x, y, z) = (self.w, self.x, self.y, self.z);
+        Matrix3::new([
+            [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - w * z), 2.0 * (x * z + w * y)],
+            [2.0 * (x * y + w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - w * x)],
+            [2.0 * (x * z - w * y), 2.0 * (y * z + w * x), 1.0 - 2.0 * (x * x + y * y)],
+        ])
+    }
+}
@@ -1,32 +1,29 @@
 use crate::physics;
-use crate::geometry::Vector3;
 
 pub struct EulerIntegrator;
 
 impl super::Integrator for EulerIntegrator {
-    fn step(&self, state: &mut physics::State, timestep: f64) {
-        let num_bodies = state.positions.len();
-        let mut forces: Vec<Vector3> = vec![Vector3::new(0.0, 0.0, 0.0); num_bodies];
-        
-        // Calculate forces between all bodies
-        for i in 0..num_bodies {
-            for j in 0..num_bodies {
-                if i != j {
-                    let force = physics::calculate_force_euler(
-                        state.masses[i],
-                        state.masses[j],
-                        state.positions[j].subtract(&state.positions[i])
-                    );
-                    forces[i] = forces[i].add(&force);
-                }
-            }
-        }
+    fn step(&self, state: &mut physics::State, model: &physics::ForceModel, timestep: f64) {
+        let derivatives = model.calculate_derivatives(state);
 
-        // Update positions and velocities
-        for i in 0..num_bodies {
-            let acc = physics::calculate_acceleration(forces[i].clone(), state.masses[i]);
+        // Update positions, velocities and masses
+        for (i, acc) in derivatives.accelerations.iter().enumerate() {
             state.velocities[i] = state.velocities[i].add(&acc.scale(timestep));
             state.positions[i] = state.positions[i].add(&state.velocities[i].scale(timestep));
+            state.masses[i] += derivatives.mass_rates[i] * timestep;
+        }
+
+        // Same semi-implicit scheme for the variational equations: velocity rows first,
+        // then position rows from the updated velocity rows
+        for (variation, rate) in state.variations.iter_mut().zip(&derivatives.variation_rates) {
+            let (positions, velocities) = variation.matrix.rows.split_at_mut(3);
+            for ((position_row, velocity_row), rate_row) in positions.iter_mut().zip(velocities.iter_mut()).zip(&rate.rows[3..]) {
+                for ((position, velocity), rate) in position_row.iter_mut().zip(velocity_row.iter_mut()).zip(rate_row) {
+                    *velocity += rate * timestep;
+                    *position += *velocity * timestep;
+                }
+            }
         }
+        state.time += timestep;
     }
-}
\ No newline at end of file
+}
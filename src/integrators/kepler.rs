@@ -0,0 +1,120 @@
+use crate::geometry::Matrix;
+use crate::orbit;
+use crate::physics;
+
+// Exact two-body propagation of every body about one central body. Only the point-mass
+// attraction of the central body is kept: other bodies, radiation, thrust and every
+// other term of the force model are ignored, and the central body coasts in a straight
+// line. Useful for isolated two-body problems and as a reference for the numerical
+// integrators.
+pub struct KeplerIntegrator {
+    central: usize,     // index into the state
+}
+
+impl KeplerIntegrator {
+    pub fn new(central: usize) -> Self {
+        KeplerIntegrator { central }
+    }
+}
+
+impl super::Integrator for KeplerIntegrator {
+    fn step(&self, state: &mut physics::State, _model: &physics::ForceModel, timestep: f64) {
+        let central_position = state.positions[self.central].clone();
+        let central_velocity = state.velocities[self.central].clone();
+        let central_mass = state.masses[self.central];
+
+        // Analytic partials of each two-body step, taken before the states move
+        let steps: Vec<Matrix> = state.variations.iter()
+            .map(|variation| {
+                let i = variation.index;
+                if i == self.central {
+                    return coasting_transition(timestep);
+                }
+                let mu = physics::GRAVITATIONAL_CONST * (central_mass + state.masses[i]);
+                orbit::kepler_transition_matrix(
+                    &state.positions[i].subtract(&central_position),
+                    &state.velocities[i].subtract(&central_velocity),
+                    mu,
+                    timestep,
+                )
+            })
+            .collect();
+        for (variation, step) in state.variations.iter_mut().zip(&steps) {
+            variation.matrix = step.multiply(&variation.matrix);
+        }
+
+        for i in 0..state.positions.len() {
+            if i == self.central {
+                continue;
+            }
+            let mu = physics::GRAVITATIONAL_CONST * (central_mass + state.masses[i]);
+            let (position, velocity) = orbit::propagate_kepler(
+                &state.positions[i].subtract(&central_position),
+                &state.velocities[i].subtract(&central_velocity),
+                mu,
+                timestep,
+            );
+            state.positions[i] = central_position.add(&central_velocity.scale(timestep)).add(&position);
+            state.velocities[i] = central_velocity.add(&velocity);
+        }
+        state.positions[self.central] = central_position.add(&central_velocity.scale(timestep));
+        state.time += timestep;
+    }
+}
+
+fn coasting_transition(timestep: f64) -> Matrix {
+    let mut matrix = Matrix::identity(6);
+    for k in 0..3 {
+        matrix.rows[k][k + 3] = timestep;
+    }
+    matrix
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::body::{BodyType, CelestialBody};
+    use crate::geometry::Vector3;
+    use crate::integrators::IntegratorType;
+    use crate::orbit;
+    use crate::physics;
+    use crate::solar_system::SolarSystem;
+
+    const EARTH_MASS: f64 = 5.972e24;
+
+    // Earth and a satellite on an eccentric orbit after `duration` seconds
+    fn two_body_run(timestep: f64, integrator: IntegratorType, duration: f64) -> Vec<CelestialBody> {
+        let mut system = SolarSystem::new(timestep, integrator);
+        system.add_body(CelestialBody::new(
+            "Earth".to_string(), BodyType::Planet, Vector3::new(0.0, 0.0, 0.0), 6378.137, EARTH_MASS,
+            Vector3::new(0.0, 0.0, 0.0), [0.0, 0.0, 1.0],
+        ));
+        let (position, velocity) = orbit::KeplerianElements::new(1.2e7, 0.4, 0.9, 0.5, 1.0, 0.2)
+            .to_state(physics::GRAVITATIONAL_CONST * (EARTH_MASS + 1000.0));
+        system.add_body(CelestialBody::new(
+            "Satellite".to_string(), BodyType::Satellite, position, 0.001, 1000.0, velocity, [1.0, 1.0, 1.0],
+        ));
+        system.run_until(duration);
+        system.get_bodies().clone()
+    }
+
+    #[test]
+    fn agrees_with_small_step_rk4() {
+        // Three revolutions of a 3.6 h orbit: RK4 with 5 s steps stays within
+        // 1 cm of the exact solution, one long Kepler step included
+        let duration = 40_000.0;
+        let rk4 = two_body_run(5.0, IntegratorType::RK4(1), duration);
+        for timestep in [60.0, duration] {
+            let kepler = two_body_run(timestep, IntegratorType::Kepler(0), duration);
+            let relative = |bodies: &[CelestialBody]| (
+                bodies[1].position.subtract(&bodies[0].position),
+                bodies[1].velocity.subtract(&bodies[0].velocity),
+            );
+            let (rk4_position, rk4_velocity) = relative(&rk4);
+            let (kepler_position, kepler_velocity) = relative(&kepler);
+            let position_error = kepler_position.subtract(&rk4_position).magnitude();
+            let velocity_error = kepler_velocity.subtract(&rk4_velocity).magnitude();
+            assert!(position_error < 0.01, "{} m off with {} s steps", position_error, timestep);
+            assert!(velocity_error < 1e-5, "{} m/s off with {} s steps", velocity_error, timestep);
+        }
+    }
+}
@@ -1,16 +1,20 @@
 use crate::physics;
 
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum IntegratorType {
     Euler,
     RK4(usize),
+    Kepler(usize),      // id of the central body
 }
 
 pub trait Integrator {
-    fn step(&self, state: &mut physics::State, timestep: f64);
+    fn step(&self, state: &mut physics::State, model: &physics::ForceModel, timestep: f64);
 }
 
 mod euler;
 mod rk4;
+mod kepler;
 
 pub use self::euler::EulerIntegrator;
-pub use self::rk4::RK4Integrator;
\ No newline at end of file
+pub use self::rk4::RK4Integrator;
+pub use self::kepler::KeplerIntegrator;
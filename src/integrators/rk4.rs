@@ -11,30 +11,32 @@ impl RK4Integrator {
         }
     }
 
-    fn single_step(&self, state: &mut physics::State, timestep: f64) {
+    fn single_step(&self, state: &mut physics::State, model: &physics::ForceModel, timestep: f64) {
         let num_bodies = state.positions.len();
 
-        let d1 = calculate_derivatives(state);
+        let d1 = model.calculate_derivatives(state);
     
-        let mut temp_state = state.advance_by_derivatives(d1.clone(), timestep / 2.0);
-        let d2 = calculate_derivatives(&mut temp_state);
+        let temp_state = state.advance_by_derivatives(&d1, timestep / 2.0);
+        let d2 = model.calculate_derivatives(&temp_state);
         
-        let mut temp_state = state.advance_by_derivatives(d2.clone(), timestep / 2.0);
-        let d3 = calculate_derivatives(&mut temp_state);
+        let temp_state = state.advance_by_derivatives(&d2, timestep / 2.0);
+        let d3 = model.calculate_derivatives(&temp_state);
         
-        let mut temp_state = state.advance_by_derivatives(d3.clone(), timestep);
-        let d4 = calculate_derivatives(&mut temp_state);
+        let temp_state = state.advance_by_derivatives(&d3, timestep);
+        let d4 = model.calculate_derivatives(&temp_state);
 
-        let derivatives = vec![d1, d2, d3, d4];
+        let derivatives = [d1, d2, d3, d4];
         let weights = [1.0/6.0, 1.0/3.0, 1.0/3.0, 1.0/6.0];
 
         for i in 0..num_bodies {
             let mut final_velocity = Vector3::new(0.0, 0.0, 0.0);
             let mut final_acceleration = Vector3::new(0.0, 0.0, 0.0);
+            let mut final_mass_rate = 0.0;
             
             for (j, &weight) in weights.iter().enumerate() {
-                final_velocity = final_velocity.add(&derivatives[j].0[i].scale(weight));
-                final_acceleration = final_acceleration.add(&derivatives[j].1[i].scale(weight));
+                final_velocity = final_velocity.add(&derivatives[j].velocities[i].scale(weight));
+                final_acceleration = final_acceleration.add(&derivatives[j].accelerations[i].scale(weight));
+                final_mass_rate += derivatives[j].mass_rates[i] * weight;
             }
 
             final_velocity = final_velocity.scale(timestep);
@@ -42,32 +44,24 @@ impl RK4Integrator {
             
             state.positions[i] = state.positions[i].add(&final_velocity);
             state.velocities[i] = state.velocities[i].add(&final_acceleration);
+            state.masses[i] += final_mass_rate * timestep;
         }
+
+        for (n, variation) in state.variations.iter_mut().enumerate() {
+            for (j, &weight) in weights.iter().enumerate() {
+                variation.matrix = variation.matrix.add(&derivatives[j].variation_rates[n].scale(weight * timestep));
+            }
+        }
+        state.time += timestep;
     }
 }
 
 impl super::Integrator for RK4Integrator {
-    fn step(&self, state: &mut physics::State, timestep: f64) {
+    fn step(&self, state: &mut physics::State, model: &physics::ForceModel, timestep: f64) {
         let substep_size = timestep / self.substeps as f64;
         
         for _ in 0..self.substeps {
-            self.single_step(state, substep_size);
+            self.single_step(state, model, substep_size);
         }
     }
 }
-
-fn calculate_derivatives(state: &mut physics::State) -> (Vec<Vector3>, Vec<Vector3>) {
-    let num_bodies = state.positions.len();
-    let mut acceleration = vec![Vector3::new(0.0, 0.0, 0.0); num_bodies];
-    let velocity = state.velocities.to_vec();
-
-    for i in 0..num_bodies {
-        for j in 0..num_bodies {
-            if i != j {
-                let force = physics::calculate_force_euler(state.masses[i], state.masses[j], state.positions[j].subtract(&state.positions[i]));
-                acceleration[i] = acceleration[i].add(&force.scale(1.0 / state.masses[i]));
-            }
-        }
-    }           
-    (velocity, acceleration)                
-}
\ No newline at end of file
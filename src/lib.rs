@@ -3,4 +3,12 @@ pub mod renderer;
 pub mod body;
 pub mod solar_system;
 pub mod physics;
-pub mod integrators;
\ No newline at end of file
+pub mod collision;
+pub mod integrators;
+pub mod mission;
+pub mod rotation;
+pub mod time;
+pub mod attitude;
+pub mod orbit;
+pub mod estimation;
+pub mod geodesy;
@@ -1,9 +1,29 @@
+use std::env;
+use satellite::orbit;
 use satellite::renderer;
 use satellite::solar_system::SolarSystem;
 
 fn main() {
     let mut renderer = renderer::Renderer::new();
-    let mut solar_system = SolarSystem::initialize_standard();
+
+    // An optional two- or three-line element file puts its satellites around Earth,
+    // starting from the epoch of the first set
+    let tles = match env::args().nth(1) {
+        Some(path) => orbit::read_tles(&path).unwrap_or_else(|error| {
+            eprintln!("Could not read {}: {}", path, error);
+            Vec::new()
+        }),
+        None => Vec::new(),
+    };
+    let mut solar_system = match tles.first() {
+        Some(tle) => SolarSystem::initialize_at_epoch(tle.epoch),
+        None => SolarSystem::initialize_standard(),
+    };
+    if !tles.is_empty() {
+        let added = tles.iter().filter(|tle| solar_system.add_satellite_from_tle(tle).is_some()).count();
+        println!("Loaded {} of {} element sets", added, tles.len());
+        solar_system.timestep = 60.0;
+    }
 
     for body in solar_system.get_bodies() {
         renderer.add_body(body);
@@ -11,4 +31,4 @@ fn main() {
 
     // Start simulation
     renderer.render_loop(&mut solar_system);
-}
\ No newline at end of file
+}
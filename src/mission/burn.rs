@@ -0,0 +1,44 @@
+use crate::geometry::Vector3;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BurnFrame {
+    Inertial,
+    RTN,    // radial, transverse, orbit normal
+    VNB,    // velocity, orbit normal, binormal
+}
+
+#[derive(Clone, Debug)]
+pub struct ImpulsiveBurn {
+    pub epoch: f64,             // simulation time in seconds
+    pub delta_v: Vector3,       // m/s, components expressed in `frame`
+    pub frame: BurnFrame,
+}
+
+impl ImpulsiveBurn {
+    pub fn new(epoch: f64, delta_v: Vector3, frame: BurnFrame) -> Self {
+        ImpulsiveBurn { epoch, delta_v, frame }
+    }
+
+    // Rotates the delta-v into the inertial frame. `position` and `velocity` are
+    // relative to the body being orbited.
+    pub fn inertial_delta_v(&self, position: &Vector3, velocity: &Vector3) -> Vector3 {
+        let normal = position.cross(velocity).norm();
+        let (x_axis, y_axis, z_axis) = match self.frame {
+            BurnFrame::Inertial => return self.delta_v.clone(),
+            BurnFrame::RTN => {
+                let radial = position.norm();
+                let transverse = normal.cross(&radial);
+                (radial, transverse, normal)
+            },
+            BurnFrame::VNB => {
+                let tangent = velocity.norm();
+                let binormal = tangent.cross(&normal);
+                (tangent, normal, binormal)
+            },
+        };
+
+        x_axis.scale(self.delta_v.x)
+            .add(&y_axis.scale(self.delta_v.y))
+            .add(&z_axis.scale(self.delta_v.z))
+    }
+}
@@ -0,0 +1,159 @@
+use std::fmt;
+use crate::geometry::Vector3;
+use crate::orbit::KeplerianElements;
+
+// B-plane of a hyperbolic approach. S points along the incoming asymptote, T lies in
+// the simulation's ecliptic plane and R = S x T completes the frame.
+#[derive(Clone, Debug)]
+pub struct BPlane {
+    pub b_dot_t: f64,       // m
+    pub b_dot_r: f64,       // m
+    pub s: Vector3,
+    pub t: Vector3,
+    pub r: Vector3,
+}
+
+impl BPlane {
+    pub fn magnitude(&self) -> f64 {
+        self.b_dot_t.hypot(self.b_dot_r)
+    }
+
+    // Angle of B measured from T towards R
+    pub fn angle(&self) -> f64 {
+        self.b_dot_r.atan2(self.b_dot_t)
+    }
+}
+
+// Desired B-plane point for the targeter, with its convergence settings
+#[derive(Clone, Debug)]
+pub struct BPlaneTarget {
+    pub b_dot_t: f64,
+    pub b_dot_r: f64,
+    pub periapsis_time: Option<f64>,
+    pub tolerance: f64,         // m, on the distance from the target point
+    pub max_iterations: usize,
+}
+
+impl BPlaneTarget {
+    pub fn new(b_dot_t: f64, b_dot_r: f64) -> Self {
+        BPlaneTarget { b_dot_t, b_dot_r, periapsis_time: None, tolerance: 1e3, max_iterations: 20 }
+    }
+
+    pub fn with_periapsis_time(mut self, time: f64) -> Self {
+        self.periapsis_time = Some(time);
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TargetingResult {
+    pub delta_v: Vector3,           // in the frame of the adjusted burn
+    pub b_plane: Option<BPlane>,    // achieved, `None` if the approach was not hyperbolic
+    pub periapsis_time: Option<f64>,
+    pub iterations: usize,
+    pub converged: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct Flyby {
+    pub time: f64,                  // of periapsis
+    pub spacecraft: String,
+    pub body: String,
+    pub periapsis_radius: f64,      // m, from the centre of the body
+    pub v_infinity_in: Vector3,
+    pub v_infinity_out: Vector3,
+    pub turn_angle: f64,            // rad
+    pub b_plane: BPlane,
+}
+
+impl fmt::Display for Flyby {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "t = {:.0} s: {} flew by {} at {:.0} km, v-infinity {:.3} km/s, turned {:.2} deg, B.T = {:.0} km, B.R = {:.0} km",
+            self.time,
+            self.spacecraft,
+            self.body,
+            self.periapsis_radius / 1e3,
+            self.v_infinity_in.magnitude() / 1e3,
+            self.turn_angle.to_degrees(),
+            self.b_plane.b_dot_t / 1e3,
+            self.b_plane.b_dot_r / 1e3,
+        )
+    }
+}
+
+// Asymptotes of the hyperbola through `position` and `velocity` relative to a body
+// with gravitational parameter `mu`: (incoming v-infinity, outgoing v-infinity,
+// eccentricity). `None` unless the orbit is hyperbolic.
+fn asymptotes(position: &Vector3, velocity: &Vector3, mu: f64) -> Option<(Vector3, Vector3, f64)> {
+    let energy = velocity.dot(velocity) / 2.0 - mu / position.magnitude();
+    if energy <= 0.0 {
+        return None;
+    }
+    let v_infinity = (2.0 * energy).sqrt();
+    let normal = position.cross(velocity).norm();
+    let eccentricity_vector = position.scale(velocity.dot(velocity) - mu / position.magnitude())
+        .subtract(&velocity.scale(position.dot(velocity)))
+        .scale(1.0 / mu);
+    let eccentricity = eccentricity_vector.magnitude();
+    if eccentricity <= 1.0 {
+        return None;
+    }
+
+    let periapsis = eccentricity_vector.scale(1.0 / eccentricity);
+    let across = normal.cross(&periapsis);
+    let along = (1.0 - 1.0 / (eccentricity * eccentricity)).sqrt();
+    let incoming = periapsis.scale(1.0 / eccentricity).add(&across.scale(along));
+    let outgoing = periapsis.scale(-1.0 / eccentricity).add(&across.scale(along));
+    Some((incoming.scale(v_infinity), outgoing.scale(v_infinity), eccentricity))
+}
+
+// B-plane of the hyperbola through `position` and `velocity` relative to the body
+// being flown by. Any point on the hyperbola gives the same answer.
+pub fn b_plane(position: &Vector3, velocity: &Vector3, mu: f64) -> Option<BPlane> {
+    let (incoming, _, _) = asymptotes(position, velocity, mu)?;
+    let normal = position.cross(velocity).norm();
+
+    // B is the miss vector of the incoming asymptote, |B| = h / v_infinity
+    let b = incoming.norm().cross(&normal).scale(position.cross(velocity).magnitude() / incoming.magnitude());
+    Some(project_b_plane(&incoming, &b))
+}
+
+// B-plane of the flyby that turns `v_infinity_in` into the direction of `v_infinity_out`
+// with periapsis at `periapsis_radius`. The spacecraft bends towards the body, so B
+// points away from the outgoing direction.
+pub fn b_plane_for_turn(v_infinity_in: &Vector3, v_infinity_out: &Vector3, periapsis_radius: f64, mu: f64) -> BPlane {
+    let s = v_infinity_in.norm();
+    let outgoing = v_infinity_out.norm();
+    let across = outgoing.subtract(&s.scale(outgoing.dot(&s)));
+    let speed_squared = v_infinity_in.dot(v_infinity_in);
+    let magnitude = periapsis_radius * (1.0 + 2.0 * mu / (periapsis_radius * speed_squared)).sqrt();
+    project_b_plane(v_infinity_in, &across.norm().scale(-magnitude))
+}
+
+fn project_b_plane(v_infinity_in: &Vector3, b: &Vector3) -> BPlane {
+    let s = v_infinity_in.norm();
+    let t = s.cross(&Vector3::new(0.0, 0.0, 1.0)).norm();
+    let r = s.cross(&t);
+    BPlane { b_dot_t: b.dot(&t), b_dot_r: b.dot(&r), s, t, r }
+}
+
+// Flyby described by a state relative to the body inside its sphere of influence at
+// `time`; `None` for bound orbits
+pub fn flyby_from_state(position: &Vector3, velocity: &Vector3, mu: f64, time: f64, spacecraft: &str, body: &str) -> Option<Flyby> {
+    let (v_infinity_in, v_infinity_out, eccentricity) = asymptotes(position, velocity, mu)?;
+    let elements = KeplerianElements::from_state(position, velocity, mu);
+    let since_periapsis = elements.mean_anomaly() / elements.mean_motion(mu);
+
+    Some(Flyby {
+        time: time - since_periapsis,
+        spacecraft: spacecraft.to_string(),
+        body: body.to_string(),
+        periapsis_radius: elements.periapsis(),
+        v_infinity_in,
+        v_infinity_out,
+        turn_angle: 2.0 * (1.0 / eccentricity).asin(),
+        b_plane: b_plane(position, velocity, mu)?,
+    })
+}
@@ -0,0 +1,286 @@
+use std::f64::consts::PI;
+use crate::geometry::Vector3;
+
+const MAX_ITERATIONS: usize = 35;
+const ABSOLUTE_TOLERANCE: f64 = 1e-12;
+const RELATIVE_TOLERANCE: f64 = 1e-10;
+
+#[derive(Clone, Debug)]
+pub struct LambertSolution {
+    pub departure_velocity: Vector3,
+    pub arrival_velocity: Vector3,
+    pub revolutions: usize,
+    pub low_path: bool,     // which of the two solutions of a multi-revolution transfer
+}
+
+// Izzo's (2015) Lambert solver: every conic from `departure` to `arrival` (relative to
+// a body with gravitational parameter `mu`) taking `time_of_flight` seconds, with up to
+// `max_revolutions` full revolutions. Zero revolutions give one solution and each
+// feasible revolution count two more. Empty for degenerate geometry, such as a
+// transfer angle of exactly 180 degrees, where the plane of the transfer is undefined.
+pub fn solve_lambert(departure: &Vector3, arrival: &Vector3, time_of_flight: f64, mu: f64, prograde: bool, max_revolutions: usize) -> Vec<LambertSolution> {
+    let mut solutions = Vec::new();
+    let chord = arrival.subtract(departure).magnitude();
+    let (r1, r2) = (departure.magnitude(), arrival.magnitude());
+    let normal = departure.cross(arrival);
+    if time_of_flight <= 0.0 || mu <= 0.0 || chord == 0.0 || normal.magnitude() <= 1e-12 * r1 * r2 {
+        return solutions;
+    }
+
+    let semiperimeter = (r1 + r2 + chord) / 2.0;
+    let (radial1, radial2, normal) = (departure.scale(1.0 / r1), arrival.scale(1.0 / r2), normal.norm());
+    let mut lambda = (1.0 - (chord / semiperimeter).min(1.0)).sqrt();
+    let (mut tangential1, mut tangential2) = if normal.z < 0.0 {
+        lambda = -lambda;
+        (radial1.cross(&normal), radial2.cross(&normal))
+    } else {
+        (normal.cross(&radial1), normal.cross(&radial2))
+    };
+    if !prograde {
+        lambda = -lambda;
+        tangential1 = tangential1.scale(-1.0);
+        tangential2 = tangential2.scale(-1.0);
+    }
+
+    let time = (2.0 * mu / semiperimeter.powi(3)).sqrt() * time_of_flight;
+    let gamma = (mu * semiperimeter / 2.0).sqrt();
+    let rho = (r1 - r2) / chord;
+    let sigma = (1.0 - rho * rho).sqrt();
+
+    let mut push = |x: f64, revolutions: usize, low_path: bool| {
+        let y = compute_y(x, lambda);
+        let radial_speed1 = gamma * ((lambda * y - x) - rho * (lambda * y + x)) / r1;
+        let radial_speed2 = -gamma * ((lambda * y - x) + rho * (lambda * y + x)) / r2;
+        let transverse_speed1 = gamma * sigma * (y + lambda * x) / r1;
+        let transverse_speed2 = gamma * sigma * (y + lambda * x) / r2;
+        solutions.push(LambertSolution {
+            departure_velocity: radial1.scale(radial_speed1).add(&tangential1.scale(transverse_speed1)),
+            arrival_velocity: radial2.scale(radial_speed2).add(&tangential2.scale(transverse_speed2)),
+            revolutions,
+            low_path,
+        });
+    };
+
+    if let Some(x) = householder(initial_guess(time, lambda, 0, true), time, lambda, 0) {
+        push(x, 0, true);
+    }
+
+    let feasible_revolutions = max_feasible_revolutions(time, lambda).min(max_revolutions);
+    for revolutions in 1..=feasible_revolutions {
+        for low_path in [true, false] {
+            if let Some(x) = householder(initial_guess(time, lambda, revolutions, low_path), time, lambda, revolutions) {
+                push(x, revolutions, low_path);
+            }
+        }
+    }
+    solutions
+}
+
+// Largest revolution count reachable in non-dimensional time `time`
+fn max_feasible_revolutions(time: f64, lambda: f64) -> usize {
+    let mut revolutions = (time / PI).floor() as usize;
+    let time_00 = lambda.acos() + lambda * (1.0 - lambda * lambda).sqrt();
+    if revolutions > 0 && time < time_00 + revolutions as f64 * PI {
+        let x = halley_minimum(0.1, lambda, revolutions);
+        if time < time_of_flight(x, lambda, revolutions) {
+            revolutions -= 1;
+        }
+    }
+    revolutions
+}
+
+fn compute_y(x: f64, lambda: f64) -> f64 {
+    (1.0 - lambda * lambda * (1.0 - x * x)).sqrt()
+}
+
+fn compute_psi(x: f64, y: f64, lambda: f64) -> f64 {
+    if (-1.0..1.0).contains(&x) {
+        (x * y + lambda * (1.0 - x * x)).clamp(-1.0, 1.0).acos()
+    } else if x > 1.0 {
+        ((y - x * lambda) * (x * x - 1.0).sqrt()).asinh()
+    } else {
+        0.0
+    }
+}
+
+// Gauss hypergeometric 2F1(3, 1, 5/2, x), used near the parabola where the closed
+// form loses precision
+fn hypergeometric(x: f64) -> f64 {
+    if x >= 1.0 {
+        return f64::INFINITY;
+    }
+    let mut result = 1.0;
+    let mut term = 1.0;
+    for n in 0..1000 {
+        let n = n as f64;
+        term *= (3.0 + n) * (1.0 + n) / (2.5 + n) * x / (n + 1.0);
+        let previous = result;
+        result += term;
+        if result == previous {
+            break;
+        }
+    }
+    result
+}
+
+// Non-dimensional time of flight T(x) of Izzo's formulation
+fn time_of_flight(x: f64, lambda: f64, revolutions: usize) -> f64 {
+    let y = compute_y(x, lambda);
+    if revolutions == 0 && x > 0.6_f64.sqrt() && x < 1.4_f64.sqrt() {
+        let eta = y - lambda * x;
+        let s1 = (1.0 - lambda - x * eta) / 2.0;
+        let q = 4.0 / 3.0 * hypergeometric(s1);
+        (eta.powi(3) * q + 4.0 * lambda * eta) / 2.0
+    } else {
+        let psi = compute_psi(x, y, lambda);
+        ((psi + revolutions as f64 * PI) / (1.0 - x * x).abs().sqrt() - x + lambda * y) / (1.0 - x * x)
+    }
+}
+
+// First three derivatives of T(x)
+fn time_derivatives(x: f64, time: f64, lambda: f64) -> (f64, f64, f64) {
+    let y = compute_y(x, lambda);
+    let l2 = lambda * lambda;
+    let l3 = l2 * lambda;
+    let first = (3.0 * time * x - 2.0 + 2.0 * l3 * x / y) / (1.0 - x * x);
+    let second = (3.0 * time + 5.0 * x * first + 2.0 * (1.0 - l2) * l3 / y.powi(3)) / (1.0 - x * x);
+    let third = (7.0 * x * second + 8.0 * first - 6.0 * (1.0 - l2) * l3 * l2 * x / y.powi(5)) / (1.0 - x * x);
+    (first, second, third)
+}
+
+fn initial_guess(time: f64, lambda: f64, revolutions: usize, low_path: bool) -> f64 {
+    if revolutions == 0 {
+        let time_00 = lambda.acos() + lambda * (1.0 - lambda * lambda).sqrt();
+        let time_1 = 2.0 * (1.0 - lambda.powi(3)) / 3.0;
+        if time >= time_00 {
+            (time_00 / time).powf(2.0 / 3.0) - 1.0
+        } else if time < time_1 {
+            2.5 * time_1 * (time_1 - time) / (time * (1.0 - lambda.powi(5))) + 1.0
+        } else {
+            (time / time_00).powf(2.0_f64.ln() / (time_1 / time_00).ln()) - 1.0
+        }
+    } else {
+        let m = revolutions as f64 * PI;
+        let left = ((m + PI) / (8.0 * time)).powf(2.0 / 3.0);
+        let right = (8.0 * time / m).powf(2.0 / 3.0);
+        let x_left = (left - 1.0) / (left + 1.0);
+        let x_right = (right - 1.0) / (right + 1.0);
+        if low_path { x_left.max(x_right) } else { x_left.min(x_right) }
+    }
+}
+
+// Solves T(x) = time with third-order Householder iterations
+fn householder(mut x: f64, time: f64, lambda: f64, revolutions: usize) -> Option<f64> {
+    for _ in 0..MAX_ITERATIONS {
+        let current = time_of_flight(x, lambda, revolutions);
+        let residual = current - time;
+        let (first, second, third) = time_derivatives(x, current, lambda);
+        let next = x - residual * (first * first - residual * second / 2.0)
+            / (first * (first * first - residual * second) + third * residual * residual / 6.0);
+        if !next.is_finite() {
+            return None;
+        }
+        if (next - x).abs() < RELATIVE_TOLERANCE * x.abs() + ABSOLUTE_TOLERANCE {
+            return Some(next);
+        }
+        x = next;
+    }
+    None
+}
+
+// Location of the minimum of T(x) for a multi-revolution transfer, by Halley iterations
+fn halley_minimum(mut x: f64, lambda: f64, revolutions: usize) -> f64 {
+    for _ in 0..MAX_ITERATIONS {
+        let time = time_of_flight(x, lambda, revolutions);
+        let (first, second, third) = time_derivatives(x, time, lambda);
+        if second == 0.0 {
+            break;
+        }
+        let next = x - 2.0 * first * second / (2.0 * second * second - first * third);
+        if !next.is_finite() {
+            break;
+        }
+        if (next - x).abs() < RELATIVE_TOLERANCE * x.abs() + ABSOLUTE_TOLERANCE {
+            return next;
+        }
+        x = next;
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orbit::propagate_kepler;
+    use crate::physics::{ASTRONOMICAL_UNIT, GRAVITATIONAL_CONST};
+    use crate::time::SECONDS_PER_DAY;
+
+    const SUN_MU: f64 = GRAVITATIONAL_CONST * 1.989e30;
+
+    // Transfer from 1 AU on the x axis to `distance` AU at `angle` radians along the
+    // ecliptic, lifted out of it by `inclination` radians
+    fn transfer(distance: f64, angle: f64, inclination: f64, days: f64, prograde: bool, max_revolutions: usize) -> Vec<LambertSolution> {
+        let departure = Vector3::new(ASTRONOMICAL_UNIT, 0.0, 0.0);
+        let arrival = Vector3::new(angle.cos(), angle.sin() * inclination.cos(), angle.sin() * inclination.sin())
+            .scale(distance * ASTRONOMICAL_UNIT);
+        let time_of_flight = days * SECONDS_PER_DAY;
+        let solutions = solve_lambert(&departure, &arrival, time_of_flight, SUN_MU, prograde, max_revolutions);
+
+        // Every solution has to reach the arrival point on time, on a conic going the
+        // requested way round
+        for solution in &solutions {
+            let (position, velocity) = propagate_kepler(&departure, &solution.departure_velocity, SUN_MU, time_of_flight);
+            let miss = position.subtract(&arrival).magnitude();
+            assert!(miss < 1e-8 * arrival.magnitude(), "{:?} misses by {} m", solution, miss);
+            let speed_error = velocity.subtract(&solution.arrival_velocity).magnitude();
+            assert!(speed_error < 1e-8 * velocity.magnitude(), "{:?} arrives {} m/s off", solution, speed_error);
+            let angular_momentum = departure.cross(&solution.departure_velocity);
+            assert_eq!(angular_momentum.z > 0.0, prograde);
+        }
+        solutions
+    }
+
+    #[test]
+    fn short_way() {
+        for (distance, angle, days) in [(1.5, PI / 2.0, 200.0), (1.0, PI / 4.0, 30.0), (1.2, 1.0, 400.0), (0.7, 2.5, 90.0)] {
+            let solutions = transfer(distance, angle, 0.0, days, true, 0);
+            assert_eq!(solutions.len(), 1, "{} AU at {} rad in {} days", distance, angle, days);
+        }
+        assert_eq!(transfer(1.5, PI / 2.0, 0.3, 200.0, true, 0).len(), 1);
+    }
+
+    #[test]
+    fn long_way() {
+        // Prograde past 180 degrees, or retrograde the other way round
+        for (distance, angle, days) in [(1.5, 4.0, 300.0), (1.0, 5.5, 250.0), (0.8, 3.5, 60.0)] {
+            assert_eq!(transfer(distance, angle, 0.0, days, true, 0).len(), 1, "{} AU at {} rad in {} days", distance, angle, days);
+        }
+        assert_eq!(transfer(1.5, PI / 2.0, 0.0, 300.0, false, 0).len(), 1);
+        assert_eq!(transfer(1.5, PI / 2.0, 0.3, 300.0, false, 0).len(), 1);
+    }
+
+    #[test]
+    fn near_half_a_revolution() {
+        for angle in [PI - 0.01, PI + 0.01] {
+            assert_eq!(transfer(1.5, angle, 0.0, 260.0, true, 0).len(), 1);
+        }
+        // Exactly opposite points do not fix the plane of the transfer
+        assert!(transfer(1.5, PI, 0.0, 260.0, true, 0).is_empty());
+    }
+
+    #[test]
+    fn multiple_revolutions() {
+        // Two revolutions need at least 1001.7 days on this geometry, each revolution
+        // count with a low and a high path
+        let solutions = transfer(1.5, PI / 2.0, 0.0, 1100.0, true, 5);
+        let counts: Vec<(usize, bool)> = solutions.iter().map(|solution| (solution.revolutions, solution.low_path)).collect();
+        assert_eq!(counts, vec![(0, true), (1, true), (1, false), (2, true), (2, false)]);
+        assert_eq!(transfer(1.5, PI / 2.0, 0.0, 1005.0, true, 5).len(), 5);
+        assert_eq!(transfer(1.5, PI / 2.0, 0.0, 1000.0, true, 5).len(), 3);
+
+        // Fewer when fewer are asked for, and none beyond the time available
+        assert_eq!(transfer(1.5, PI / 2.0, 0.0, 1100.0, true, 1).len(), 3);
+        assert_eq!(transfer(1.5, PI / 2.0, 0.0, 200.0, true, 3).len(), 1);
+        assert_eq!(transfer(1.5, 4.0, 0.3, 1500.0, false, 2).len(), 5);
+    }
+}
@@ -0,0 +1,152 @@
+use std::f64::consts::PI;
+use crate::geometry::Vector3;
+use crate::orbit::{self, KeplerianElements};
+use super::{BurnFrame, ImpulsiveBurn};
+
+const GOLDEN_SECTION_ITERATIONS: usize = 60;
+const COPLANAR_TOLERANCE: f64 = 1e-9;   // rad
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ManeuverKind {
+    Hohmann,
+    BiElliptic { intermediate_radius: f64 },
+}
+
+// Circular orbit to reach, about the same primary as the spacecraft
+#[derive(Clone, Debug)]
+pub enum ManeuverTarget {
+    Circular { radius: f64, normal: Option<Vector3> },     // no normal keeps the current plane
+    Body(usize),                                            // id of a body orbiting the same primary
+}
+
+#[derive(Clone, Debug)]
+pub struct ManeuverPlan {
+    pub kind: ManeuverKind,
+    pub burns: Vec<ImpulsiveBurn>,
+    pub delta_v: Vec<f64>,          // m/s, magnitude of each burn
+    pub total_delta_v: f64,
+    pub inclination_change: f64,    // rad
+    pub duration: f64,              // s, from the first burn to the last
+}
+
+// Transfer from the current orbit (assumed near circular) of a spacecraft at `position`
+// and `velocity` relative to a primary with gravitational parameter `mu`, at `epoch`,
+// to a circular orbit of `target_radius` in the plane with normal `target_normal`.
+//
+// The first burn waits for the line of nodes between the two planes, so every burn of a
+// Hohmann or bi-elliptic sequence falls on it. Hohmann transfers share the plane change
+// between both burns in the proportion that minimises the total; bi-elliptic transfers
+// do it all at the intermediate apoapsis, where the spacecraft is slowest. Burns are in
+// the RTN frame so they follow the spacecraft's actual state when they fire. Returns
+// `None` if the intermediate radius of a bi-elliptic transfer lies below either orbit.
+pub fn plan_maneuver(position: &Vector3, velocity: &Vector3, mu: f64, epoch: f64, target_radius: f64, target_normal: &Vector3, kind: ManeuverKind) -> Option<ManeuverPlan> {
+    let elements = KeplerianElements::from_state(position, velocity, mu);
+    let normal = position.cross(velocity).norm();
+    let target_normal = target_normal.norm();
+    let inclination_change = normal.dot(&target_normal).clamp(-1.0, 1.0).acos();
+
+    // Wait for the next node, or burn straight away when the planes already agree
+    let node = normal.cross(&target_normal);
+    let (start, burn_position, burn_velocity) = if node.magnitude() < COPLANAR_TOLERANCE {
+        (epoch, position.clone(), velocity.clone())
+    } else {
+        let rotation = elements.perifocal_to_inertial();
+        let periapsis = Vector3::new(rotation.rows[0][0], rotation.rows[1][0], rotation.rows[2][0]);
+        let quadrature = Vector3::new(rotation.rows[0][1], rotation.rows[1][1], rotation.rows[2][1]);
+        let node_anomaly = node.dot(&quadrature).atan2(node.dot(&periapsis));
+
+        let current = elements.mean_anomaly();
+        let (wait, anomaly) = [node_anomaly, node_anomaly + PI]
+            .map(|anomaly| {
+                let mean_anomaly = orbit::true_to_mean_anomaly(anomaly, elements.eccentricity);
+                ((mean_anomaly - current).rem_euclid(2.0 * PI) / elements.mean_motion(mu), anomaly)
+            })
+            .into_iter()
+            .fold((f64::INFINITY, 0.0), |best, candidate| if candidate.0 < best.0 { candidate } else { best });
+        let (burn_position, burn_velocity) = KeplerianElements { true_anomaly: anomaly, ..elements.clone() }.to_state(mu);
+        (epoch + wait, burn_position, burn_velocity)
+    };
+
+    // Radius and transverse speed where the first burn fires, plus the sense of the
+    // out-of-plane component that turns the orbit normal towards the target
+    let radius = burn_position.magnitude();
+    let transverse = normal.cross(&burn_position.norm());
+    let speed = burn_velocity.dot(&transverse);
+    let sense = if transverse.dot(&target_normal) > 0.0 { -1.0 } else { 1.0 };
+
+    let circular = |r: f64| (mu / r).sqrt();
+    let apsis_speed = |r: f64, other: f64| (mu * (2.0 / r - 2.0 / (r + other))).sqrt();
+    let half_period = |r: f64, other: f64| PI * ((r + other).powi(3) / (8.0 * mu)).sqrt();
+
+    // (epoch, speed before, speed after, plane change) for every burn
+    let sequence: Vec<(f64, f64, f64, f64)> = match kind {
+        ManeuverKind::Hohmann => {
+            let (before, after) = (apsis_speed(radius, target_radius), apsis_speed(target_radius, radius));
+            let cost = |share: f64| {
+                combined_burn(speed, before, share * inclination_change).2
+                    + combined_burn(after, circular(target_radius), (1.0 - share) * inclination_change).2
+            };
+            let share = golden_section(cost);
+            vec![
+                (start, speed, before, share * inclination_change),
+                (start + half_period(radius, target_radius), after, circular(target_radius), (1.0 - share) * inclination_change),
+            ]
+        },
+        ManeuverKind::BiElliptic { intermediate_radius } => {
+            if intermediate_radius < radius || intermediate_radius < target_radius {
+                return None;
+            }
+            let second = start + half_period(radius, intermediate_radius);
+            vec![
+                (start, speed, apsis_speed(radius, intermediate_radius), 0.0),
+                (second, apsis_speed(intermediate_radius, radius), apsis_speed(intermediate_radius, target_radius), inclination_change),
+                (
+                    second + half_period(intermediate_radius, target_radius),
+                    apsis_speed(target_radius, intermediate_radius),
+                    circular(target_radius),
+                    0.0,
+                ),
+            ]
+        },
+    };
+
+    // The second burn of either sequence fires at the opposite node, where the normal
+    // component has to point the other way in the RTN frame
+    let mut burns = Vec::new();
+    let mut delta_v = Vec::new();
+    for (n, &(burn_epoch, before, after, angle)) in sequence.iter().enumerate() {
+        let (tangential, out_of_plane, magnitude) = combined_burn(before, after, angle);
+        let side = if n % 2 == 0 { sense } else { -sense };
+        burns.push(ImpulsiveBurn::new(burn_epoch, Vector3::new(0.0, tangential, side * out_of_plane), BurnFrame::RTN));
+        delta_v.push(magnitude);
+    }
+
+    Some(ManeuverPlan {
+        kind,
+        total_delta_v: delta_v.iter().sum(),
+        duration: sequence.last().map_or(0.0, |last| last.0) - start,
+        burns,
+        delta_v,
+        inclination_change,
+    })
+}
+
+// Tangential and out-of-plane components, and magnitude, of the burn that changes the
+// speed from `before` to `after` while turning the velocity through `angle`
+fn combined_burn(before: f64, after: f64, angle: f64) -> (f64, f64, f64) {
+    let tangential = after * angle.cos() - before;
+    let out_of_plane = after * angle.sin();
+    (tangential, out_of_plane, tangential.hypot(out_of_plane))
+}
+
+// Minimum of a unimodal function on [0, 1]
+fn golden_section(function: impl Fn(f64) -> f64) -> f64 {
+    let ratio = (5.0_f64.sqrt() - 1.0) / 2.0;
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..GOLDEN_SECTION_ITERATIONS {
+        let a = high - ratio * (high - low);
+        let b = low + ratio * (high - low);
+        if function(a) < function(b) { high = b } else { low = a }
+    }
+    (low + high) / 2.0
+}
@@ -0,0 +1,340 @@
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::geometry::Vector3;
+use crate::time::{self, CalendarDate};
+use super::{solve_lambert, b_plane_for_turn, BPlane};
+
+// Cost of a trajectory whose Lambert arcs cannot be solved
+const INFEASIBLE: f64 = 1e12;
+const BISECTION_ITERATIONS: usize = 100;
+
+// A planet of the sequence as the optimizer sees it
+#[derive(Clone, Debug)]
+pub struct MgaBody {
+    pub id: usize,
+    pub name: String,
+    pub mu: f64,
+    pub radius: f64,    // m
+}
+
+#[derive(Clone, Debug)]
+pub struct MgaOptions {
+    pub departure_window: (f64, f64),               // epochs, s past J2000
+    pub time_of_flight_bounds: Vec<(f64, f64)>,     // s, one per leg
+    pub include_arrival: bool,                      // count the arrival v-infinity as delta-v
+    pub minimum_periapsis: f64,                     // flyby periapsis floor in body radii
+    pub population: usize,
+    pub generations: usize,
+    pub differential_weight: f64,
+    pub crossover_probability: f64,
+    pub seed: u64,
+}
+
+impl MgaOptions {
+    pub fn new(departure_window: (f64, f64), time_of_flight_bounds: Vec<(f64, f64)>) -> Self {
+        MgaOptions {
+            departure_window,
+            time_of_flight_bounds,
+            include_arrival: true,
+            minimum_periapsis: 1.1,
+            population: 60,
+            generations: 800,
+            differential_weight: 0.7,
+            crossover_probability: 0.9,
+            seed: 0,
+        }
+    }
+}
+
+// Patched-conic flyby at an intermediate planet of the sequence
+#[derive(Clone, Debug)]
+pub struct PlannedFlyby {
+    pub epoch: f64,
+    pub body: String,
+    pub v_infinity_in: Vector3,
+    pub v_infinity_out: Vector3,
+    pub periapsis_radius: f64,
+    pub delta_v: f64,           // powered part, applied along the velocity at periapsis
+    pub b_plane: BPlane,
+}
+
+#[derive(Clone, Debug)]
+pub struct MgaSolution {
+    pub body_ids: Vec<usize>,
+    pub bodies: Vec<String>,
+    pub epochs: Vec<f64>,                   // departure, then every encounter
+    pub departure_velocities: Vec<Vector3>, // heliocentric, at the start of each leg
+    pub arrival_velocities: Vec<Vector3>,   // heliocentric, at the end of each leg
+    pub departure_v_infinity: Vector3,
+    pub arrival_v_infinity: Vector3,
+    pub flybys: Vec<PlannedFlyby>,
+    pub total_delta_v: f64,
+}
+
+impl MgaSolution {
+    // One row per event of the tour. delta_v is only the powered flyby burn; the
+    // departure and arrival rows leave it empty and give their v-infinity instead.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("event,body,date,epoch,v_infinity_in_km_s,v_infinity_out_km_s,periapsis_km,delta_v_km_s,b_dot_t_km,b_dot_r_km\n");
+        let date = |epoch: f64| CalendarDate::from_time(epoch).to_string();
+        let _ = writeln!(
+            csv,
+            "departure,{},{},{:.3},,{:.6},,,,",
+            self.bodies[0], date(self.epochs[0]), self.epochs[0],
+            self.departure_v_infinity.magnitude() / 1e3
+        );
+        for flyby in &self.flybys {
+            let _ = writeln!(
+                csv,
+                "flyby,{},{},{:.3},{:.6},{:.6},{:.3},{:.6},{:.3},{:.3}",
+                flyby.body, date(flyby.epoch), flyby.epoch,
+                flyby.v_infinity_in.magnitude() / 1e3, flyby.v_infinity_out.magnitude() / 1e3,
+                flyby.periapsis_radius / 1e3, flyby.delta_v / 1e3,
+                flyby.b_plane.b_dot_t / 1e3, flyby.b_plane.b_dot_r / 1e3
+            );
+        }
+        let last = self.epochs.len() - 1;
+        let _ = writeln!(
+            csv,
+            "arrival,{},{},{:.3},{:.6},,,,,",
+            self.bodies[last], date(self.epochs[last]), self.epochs[last],
+            self.arrival_v_infinity.magnitude() / 1e3
+        );
+        csv
+    }
+
+    pub fn write_csv(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_csv())
+    }
+
+    pub fn time_of_flight_days(&self) -> f64 {
+        (self.epochs[self.epochs.len() - 1] - self.epochs[0]) / time::SECONDS_PER_DAY
+    }
+}
+
+// Multiple gravity assist tour through `bodies` minimising total delta-v, with the
+// departure epoch and leg durations found by differential evolution (DE/rand/1/bin).
+// Each leg is a zero-revolution Lambert arc about the star of gravitational parameter
+// `mu`; `ephemeris(n, epoch)` gives the state of `bodies[n]` relative to the star.
+// Flybys are powered: the periapsis is chosen to bend v-infinity in onto v-infinity out
+// and the difference in periapsis speeds is charged, plus a penalty when the bend needs
+// a periapsis below the floor.
+pub fn optimize_mga(bodies: &[MgaBody], mu: f64, ephemeris: &dyn Fn(usize, f64) -> (Vector3, Vector3), options: &MgaOptions) -> Option<MgaSolution> {
+    let legs = bodies.len().checked_sub(1)?;
+    if legs == 0 || options.time_of_flight_bounds.len() != legs {
+        return None;
+    }
+    let mut bounds = vec![options.departure_window];
+    bounds.extend(options.time_of_flight_bounds.iter().copied());
+    let dimension = bounds.len();
+    let population_size = options.population.max(4);
+
+    let evaluate = |x: &[f64]| evaluate_tour(bodies, mu, ephemeris, options, x);
+    let cost = |x: &[f64]| evaluate(x).map_or(INFEASIBLE, |solution| solution.total_delta_v);
+
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let mut population: Vec<Vec<f64>> = (0..population_size)
+        .map(|_| bounds.iter().map(|&(low, high)| sample(&mut rng, low, high)).collect())
+        .collect();
+    let mut costs: Vec<f64> = population.iter().map(|x| cost(x)).collect();
+
+    for _ in 0..options.generations {
+        for i in 0..population_size {
+            let mut picks = [i; 3];
+            for n in 0..3 {
+                while picks[n] == i || picks[..n].contains(&picks[n]) {
+                    picks[n] = rng.gen_range(0..population_size);
+                }
+            }
+            let [a, b, c] = picks;
+            let forced = rng.gen_range(0..dimension);
+
+            let trial: Vec<f64> = (0..dimension)
+                .map(|k| {
+                    if k == forced || rng.gen::<f64>() < options.crossover_probability {
+                        let value = population[a][k] + options.differential_weight * (population[b][k] - population[c][k]);
+                        let (low, high) = bounds[k];
+                        if value < low || value > high { sample(&mut rng, low, high) } else { value }
+                    } else {
+                        population[i][k]
+                    }
+                })
+                .collect();
+
+            let trial_cost = cost(&trial);
+            if trial_cost <= costs[i] {
+                population[i] = trial;
+                costs[i] = trial_cost;
+            }
+        }
+    }
+
+    let best = (0..population_size).min_by(|&a, &b| costs[a].total_cmp(&costs[b]))?;
+    evaluate(&population[best])
+}
+
+fn sample(rng: &mut StdRng, low: f64, high: f64) -> f64 {
+    if high > low { rng.gen_range(low..high) } else { low }
+}
+
+// The tour given by decision vector `x` = [departure epoch, leg durations...]
+fn evaluate_tour(bodies: &[MgaBody], mu: f64, ephemeris: &dyn Fn(usize, f64) -> (Vector3, Vector3), options: &MgaOptions, x: &[f64]) -> Option<MgaSolution> {
+    let mut epochs = vec![x[0]];
+    for duration in &x[1..] {
+        epochs.push(epochs[epochs.len() - 1] + duration);
+    }
+    let states: Vec<(Vector3, Vector3)> = epochs.iter().enumerate().map(|(n, &epoch)| ephemeris(n, epoch)).collect();
+
+    let mut departure_velocities = Vec::new();
+    let mut arrival_velocities = Vec::new();
+    for n in 0..bodies.len() - 1 {
+        let solution = solve_lambert(&states[n].0, &states[n + 1].0, epochs[n + 1] - epochs[n], mu, true, 0).into_iter().next()?;
+        departure_velocities.push(solution.departure_velocity);
+        arrival_velocities.push(solution.arrival_velocity);
+    }
+
+    let departure_v_infinity = departure_velocities[0].subtract(&states[0].1);
+    let last = bodies.len() - 1;
+    let arrival_v_infinity = arrival_velocities[last - 1].subtract(&states[last].1);
+    let mut total_delta_v = departure_v_infinity.magnitude();
+    if options.include_arrival {
+        total_delta_v += arrival_v_infinity.magnitude();
+    }
+
+    let mut flybys = Vec::new();
+    for n in 1..last {
+        let v_infinity_in = arrival_velocities[n - 1].subtract(&states[n].1);
+        let v_infinity_out = departure_velocities[n].subtract(&states[n].1);
+        let minimum_radius = options.minimum_periapsis * bodies[n].radius;
+        let (periapsis_radius, delta_v) = powered_flyby(&v_infinity_in, &v_infinity_out, bodies[n].mu, minimum_radius);
+        total_delta_v += delta_v;
+        flybys.push(PlannedFlyby {
+            epoch: epochs[n],
+            body: bodies[n].name.clone(),
+            b_plane: b_plane_for_turn(&v_infinity_in, &v_infinity_out, periapsis_radius, bodies[n].mu),
+            v_infinity_in,
+            v_infinity_out,
+            periapsis_radius,
+            delta_v,
+        });
+    }
+
+    Some(MgaSolution {
+        body_ids: bodies.iter().map(|body| body.id).collect(),
+        bodies: bodies.iter().map(|body| body.name.clone()).collect(),
+        epochs,
+        departure_velocities,
+        arrival_velocities,
+        departure_v_infinity,
+        arrival_v_infinity,
+        flybys,
+        total_delta_v,
+    })
+}
+
+// Periapsis radius and delta-v of the powered flyby joining the two asymptotes. The
+// bend of each half of the hyperbola is asin(1 / e) with e = 1 + rp v^2 / mu; when the
+// total bend needs a periapsis below `minimum_radius` the missing bend is charged as
+// the velocity rotation it would take.
+pub fn powered_flyby(v_infinity_in: &Vector3, v_infinity_out: &Vector3, mu: f64, minimum_radius: f64) -> (f64, f64) {
+    let (speed_in, speed_out) = (v_infinity_in.magnitude(), v_infinity_out.magnitude());
+    let turn = (v_infinity_in.dot(v_infinity_out) / (speed_in * speed_out)).clamp(-1.0, 1.0).acos();
+    let bend = |radius: f64| {
+        (1.0 / (1.0 + radius * speed_in * speed_in / mu)).asin() + (1.0 / (1.0 + radius * speed_out * speed_out / mu)).asin()
+    };
+
+    let (radius, penalty) = if bend(minimum_radius) <= turn {
+        (minimum_radius, 2.0 * speed_out * ((turn - bend(minimum_radius)) / 2.0).sin())
+    } else {
+        // Bisection in log radius: the bend falls monotonically with the radius
+        let (mut low, mut high) = (minimum_radius.ln(), (minimum_radius * 1e8).ln());
+        for _ in 0..BISECTION_ITERATIONS {
+            let middle = (low + high) / 2.0;
+            if bend(middle.exp()) > turn { low = middle } else { high = middle }
+        }
+        (((low + high) / 2.0).exp(), 0.0)
+    };
+
+    let periapsis_speed = |speed: f64| (speed * speed + 2.0 * mu / radius).sqrt();
+    (radius, (periapsis_speed(speed_out) - periapsis_speed(speed_in)).abs() + penalty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::{BodyType, CelestialBody};
+    use crate::integrators::IntegratorType;
+    use crate::physics::GRAVITATIONAL_CONST;
+    use crate::solar_system::SolarSystem;
+
+    const VENUS_MU: f64 = GRAVITATIONAL_CONST * 4.8675e24;
+
+    fn date(year: i64, month: u32, day: u32) -> f64 {
+        CalendarDate { year, month, day, hour: 0, minute: 0, second: 0.0 }.to_time()
+    }
+
+    #[test]
+    fn unpowered_flyby_keeps_its_turn() {
+        // Bending 6 km/s by the turn of a 9000 km periapsis hyperbola needs no burn
+        let (speed, radius) = (6000.0, 9.0e6);
+        let turn = 2.0 * (1.0 / (1.0 + radius * speed * speed / VENUS_MU)).asin();
+        let v_infinity_in = Vector3::new(speed, 0.0, 0.0);
+        let v_infinity_out = Vector3::new(turn.cos(), turn.sin() * 0.6, turn.sin() * 0.8).scale(speed);
+
+        let (periapsis_radius, delta_v) = powered_flyby(&v_infinity_in, &v_infinity_out, VENUS_MU, 6.1e6);
+        assert!((periapsis_radius - radius).abs() < 1e-6 * radius, "periapsis {} m", periapsis_radius);
+        assert!(delta_v < 1e-6, "delta-v {} m/s", delta_v);
+
+        // Leaving 100 m/s faster costs the difference in periapsis speeds
+        let (periapsis_radius, delta_v) = powered_flyby(&v_infinity_in, &v_infinity_out.scale(6100.0 / 6000.0), VENUS_MU, 6.1e6);
+        let periapsis_speed = |v: f64| (v * v + 2.0 * VENUS_MU / periapsis_radius).sqrt();
+        assert!((delta_v - (periapsis_speed(6100.0) - periapsis_speed(6000.0))).abs() < 1e-9);
+    }
+
+    #[test]
+    fn earth_venus_mars_tour_reaches_mars() {
+        let start = date(2021, 10, 1);
+        let mut system = SolarSystem::new(1800.0, IntegratorType::RK4(1));
+        system.time = start;
+        for (name, body_type, radius, mass) in [
+            ("Sun", BodyType::Star, 695_700.0, 1.989e30),
+            ("Earth", BodyType::Planet, 6371.0, 5.972e24),
+            ("Venus", BodyType::Planet, 6051.8, 4.8675e24),
+            ("Mars", BodyType::Planet, 3389.5, 6.417e23),
+        ] {
+            system.add_body(CelestialBody::new(
+                name.to_string(), body_type, Vector3::new(0.0, 0.0, 0.0), radius, mass, Vector3::new(0.0, 0.0, 0.0), [1.0, 1.0, 1.0],
+            ));
+        }
+        system.place_planets(start);
+
+        let mut options = MgaOptions::new(
+            (start, date(2021, 12, 15)),
+            vec![(100.0 * time::SECONDS_PER_DAY, 200.0 * time::SECONDS_PER_DAY), (120.0 * time::SECONDS_PER_DAY, 250.0 * time::SECONDS_PER_DAY)],
+        );
+        options.population = 40;
+        options.generations = 300;
+        let solution = system.optimize_mga(&[1, 2, 3], &options).unwrap();
+        assert_eq!(solution.flybys.len(), 1);
+
+        system.run_until(solution.epochs[0]);
+        let probe = CelestialBody::new(
+            "Probe".to_string(), BodyType::Satellite, Vector3::new(0.0, 0.0, 0.0), 0.001, 1000.0, Vector3::new(0.0, 0.0, 0.0), [1.0, 1.0, 1.0],
+        );
+        let results = system.inject_mga(&solution, probe);
+        assert!(results.iter().all(|result| result.converged));
+
+        // The Venus flyby is flown as planned, and the single Lambert correction of the
+        // last leg brings the probe well inside Mars's 580 000 km sphere of influence
+        system.run_until(solution.epochs[2]);
+        let flyby = &system.flyby_log[0];
+        assert_eq!(flyby.body, "Venus");
+        assert!((flyby.time - solution.flybys[0].epoch).abs() < 60.0);
+        let bodies = system.get_bodies();
+        let miss = bodies[4].position.subtract(&bodies[3].position).magnitude();
+        assert!(miss < 1.5e8, "missed Mars by {} km", miss / 1e3);
+    }
+}
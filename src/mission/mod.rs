@@ -0,0 +1,17 @@
+mod burn;
+mod thrust;
+mod lambert;
+mod transfer;
+mod porkchop;
+mod maneuver;
+mod flyby;
+mod mga;
+
+pub use self::burn::{BurnFrame, ImpulsiveBurn};
+pub use self::thrust::{SteeringLaw, ThrustArc, STANDARD_GRAVITY};
+pub use self::lambert::{solve_lambert, LambertSolution};
+pub use self::transfer::{sphere_of_influence, Transfer};
+pub use self::porkchop::{epoch_range, PorkchopGrid, PorkchopQuantity};
+pub use self::maneuver::{plan_maneuver, ManeuverKind, ManeuverPlan, ManeuverTarget};
+pub use self::flyby::{b_plane, b_plane_for_turn, flyby_from_state, BPlane, BPlaneTarget, Flyby, TargetingResult};
+pub use self::mga::{optimize_mga, powered_flyby, MgaBody, MgaOptions, MgaSolution, PlannedFlyby};
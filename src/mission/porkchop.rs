@@ -0,0 +1,293 @@
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use crate::time::{self, CalendarDate};
+use super::Transfer;
+
+const WIDTH: f64 = 900.0;
+const HEIGHT: f64 = 700.0;
+const MARGIN: f64 = 80.0;
+const LEGEND_WIDTH: f64 = 140.0;
+const DEFAULT_LEVELS: usize = 12;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PorkchopQuantity {
+    C3,                     // km^2/s^2
+    DepartureVInfinity,     // km/s
+    ArrivalVInfinity,       // km/s
+    TotalDeltaV,            // km/s, departure plus arrival v-infinity
+}
+
+impl PorkchopQuantity {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PorkchopQuantity::C3 => "C3 (km^2/s^2)",
+            PorkchopQuantity::DepartureVInfinity => "Departure v-infinity (km/s)",
+            PorkchopQuantity::ArrivalVInfinity => "Arrival v-infinity (km/s)",
+            PorkchopQuantity::TotalDeltaV => "Total delta-v (km/s)",
+        }
+    }
+
+    fn of(&self, transfer: &Transfer) -> f64 {
+        match self {
+            PorkchopQuantity::C3 => transfer.c3() / 1e6,
+            PorkchopQuantity::DepartureVInfinity => transfer.departure_delta_v.magnitude() / 1e3,
+            PorkchopQuantity::ArrivalVInfinity => transfer.arrival_delta_v.magnitude() / 1e3,
+            PorkchopQuantity::TotalDeltaV => transfer.total_delta_v() / 1e3,
+        }
+    }
+}
+
+// Zero-revolution transfers over a grid of departure and arrival epochs. Each row of
+// `transfers` is one departure epoch; cells where the arrival is not after the departure
+// or the Lambert problem has no solution are `None`.
+#[derive(Clone, Debug)]
+pub struct PorkchopGrid {
+    pub departure_body: String,
+    pub arrival_body: String,
+    pub departures: Vec<f64>,
+    pub arrivals: Vec<f64>,
+    pub transfers: Vec<Vec<Option<Transfer>>>,
+}
+
+impl PorkchopGrid {
+    // NaN where there is no transfer
+    pub fn values(&self, quantity: PorkchopQuantity) -> Vec<Vec<f64>> {
+        self.transfers.iter()
+            .map(|row| row.iter().map(|cell| cell.as_ref().map_or(f64::NAN, |transfer| quantity.of(transfer))).collect())
+            .collect()
+    }
+
+    // Transfer minimising `quantity`
+    pub fn best(&self, quantity: PorkchopQuantity) -> Option<&Transfer> {
+        self.transfers.iter()
+            .flatten()
+            .flatten()
+            .min_by(|a, b| quantity.of(a).total_cmp(&quantity.of(b)))
+    }
+
+    // One line per grid cell, epochs as calendar dates and seconds past J2000
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("departure_date,arrival_date,departure_time,arrival_time,time_of_flight_days,c3_km2_s2,departure_vinf_km_s,arrival_vinf_km_s,total_delta_v_km_s\n");
+        for (i, &departure) in self.departures.iter().enumerate() {
+            for (j, &arrival) in self.arrivals.iter().enumerate() {
+                let quantities = [
+                    PorkchopQuantity::C3,
+                    PorkchopQuantity::DepartureVInfinity,
+                    PorkchopQuantity::ArrivalVInfinity,
+                    PorkchopQuantity::TotalDeltaV,
+                ].map(|quantity| self.transfers[i][j].as_ref().map_or(String::new(), |transfer| format!("{:.6}", quantity.of(transfer))));
+                let _ = writeln!(
+                    csv,
+                    "{},{},{:.3},{:.3},{:.4},{}",
+                    CalendarDate::from_time(departure).date_string(),
+                    CalendarDate::from_time(arrival).date_string(),
+                    departure,
+                    arrival,
+                    (arrival - departure) / time::SECONDS_PER_DAY,
+                    quantities.join(","),
+                );
+            }
+        }
+        csv
+    }
+
+    pub fn write_csv(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_csv())
+    }
+
+    // Evenly spaced levels from the minimum of `quantity` up to its median, which keeps
+    // the contours on the interesting part of the plot
+    pub fn default_levels(&self, quantity: PorkchopQuantity) -> Vec<f64> {
+        let mut values: Vec<f64> = self.values(quantity).into_iter().flatten().filter(|v| v.is_finite()).collect();
+        if values.is_empty() {
+            return Vec::new();
+        }
+        values.sort_by(|a, b| a.total_cmp(b));
+        let (low, high) = (values[0], values[values.len() / 2]);
+        let step = (high - low) / DEFAULT_LEVELS as f64;
+        if step <= 0.0 {
+            return vec![low];
+        }
+        (1..=DEFAULT_LEVELS).map(|n| low + step * n as f64).collect()
+    }
+
+    // Contour plot of `quantity` with departure date along x and arrival date along y.
+    // Uses `default_levels` when `levels` is empty.
+    pub fn to_svg(&self, quantity: PorkchopQuantity, levels: &[f64]) -> String {
+        let levels = if levels.is_empty() { self.default_levels(quantity) } else { levels.to_vec() };
+        let values = self.values(quantity);
+        let plot_width = WIDTH - 2.0 * MARGIN - LEGEND_WIDTH;
+        let plot_height = HEIGHT - 2.0 * MARGIN;
+
+        let (x_min, x_max) = range(&self.departures);
+        let (y_min, y_max) = range(&self.arrivals);
+        let x = |t: f64| MARGIN + (t - x_min) / (x_max - x_min).max(f64::MIN_POSITIVE) * plot_width;
+        let y = |t: f64| HEIGHT - MARGIN - (t - y_min) / (y_max - y_min).max(f64::MIN_POSITIVE) * plot_height;
+
+        let mut svg = String::new();
+        let _ = writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" font-family="sans-serif" font-size="12">"#);
+        let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#);
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{}" text-anchor="middle" font-size="16">{} to {}: {}</text>"#,
+            MARGIN + plot_width / 2.0, MARGIN / 2.0, self.departure_body, self.arrival_body, quantity.label()
+        );
+
+        // Contours
+        for (n, &level) in levels.iter().enumerate() {
+            let colour = level_colour(n, levels.len());
+            let mut path = String::new();
+            for i in 0..self.departures.len().saturating_sub(1) {
+                for j in 0..self.arrivals.len().saturating_sub(1) {
+                    let corners = [
+                        (self.departures[i], self.arrivals[j], values[i][j]),
+                        (self.departures[i + 1], self.arrivals[j], values[i + 1][j]),
+                        (self.departures[i + 1], self.arrivals[j + 1], values[i + 1][j + 1]),
+                        (self.departures[i], self.arrivals[j + 1], values[i][j + 1]),
+                    ];
+                    for ((x0, y0), (x1, y1)) in contour_cell(&corners, level) {
+                        let _ = write!(path, "M{:.1},{:.1}L{:.1},{:.1}", x(x0), y(y0), x(x1), y(y1));
+                    }
+                }
+            }
+            let _ = writeln!(svg, r#"<path d="{path}" stroke="{colour}" stroke-width="1.5" fill="none"/>"#);
+            let legend_y = MARGIN + 20.0 * n as f64;
+            let legend_x = WIDTH - MARGIN - LEGEND_WIDTH + 30.0;
+            let _ = writeln!(svg, r#"<line x1="{legend_x}" y1="{legend_y}" x2="{}" y2="{legend_y}" stroke="{colour}" stroke-width="3"/>"#, legend_x + 25.0);
+            let _ = writeln!(svg, r#"<text x="{}" y="{}">{level:.2}</text>"#, legend_x + 32.0, legend_y + 4.0);
+        }
+
+        // Axes with calendar-date ticks
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{MARGIN}" y="{MARGIN}" width="{plot_width}" height="{plot_height}" stroke="black" fill="none"/>"#
+        );
+        for tick in ticks(x_min, x_max) {
+            let _ = writeln!(svg, r#"<line x1="{0:.1}" y1="{1}" x2="{0:.1}" y2="{2}" stroke="black"/>"#, x(tick), HEIGHT - MARGIN, HEIGHT - MARGIN + 5.0);
+            let _ = writeln!(
+                svg,
+                r#"<text x="{:.1}" y="{}" text-anchor="middle">{}</text>"#,
+                x(tick), HEIGHT - MARGIN + 20.0, CalendarDate::from_time(tick).date_string()
+            );
+        }
+        for tick in ticks(y_min, y_max) {
+            let _ = writeln!(svg, r#"<line x1="{}" y1="{1:.1}" x2="{2}" y2="{1:.1}" stroke="black"/>"#, MARGIN - 5.0, y(tick), MARGIN);
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="{:.1}" text-anchor="end">{}</text>"#,
+                MARGIN - 8.0, y(tick) + 4.0, CalendarDate::from_time(tick).date_string()
+            );
+        }
+        let _ = writeln!(svg, r#"<text x="{}" y="{}" text-anchor="middle">Departure date</text>"#, MARGIN + plot_width / 2.0, HEIGHT - MARGIN / 3.0);
+        let _ = writeln!(
+            svg,
+            r#"<text x="{0}" y="{1}" text-anchor="middle" transform="rotate(-90 {0} {1})">Arrival date</text>"#,
+            MARGIN / 4.0, MARGIN + plot_height / 2.0
+        );
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    pub fn write_svg(&self, path: &str, quantity: PorkchopQuantity, levels: &[f64]) -> io::Result<()> {
+        fs::write(path, self.to_svg(quantity, levels))
+    }
+}
+
+// `count` evenly spaced epochs from `start` to `end` inclusive
+pub fn epoch_range(start: f64, end: f64, count: usize) -> Vec<f64> {
+    match count {
+        0 => Vec::new(),
+        1 => vec![start],
+        _ => (0..count).map(|n| start + (end - start) * n as f64 / (count - 1) as f64).collect(),
+    }
+}
+
+fn range(values: &[f64]) -> (f64, f64) {
+    values.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), &v| (low.min(v), high.max(v)))
+}
+
+// About six tick positions between `low` and `high`, on whole days
+fn ticks(low: f64, high: f64) -> Vec<f64> {
+    let step = ((high - low) / 6.0 / time::SECONDS_PER_DAY).ceil().max(1.0) * time::SECONDS_PER_DAY;
+    let first = (low / step).ceil() * step;
+    (0..).map(|n| first + step * n as f64).take_while(|&t| t <= high).collect()
+}
+
+// Blue for the lowest level through to red for the highest
+fn level_colour(index: usize, count: usize) -> String {
+    let s = if count > 1 { index as f64 / (count - 1) as f64 } else { 0.0 };
+    format!("rgb({},{},{})", (255.0 * s) as u8, (60.0 + 80.0 * (1.0 - (2.0 * s - 1.0).abs())) as u8, (255.0 * (1.0 - s)) as u8)
+}
+
+// Marching squares on one cell with corners in counter-clockwise order, returning the
+// segments where the interpolated surface crosses `level`. Cells touching a missing
+// value are skipped; saddles are split the same way every time.
+fn contour_cell(corners: &[(f64, f64, f64); 4], level: f64) -> Vec<((f64, f64), (f64, f64))> {
+    if corners.iter().any(|c| !c.2.is_finite()) {
+        return Vec::new();
+    }
+    let crossing = |a: usize, b: usize| {
+        let (pa, pb) = (corners[a], corners[b]);
+        let s = (level - pa.2) / (pb.2 - pa.2);
+        (pa.0 + s * (pb.0 - pa.0), pa.1 + s * (pb.1 - pa.1))
+    };
+
+    let above: Vec<bool> = corners.iter().map(|c| c.2 > level).collect();
+    let edges: Vec<(usize, usize)> = (0..4)
+        .map(|n| (n, (n + 1) % 4))
+        .filter(|&(a, b)| above[a] != above[b])
+        .collect();
+
+    match edges.len() {
+        2 => vec![(crossing(edges[0].0, edges[0].1), crossing(edges[1].0, edges[1].1))],
+        4 => vec![
+            (crossing(edges[0].0, edges[0].1), crossing(edges[1].0, edges[1].1)),
+            (crossing(edges[2].0, edges[2].1), crossing(edges[3].0, edges[3].1)),
+        ],
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::{BodyType, CelestialBody};
+    use crate::geometry::Vector3;
+    use crate::integrators::IntegratorType;
+    use crate::solar_system::SolarSystem;
+
+    fn date(year: i64, month: u32, day: u32) -> f64 {
+        CalendarDate { year, month, day, hour: 0, minute: 0, second: 0.0 }.to_time()
+    }
+
+    #[test]
+    fn earth_to_mars_2005() {
+        // The 2005 opportunity (Mars Reconnaissance Orbiter) bottoms out at a C3 of about
+        // 16 km^2/s^2 for an August 2005 departure and a February 2006 arrival
+        let start = date(2005, 6, 1);
+        let mut system = SolarSystem::new(time::SECONDS_PER_DAY, IntegratorType::RK4(1));
+        system.time = start;
+        for (name, body_type, radius, mass) in [
+            ("Sun", BodyType::Star, 695_700.0, 1.989e30),
+            ("Earth", BodyType::Planet, 6371.0, 5.972e24),
+            ("Mars", BodyType::Planet, 3389.5, 6.417e23),
+        ] {
+            system.add_body(CelestialBody::new(
+                name.to_string(), body_type, Vector3::new(0.0, 0.0, 0.0), radius, mass, Vector3::new(0.0, 0.0, 0.0), [1.0, 1.0, 1.0],
+            ));
+        }
+        system.place_planets(start);
+
+        let departures = epoch_range(start, date(2005, 11, 1), 78);
+        let arrivals = epoch_range(date(2005, 12, 1), date(2006, 8, 1), 81);
+        let grid = system.porkchop(1, 2, &departures, &arrivals);
+        let best = grid.best(PorkchopQuantity::C3).unwrap();
+
+        let c3 = best.c3() / 1e6;
+        let (departure, arrival) = (CalendarDate::from_time(best.departure_epoch), CalendarDate::from_time(best.arrival_epoch));
+        assert!((c3 - 16.0).abs() < 1.0, "C3 {} km^2/s^2 leaving {} and arriving {}", c3, departure, arrival);
+        assert_eq!((departure.year, departure.month), (2005, 8), "left {}", departure);
+        assert!(arrival.year == 2006 && (1..=3).contains(&arrival.month), "arrived {}", arrival);
+    }
+}
@@ -0,0 +1,58 @@
+use crate::geometry::Vector3;
+
+pub const STANDARD_GRAVITY: f64 = 9.806_65;    // m/s^2, used to convert Isp to exhaust velocity
+
+#[derive(Clone, Debug)]
+pub enum SteeringLaw {
+    Prograde,                           // along the velocity relative to the primary
+    Retrograde,
+    Inertial(Vector3),                  // fixed inertial direction
+    Tabulated(Vec<(f64, Vector3)>),     // (epoch, inertial direction), linearly interpolated
+}
+
+#[derive(Clone, Debug)]
+pub struct ThrustArc {
+    pub start: f64,             // simulation time in seconds
+    pub end: f64,
+    pub thrust: f64,            // N
+    pub isp: f64,               // s
+    pub steering: SteeringLaw,
+}
+
+impl ThrustArc {
+    pub fn new(start: f64, end: f64, thrust: f64, isp: f64, steering: SteeringLaw) -> Self {
+        ThrustArc { start, end, thrust, isp, steering }
+    }
+
+    pub fn is_active(&self, time: f64) -> bool {
+        time >= self.start && time < self.end
+    }
+
+    pub fn mass_flow_rate(&self) -> f64 {
+        self.thrust / (self.isp * STANDARD_GRAVITY)
+    }
+
+    // Unit thrust direction at `time`; `velocity` is relative to the primary
+    pub fn direction(&self, time: f64, velocity: &Vector3) -> Vector3 {
+        match &self.steering {
+            SteeringLaw::Prograde => velocity.norm(),
+            SteeringLaw::Retrograde => velocity.norm().scale(-1.0),
+            SteeringLaw::Inertial(direction) => direction.norm(),
+            SteeringLaw::Tabulated(table) => interpolate_direction(table, time),
+        }
+    }
+}
+
+fn interpolate_direction(table: &[(f64, Vector3)], time: f64) -> Vector3 {
+    let after = table.iter().position(|(epoch, _)| *epoch > time);
+    match after {
+        Some(0) => table[0].1.norm(),
+        Some(i) => {
+            let (t0, d0) = &table[i - 1];
+            let (t1, d1) = &table[i];
+            let fraction = (time - t0) / (t1 - t0);
+            d0.scale(1.0 - fraction).add(&d1.scale(fraction)).norm()
+        },
+        None => table.last().map(|(_, direction)| direction.norm()).unwrap_or(Vector3::new(0.0, 0.0, 0.0)),
+    }
+}
@@ -0,0 +1,51 @@
+use crate::geometry::Vector3;
+use crate::orbit;
+use super::LambertSolution;
+
+// A Lambert arc between two bodies, solved about their common primary. Positions and
+// velocities are relative to that primary; delta-v vectors are inertial.
+#[derive(Clone, Debug)]
+pub struct Transfer {
+    pub departure_body: usize,      // body ids
+    pub arrival_body: usize,
+    pub central_body: usize,
+    pub departure_epoch: f64,
+    pub arrival_epoch: f64,
+    pub departure_position: Vector3,
+    pub arrival_position: Vector3,
+    pub solution: LambertSolution,
+    pub departure_delta_v: Vector3,     // hyperbolic excess velocity leaving the departure body
+    pub arrival_delta_v: Vector3,       // velocity change needed to match the arrival body
+    pub mu: f64,                        // of the central body
+}
+
+impl Transfer {
+    pub fn total_delta_v(&self) -> f64 {
+        self.departure_delta_v.magnitude() + self.arrival_delta_v.magnitude()
+    }
+
+    // Characteristic energy of the departure, m^2/s^2
+    pub fn c3(&self) -> f64 {
+        self.departure_delta_v.dot(&self.departure_delta_v)
+    }
+
+    pub fn time_of_flight(&self) -> f64 {
+        self.arrival_epoch - self.departure_epoch
+    }
+
+    // State on the arc at `epoch`, relative to the central body
+    pub fn state_at(&self, epoch: f64) -> (Vector3, Vector3) {
+        orbit::propagate_kepler(
+            &self.departure_position,
+            &self.solution.departure_velocity,
+            self.mu,
+            epoch - self.departure_epoch,
+        )
+    }
+}
+
+// Laplace sphere of influence of a body of `mass` orbiting a primary of `primary_mass`
+// at `distance`
+pub fn sphere_of_influence(distance: f64, mass: f64, primary_mass: f64) -> f64 {
+    distance * (mass / primary_mass).powf(0.4)
+}
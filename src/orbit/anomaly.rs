@@ -0,0 +1,68 @@
+use std::f64::consts::PI;
+
+const TOLERANCE: f64 = 1e-14;
+const MAX_ITERATIONS: usize = 50;
+
+// Conversions between true anomaly and mean anomaly for every conic. The mean
+// anomaly of a parabola is Barker's D + D^3 / 3 with D = tan(nu / 2).
+
+pub fn true_to_mean_anomaly(true_anomaly: f64, eccentricity: f64) -> f64 {
+    let half = true_anomaly / 2.0;
+    if eccentricity < 1.0 {
+        let eccentric = 2.0 * (((1.0 - eccentricity) / (1.0 + eccentricity)).sqrt() * half.sin()).atan2(half.cos());
+        eccentric - eccentricity * eccentric.sin()
+    } else if eccentricity > 1.0 {
+        let hyperbolic = 2.0 * (((eccentricity - 1.0) / (eccentricity + 1.0)).sqrt() * half.tan()).atanh();
+        eccentricity * hyperbolic.sinh() - hyperbolic
+    } else {
+        let d = half.tan();
+        d + d.powi(3) / 3.0
+    }
+}
+
+pub fn mean_to_true_anomaly(mean_anomaly: f64, eccentricity: f64) -> f64 {
+    if eccentricity < 1.0 {
+        let eccentric = solve_kepler_elliptic(mean_anomaly, eccentricity);
+        let half = eccentric / 2.0;
+        2.0 * (((1.0 + eccentricity) / (1.0 - eccentricity)).sqrt() * half.sin()).atan2(half.cos())
+    } else if eccentricity > 1.0 {
+        let hyperbolic = solve_kepler_hyperbolic(mean_anomaly, eccentricity);
+        2.0 * (((eccentricity + 1.0) / (eccentricity - 1.0)).sqrt() * (hyperbolic / 2.0).tanh()).atan()
+    } else {
+        2.0 * solve_barker(mean_anomaly).atan()
+    }
+}
+
+// Eccentric anomaly E from M = E - e sin E
+pub fn solve_kepler_elliptic(mean_anomaly: f64, eccentricity: f64) -> f64 {
+    let mean_anomaly = mean_anomaly.rem_euclid(2.0 * PI);
+    let mut eccentric = if eccentricity < 0.8 { mean_anomaly } else { PI };
+    for _ in 0..MAX_ITERATIONS {
+        let correction = (eccentric - eccentricity * eccentric.sin() - mean_anomaly) / (1.0 - eccentricity * eccentric.cos());
+        eccentric -= correction;
+        if correction.abs() < TOLERANCE {
+            break;
+        }
+    }
+    eccentric
+}
+
+// Hyperbolic anomaly H from M = e sinh H - H
+pub fn solve_kepler_hyperbolic(mean_anomaly: f64, eccentricity: f64) -> f64 {
+    let mut hyperbolic = (mean_anomaly / eccentricity).asinh();
+    for _ in 0..MAX_ITERATIONS {
+        let correction = (eccentricity * hyperbolic.sinh() - hyperbolic - mean_anomaly) / (eccentricity * hyperbolic.cosh() - 1.0);
+        hyperbolic -= correction;
+        if correction.abs() < TOLERANCE * hyperbolic.abs().max(1.0) {
+            break;
+        }
+    }
+    hyperbolic
+}
+
+// D = tan(nu / 2) from Barker's equation M = D + D^3 / 3, solved in closed form
+pub fn solve_barker(mean_anomaly: f64) -> f64 {
+    let b = 1.5 * mean_anomaly;
+    let z = (b + (1.0 + b * b).sqrt()).cbrt();
+    z - 1.0 / z
+}
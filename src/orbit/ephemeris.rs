@@ -0,0 +1,107 @@
+use crate::physics::ASTRONOMICAL_UNIT;
+use crate::time;
+use super::KeplerianElements;
+
+// JPL approximate heliocentric elements of the major planets (Standish, "Keplerian
+// Elements for Approximate Positions of the Major Planets", table 1, valid 1800-2050),
+// referred to the J2000 ecliptic and equinox. Both arrays hold a (AU), e, I, L, long.
+// peri., long. node in degrees; `rates` are per Julian century. Earth's entry is the
+// Earth-Moon barycentre.
+#[derive(Clone, Debug)]
+pub struct PlanetaryElements {
+    pub elements: [f64; 6],
+    pub rates: [f64; 6],
+}
+
+impl PlanetaryElements {
+    pub fn new(elements: [f64; 6], rates: [f64; 6]) -> Self {
+        PlanetaryElements { elements, rates }
+    }
+
+    pub fn mercury() -> Self {
+        PlanetaryElements::new(
+            [0.387_099_27, 0.205_635_93, 7.004_979_02, 252.250_323_50, 77.457_796_28, 48.330_765_93],
+            [0.000_000_37, 0.000_019_06, -0.005_947_49, 149_472.674_111_75, 0.160_476_89, -0.125_340_81],
+        )
+    }
+
+    pub fn venus() -> Self {
+        PlanetaryElements::new(
+            [0.723_335_66, 0.006_776_72, 3.394_676_05, 181.979_099_50, 131.602_467_18, 76.679_842_55],
+            [0.000_003_90, -0.000_041_07, -0.000_788_90, 58_517.815_387_29, 0.002_683_29, -0.277_694_18],
+        )
+    }
+
+    pub fn earth() -> Self {
+        PlanetaryElements::new(
+            [1.000_002_61, 0.016_711_23, -0.000_015_31, 100.464_571_66, 102.937_681_93, 0.0],
+            [0.000_005_62, -0.000_043_92, -0.012_946_68, 35_999.372_449_81, 0.323_273_64, 0.0],
+        )
+    }
+
+    pub fn mars() -> Self {
+        PlanetaryElements::new(
+            [1.523_710_34, 0.093_394_10, 1.849_691_42, -4.553_432_05, -23.943_629_59, 49.559_538_91],
+            [0.000_018_47, 0.000_078_82, -0.008_131_31, 19_140.302_684_99, 0.444_410_88, -0.292_573_43],
+        )
+    }
+
+    pub fn jupiter() -> Self {
+        PlanetaryElements::new(
+            [5.202_887_00, 0.048_386_24, 1.304_396_95, 34.396_440_51, 14.728_479_83, 100.473_909_09],
+            [-0.000_116_07, -0.000_132_53, -0.001_837_14, 3_034.746_127_75, 0.212_526_68, 0.204_691_06],
+        )
+    }
+
+    pub fn saturn() -> Self {
+        PlanetaryElements::new(
+            [9.536_675_94, 0.053_861_79, 2.485_991_87, 49.954_244_23, 92.598_878_31, 113.662_424_48],
+            [-0.001_250_60, -0.000_509_91, 0.001_936_09, 1_222.493_622_01, -0.418_972_16, -0.288_677_94],
+        )
+    }
+
+    pub fn uranus() -> Self {
+        PlanetaryElements::new(
+            [19.189_164_64, 0.047_257_44, 0.772_637_83, 313.238_104_51, 170.954_276_30, 74.016_925_03],
+            [-0.001_961_76, -0.000_043_97, -0.002_429_39, 428.482_027_85, 0.408_052_81, 0.042_405_89],
+        )
+    }
+
+    pub fn neptune() -> Self {
+        PlanetaryElements::new(
+            [30.069_922_76, 0.008_590_48, 1.770_043_47, -55.120_029_69, 44.964_762_27, 131.784_225_74],
+            [0.000_262_91, 0.000_051_05, 0.000_353_72, 218.459_453_25, -0.322_414_64, -0.005_086_64],
+        )
+    }
+
+    pub fn for_body(name: &str) -> Option<Self> {
+        match name {
+            "Mercury" => Some(PlanetaryElements::mercury()),
+            "Venus" => Some(PlanetaryElements::venus()),
+            "Earth" => Some(PlanetaryElements::earth()),
+            "Mars" => Some(PlanetaryElements::mars()),
+            "Jupiter" => Some(PlanetaryElements::jupiter()),
+            "Saturn" => Some(PlanetaryElements::saturn()),
+            "Uranus" => Some(PlanetaryElements::uranus()),
+            "Neptune" => Some(PlanetaryElements::neptune()),
+            _ => None,
+        }
+    }
+
+    // Osculating heliocentric elements at `time` (seconds past J2000)
+    pub fn at(&self, time: f64) -> KeplerianElements {
+        let centuries = time::centuries_since_j2000(time);
+        let value = |n: usize| self.elements[n] + self.rates[n] * centuries;
+
+        let perihelion_longitude = value(4);
+        let node = value(5);
+        KeplerianElements::from_mean_anomaly(
+            value(0) * ASTRONOMICAL_UNIT,
+            value(1),
+            value(2).to_radians(),
+            node.to_radians(),
+            (perihelion_longitude - node).to_radians(),
+            (value(3) - perihelion_longitude).to_radians(),
+        )
+    }
+}
@@ -0,0 +1,259 @@
+use std::f64::consts::PI;
+use crate::geometry::Vector3;
+use super::anomaly;
+use super::KeplerianElements;
+
+// Element sets that stay regular for circular and equatorial orbits. Both use the
+// prograde formulation and are singular for retrograde equatorial orbits (i = pi).
+
+// Broucke & Cefola (1972) equinoctial elements. Defined for elliptic and hyperbolic
+// orbits only; the mean longitude uses the hyperbolic mean anomaly when e > 1.
+#[derive(Clone, Debug)]
+pub struct EquinoctialElements {
+    pub semi_major_axis: f64,           // m, negative for hyperbolas
+    pub h: f64,                         // e sin(longitude of periapsis)
+    pub k: f64,                         // e cos(longitude of periapsis)
+    pub p: f64,                         // tan(i / 2) sin(raan)
+    pub q: f64,                         // tan(i / 2) cos(raan)
+    pub mean_longitude: f64,
+}
+
+// Walker, Ireland & Owens (1985) modified equinoctial elements, valid for every conic
+#[derive(Clone, Debug)]
+pub struct ModifiedEquinoctialElements {
+    pub semi_latus_rectum: f64,         // m
+    pub f: f64,                         // e cos(longitude of periapsis)
+    pub g: f64,                         // e sin(longitude of periapsis)
+    pub h: f64,                         // tan(i / 2) cos(raan)
+    pub k: f64,                         // tan(i / 2) sin(raan)
+    pub true_longitude: f64,
+}
+
+impl ModifiedEquinoctialElements {
+    pub fn new(semi_latus_rectum: f64, f: f64, g: f64, h: f64, k: f64, true_longitude: f64) -> Self {
+        ModifiedEquinoctialElements { semi_latus_rectum, f, g, h, k, true_longitude }
+    }
+
+    pub fn from_keplerian(elements: &KeplerianElements) -> Self {
+        let periapsis_longitude = elements.raan + elements.argument_of_periapsis;
+        let node_scale = (elements.inclination / 2.0).tan();
+        ModifiedEquinoctialElements {
+            semi_latus_rectum: elements.semi_latus_rectum,
+            f: elements.eccentricity * periapsis_longitude.cos(),
+            g: elements.eccentricity * periapsis_longitude.sin(),
+            h: node_scale * elements.raan.cos(),
+            k: node_scale * elements.raan.sin(),
+            true_longitude: (periapsis_longitude + elements.true_anomaly).rem_euclid(2.0 * PI),
+        }
+    }
+
+    // Undefined angles are resolved with the same conventions as KeplerianElements::from_state
+    pub fn to_keplerian(&self) -> KeplerianElements {
+        let eccentricity = self.f.hypot(self.g);
+        let raan = self.k.atan2(self.h).rem_euclid(2.0 * PI);
+        let periapsis_longitude = if eccentricity > 0.0 { self.g.atan2(self.f) } else { raan };
+        let true_anomaly = (self.true_longitude - periapsis_longitude).rem_euclid(2.0 * PI);
+
+        KeplerianElements {
+            semi_latus_rectum: self.semi_latus_rectum,
+            eccentricity,
+            inclination: 2.0 * self.h.hypot(self.k).atan(),
+            raan,
+            argument_of_periapsis: (periapsis_longitude - raan).rem_euclid(2.0 * PI),
+            true_anomaly: if eccentricity >= 1.0 && true_anomaly > PI { true_anomaly - 2.0 * PI } else { true_anomaly },
+        }
+    }
+
+    pub fn from_state(position: &Vector3, velocity: &Vector3, mu: f64) -> Self {
+        ModifiedEquinoctialElements::from_keplerian(&KeplerianElements::from_state(position, velocity, mu))
+    }
+
+    pub fn to_state(&self, mu: f64) -> (Vector3, Vector3) {
+        let (sin, cos) = self.true_longitude.sin_cos();
+        let (f, g, h, k) = (self.f, self.g, self.h, self.k);
+        let alpha_squared = h * h - k * k;
+        let s_squared = 1.0 + h * h + k * k;
+        let radius = self.semi_latus_rectum / (1.0 + f * cos + g * sin);
+        let speed = (mu / self.semi_latus_rectum).sqrt() / s_squared;
+
+        let position = Vector3::new(
+            cos + alpha_squared * cos + 2.0 * h * k * sin,
+            sin - alpha_squared * sin + 2.0 * h * k * cos,
+            2.0 * (h * sin - k * cos),
+        ).scale(radius / s_squared);
+
+        let velocity = Vector3::new(
+            -(sin + alpha_squared * sin - 2.0 * h * k * cos + g - 2.0 * f * h * k + alpha_squared * g),
+            -(-cos + alpha_squared * cos + 2.0 * h * k * sin - f + 2.0 * g * h * k + alpha_squared * f),
+            2.0 * (h * cos + k * sin + f * h + g * k),
+        ).scale(speed);
+
+        (position, velocity)
+    }
+
+    pub fn eccentricity(&self) -> f64 {
+        self.f.hypot(self.g)
+    }
+
+    pub fn inclination(&self) -> f64 {
+        2.0 * self.h.hypot(self.k).atan()
+    }
+}
+
+impl EquinoctialElements {
+    pub fn new(semi_major_axis: f64, h: f64, k: f64, p: f64, q: f64, mean_longitude: f64) -> Self {
+        EquinoctialElements { semi_major_axis, h, k, p, q, mean_longitude }
+    }
+
+    // Parabolic orbits have no finite semi-major axis and give an infinite one here.
+    // The mean longitude is only wrapped for ellipses; on a hyperbola it grows without
+    // bound and is negative on the inbound leg.
+    pub fn from_modified(elements: &ModifiedEquinoctialElements) -> Self {
+        let eccentricity = elements.eccentricity();
+        let periapsis_longitude = elements.g.atan2(elements.f);
+        let true_anomaly = if eccentricity < 1.0 {
+            elements.true_longitude - periapsis_longitude
+        } else {
+            (elements.true_longitude - periapsis_longitude + PI).rem_euclid(2.0 * PI) - PI
+        };
+        let mean_anomaly = anomaly::true_to_mean_anomaly(true_anomaly, eccentricity);
+        let mean_longitude = periapsis_longitude + mean_anomaly;
+
+        EquinoctialElements {
+            semi_major_axis: elements.semi_latus_rectum / (1.0 - eccentricity * eccentricity),
+            h: elements.g,
+            k: elements.f,
+            p: elements.k,
+            q: elements.h,
+            mean_longitude: if eccentricity < 1.0 { mean_longitude.rem_euclid(2.0 * PI) } else { mean_longitude },
+        }
+    }
+
+    pub fn to_modified(&self) -> ModifiedEquinoctialElements {
+        let eccentricity = self.eccentricity();
+        let periapsis_longitude = self.h.atan2(self.k);
+        let true_anomaly = anomaly::mean_to_true_anomaly(self.mean_longitude - periapsis_longitude, eccentricity);
+        let true_longitude = periapsis_longitude + true_anomaly;
+
+        ModifiedEquinoctialElements {
+            semi_latus_rectum: self.semi_major_axis * (1.0 - eccentricity * eccentricity),
+            f: self.k,
+            g: self.h,
+            h: self.q,
+            k: self.p,
+            true_longitude: if eccentricity < 1.0 { true_longitude.rem_euclid(2.0 * PI) } else { true_longitude },
+        }
+    }
+
+    pub fn from_keplerian(elements: &KeplerianElements) -> Self {
+        EquinoctialElements::from_modified(&ModifiedEquinoctialElements::from_keplerian(elements))
+    }
+
+    pub fn to_keplerian(&self) -> KeplerianElements {
+        self.to_modified().to_keplerian()
+    }
+
+    pub fn from_state(position: &Vector3, velocity: &Vector3, mu: f64) -> Self {
+        EquinoctialElements::from_modified(&ModifiedEquinoctialElements::from_state(position, velocity, mu))
+    }
+
+    pub fn to_state(&self, mu: f64) -> (Vector3, Vector3) {
+        self.to_modified().to_state(mu)
+    }
+
+    pub fn eccentricity(&self) -> f64 {
+        self.h.hypot(self.k)
+    }
+
+    pub fn inclination(&self) -> f64 {
+        2.0 * self.p.hypot(self.q).atan()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MU: f64 = 3.986_004_418e14;
+
+    fn assert_close(a: &Vector3, b: &Vector3, tolerance: f64) {
+        let error = a.subtract(b).magnitude();
+        assert!(error <= tolerance * b.magnitude(), "{:?} != {:?} ({} off)", a, b, error);
+    }
+
+    fn assert_angle_close(a: f64, b: f64) {
+        let difference = (a - b + PI).rem_euclid(2.0 * PI) - PI;
+        assert!(difference.abs() < 1e-10, "{} != {}", a, b);
+    }
+
+    // State -> elements -> state through both element sets
+    fn round_trip(elements: &KeplerianElements) {
+        let (position, velocity) = elements.to_state(MU);
+
+        let modified = ModifiedEquinoctialElements::from_state(&position, &velocity, MU);
+        let (modified_position, modified_velocity) = modified.to_state(MU);
+        assert_close(&modified_position, &position, 1e-10);
+        assert_close(&modified_velocity, &velocity, 1e-10);
+
+        let (keplerian_position, keplerian_velocity) = modified.to_keplerian().to_state(MU);
+        assert_close(&keplerian_position, &position, 1e-10);
+        assert_close(&keplerian_velocity, &velocity, 1e-10);
+
+        if elements.eccentricity != 1.0 {
+            let equinoctial = EquinoctialElements::from_state(&position, &velocity, MU);
+            let (equinoctial_position, equinoctial_velocity) = equinoctial.to_state(MU);
+            assert_close(&equinoctial_position, &position, 1e-9);
+            assert_close(&equinoctial_velocity, &velocity, 1e-9);
+        }
+    }
+
+    #[test]
+    fn circular_orbits() {
+        round_trip(&KeplerianElements::new(7.0e6, 0.0, 0.9, 1.2, 0.0, 2.5));
+        round_trip(&KeplerianElements::new(4.2164e7, 0.0, 0.0, 0.0, 0.0, 4.0));
+    }
+
+    #[test]
+    fn equatorial_orbits() {
+        round_trip(&KeplerianElements::new(2.4e7, 0.7, 0.0, 0.0, 1.1, 5.9));
+    }
+
+    #[test]
+    fn retrograde_orbits() {
+        round_trip(&KeplerianElements::new(1.2e7, 0.3, 2.6, 4.0, 0.6, 1.9));
+        round_trip(&KeplerianElements::new(-2.0e7, 1.8, 2.2, 0.3, 3.0, -0.8));
+    }
+
+    #[test]
+    fn parabolic_orbit() {
+        round_trip(&KeplerianElements::from_semi_latus_rectum(1.5e7, 1.0, 0.4, 2.0, 1.0, 1.2));
+        round_trip(&KeplerianElements::from_semi_latus_rectum(1.5e7, 1.0, 0.4, 2.0, 1.0, -2.0));
+    }
+
+    #[test]
+    fn hyperbolic_orbits() {
+        round_trip(&KeplerianElements::new(-1.5e7, 1.4, 0.5, 0.2, 0.9, 1.0));
+        round_trip(&KeplerianElements::new(-1.5e7, 1.4, 0.5, 0.2, 0.9, -1.5));
+    }
+
+    #[test]
+    fn inbound_hyperbola_keeps_its_longitude() {
+        let modified = ModifiedEquinoctialElements::new(1e7, 1.5, 0.0, 0.0, 0.0, -1.0);
+        let equinoctial = EquinoctialElements::from_modified(&modified);
+        assert!(equinoctial.mean_longitude < 0.0);
+        assert!((equinoctial.to_modified().true_longitude + 1.0).abs() < 1e-10);
+
+        let (position, velocity) = modified.to_state(MU);
+        let (equinoctial_position, equinoctial_velocity) = equinoctial.to_state(MU);
+        assert_close(&equinoctial_position, &position, 1e-10);
+        assert_close(&equinoctial_velocity, &velocity, 1e-10);
+    }
+
+    #[test]
+    fn elliptic_mean_longitude_is_wrapped() {
+        let modified = ModifiedEquinoctialElements::new(1e7, 0.1, 0.2, 0.1, 0.0, 6.0);
+        let equinoctial = EquinoctialElements::from_modified(&modified);
+        assert!((0.0..2.0 * PI).contains(&equinoctial.mean_longitude));
+        assert_angle_close(equinoctial.to_modified().true_longitude, 6.0);
+    }
+}
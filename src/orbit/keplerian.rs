@@ -0,0 +1,245 @@
+use std::f64::consts::PI;
+use crate::geometry::{Matrix3, Vector3};
+use super::anomaly;
+
+// Below these an orbit is treated as circular or equatorial
+const CIRCULAR_TOLERANCE: f64 = 1e-11;
+const EQUATORIAL_TOLERANCE: f64 = 1e-11;
+
+// Classical elements. The size is kept as the semi-latus rectum so that parabolic
+// orbits are representable; angles are in radians.
+//
+// Singular geometries follow Vallado's conventions: equatorial orbits have a zero
+// ascending node and measure the argument of periapsis from the x axis, circular
+// orbits have a zero argument of periapsis and measure the true anomaly from the
+// node (the argument of latitude, or the true longitude when also equatorial).
+#[derive(Clone, Debug)]
+pub struct KeplerianElements {
+    pub semi_latus_rectum: f64,         // m
+    pub eccentricity: f64,
+    pub inclination: f64,
+    pub raan: f64,                      // right ascension of the ascending node
+    pub argument_of_periapsis: f64,
+    pub true_anomaly: f64,
+}
+
+impl KeplerianElements {
+    // Elliptic or hyperbolic orbit (negative semi-major axis for hyperbolas)
+    pub fn new(semi_major_axis: f64, eccentricity: f64, inclination: f64, raan: f64, argument_of_periapsis: f64, true_anomaly: f64) -> Self {
+        KeplerianElements {
+            semi_latus_rectum: semi_major_axis * (1.0 - eccentricity * eccentricity),
+            eccentricity,
+            inclination,
+            raan,
+            argument_of_periapsis,
+            true_anomaly,
+        }
+    }
+
+    pub fn from_semi_latus_rectum(semi_latus_rectum: f64, eccentricity: f64, inclination: f64, raan: f64, argument_of_periapsis: f64, true_anomaly: f64) -> Self {
+        KeplerianElements { semi_latus_rectum, eccentricity, inclination, raan, argument_of_periapsis, true_anomaly }
+    }
+
+    pub fn from_mean_anomaly(semi_major_axis: f64, eccentricity: f64, inclination: f64, raan: f64, argument_of_periapsis: f64, mean_anomaly: f64) -> Self {
+        let true_anomaly = anomaly::mean_to_true_anomaly(mean_anomaly, eccentricity);
+        KeplerianElements::new(semi_major_axis, eccentricity, inclination, raan, argument_of_periapsis, true_anomaly)
+    }
+
+    // `position` and `velocity` relative to the central body with gravitational parameter `mu`
+    pub fn from_state(position: &Vector3, velocity: &Vector3, mu: f64) -> Self {
+        let radius = position.magnitude();
+        let angular_momentum = position.cross(velocity);
+        let h = angular_momentum.magnitude();
+        let normal = angular_momentum.scale(1.0 / h);
+        let node = Vector3::new(-angular_momentum.y, angular_momentum.x, 0.0);
+
+        let eccentricity_vector = position.scale(velocity.dot(velocity) - mu / radius)
+            .subtract(&velocity.scale(position.dot(velocity)))
+            .scale(1.0 / mu);
+        let eccentricity = eccentricity_vector.magnitude();
+        let circular = eccentricity < CIRCULAR_TOLERANCE;
+        let equatorial = node.magnitude() < EQUATORIAL_TOLERANCE * h;
+
+        let inclination = normal.x.hypot(normal.y).atan2(normal.z);
+        let (raan, node_direction) = if equatorial {
+            (0.0, Vector3::new(1.0, 0.0, 0.0))
+        } else {
+            (node.y.atan2(node.x).rem_euclid(2.0 * PI), node.norm())
+        };
+
+        let (argument_of_periapsis, true_anomaly) = if circular {
+            (0.0, angle_about(&node_direction, position, &normal))
+        } else {
+            (
+                angle_about(&node_direction, &eccentricity_vector, &normal),
+                angle_about(&eccentricity_vector, position, &normal),
+            )
+        };
+
+        // Hyperbolic and parabolic anomalies are symmetric about periapsis
+        let true_anomaly = if eccentricity >= 1.0 && true_anomaly > PI { true_anomaly - 2.0 * PI } else { true_anomaly };
+
+        KeplerianElements {
+            semi_latus_rectum: h * h / mu,
+            eccentricity,
+            inclination,
+            raan,
+            argument_of_periapsis,
+            true_anomaly,
+        }
+    }
+
+    // Position and velocity relative to the central body
+    pub fn to_state(&self, mu: f64) -> (Vector3, Vector3) {
+        let (sin, cos) = self.true_anomaly.sin_cos();
+        let radius = self.semi_latus_rectum / (1.0 + self.eccentricity * cos);
+        let speed = (mu / self.semi_latus_rectum).sqrt();
+
+        let perifocal_position = Vector3::new(radius * cos, radius * sin, 0.0);
+        let perifocal_velocity = Vector3::new(-speed * sin, speed * (self.eccentricity + cos), 0.0);
+
+        let rotation = self.perifocal_to_inertial();
+        (rotation.multiply_vector(&perifocal_position), rotation.multiply_vector(&perifocal_velocity))
+    }
+
+    pub fn perifocal_to_inertial(&self) -> Matrix3 {
+        Matrix3::rotation_z(-self.raan)
+            .multiply(&Matrix3::rotation_x(-self.inclination))
+            .multiply(&Matrix3::rotation_z(-self.argument_of_periapsis))
+    }
+
+    // Infinite for parabolas, negative for hyperbolas
+    pub fn semi_major_axis(&self) -> f64 {
+        self.semi_latus_rectum / (1.0 - self.eccentricity * self.eccentricity)
+    }
+
+    pub fn mean_anomaly(&self) -> f64 {
+        anomaly::true_to_mean_anomaly(self.true_anomaly, self.eccentricity)
+    }
+
+    // Rate of the mean anomaly; for parabolas the rate of Barker's D + D^3 / 3
+    pub fn mean_motion(&self, mu: f64) -> f64 {
+        if self.eccentricity == 1.0 {
+            2.0 * (mu / self.semi_latus_rectum.powi(3)).sqrt()
+        } else {
+            (mu / self.semi_major_axis().abs().powi(3)).sqrt()
+        }
+    }
+
+    pub fn period(&self, mu: f64) -> Option<f64> {
+        if self.eccentricity < 1.0 { Some(2.0 * PI / self.mean_motion(mu)) } else { None }
+    }
+
+    pub fn periapsis(&self) -> f64 {
+        self.semi_latus_rectum / (1.0 + self.eccentricity)
+    }
+
+    pub fn apoapsis(&self) -> Option<f64> {
+        if self.eccentricity < 1.0 { Some(self.semi_latus_rectum / (1.0 - self.eccentricity)) } else { None }
+    }
+
+    // Same orbit with the body moved to `mean_anomaly`
+    pub fn with_mean_anomaly(&self, mean_anomaly: f64) -> Self {
+        KeplerianElements { true_anomaly: anomaly::mean_to_true_anomaly(mean_anomaly, self.eccentricity), ..self.clone() }
+    }
+}
+
+// Angle from `from` to `to` measured counter-clockwise about `axis`, in [0, 2pi)
+fn angle_about(from: &Vector3, to: &Vector3, axis: &Vector3) -> f64 {
+    from.cross(to).dot(axis).atan2(from.dot(to)).rem_euclid(2.0 * PI)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MU: f64 = 3.986_004_418e14;
+
+    fn assert_close(a: &Vector3, b: &Vector3) {
+        let error = a.subtract(b).magnitude();
+        assert!(error <= 1e-10 * b.magnitude(), "{:?} != {:?} ({} off)", a, b, error);
+    }
+
+    fn assert_angle_close(a: f64, b: f64) {
+        let difference = (a - b + PI).rem_euclid(2.0 * PI) - PI;
+        assert!(difference.abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    // Elements -> state -> elements -> state, comparing the recovered elements with
+    // `expected`, which spells out the conventions for singular orbits
+    fn round_trip(elements: &KeplerianElements, expected: &KeplerianElements) {
+        let (position, velocity) = elements.to_state(MU);
+        let recovered = KeplerianElements::from_state(&position, &velocity, MU);
+
+        assert!((recovered.semi_latus_rectum - expected.semi_latus_rectum).abs() < 1e-6 * expected.semi_latus_rectum);
+        assert!((recovered.eccentricity - expected.eccentricity).abs() < 1e-10);
+        assert_angle_close(recovered.inclination, expected.inclination);
+        assert_angle_close(recovered.raan, expected.raan);
+        assert_angle_close(recovered.argument_of_periapsis, expected.argument_of_periapsis);
+        assert_angle_close(recovered.true_anomaly, expected.true_anomaly);
+
+        let (recovered_position, recovered_velocity) = recovered.to_state(MU);
+        assert_close(&recovered_position, &position);
+        assert_close(&recovered_velocity, &velocity);
+    }
+
+    #[test]
+    fn elliptic_orbit() {
+        let elements = KeplerianElements::new(2.4e7, 0.7, 0.5, 1.0, 2.0, 3.0);
+        round_trip(&elements, &elements);
+    }
+
+    #[test]
+    fn circular_orbit_measures_from_the_node() {
+        // Argument of latitude 0.5 + 2.0
+        round_trip(
+            &KeplerianElements::new(7.0e6, 0.0, 0.9, 1.2, 0.5, 2.0),
+            &KeplerianElements::new(7.0e6, 0.0, 0.9, 1.2, 0.0, 2.5),
+        );
+    }
+
+    #[test]
+    fn equatorial_orbit_measures_from_the_x_axis() {
+        // Longitude of periapsis 1.0 + 1.1
+        round_trip(
+            &KeplerianElements::new(2.4e7, 0.3, 0.0, 1.0, 1.1, 0.4),
+            &KeplerianElements::new(2.4e7, 0.3, 0.0, 0.0, 2.1, 0.4),
+        );
+    }
+
+    #[test]
+    fn circular_equatorial_orbit_uses_the_true_longitude() {
+        round_trip(
+            &KeplerianElements::new(4.2164e7, 0.0, 0.0, 1.0, 2.0, 3.0),
+            &KeplerianElements::new(4.2164e7, 0.0, 0.0, 0.0, 0.0, 6.0),
+        );
+    }
+
+    #[test]
+    fn retrograde_orbit() {
+        let elements = KeplerianElements::new(1.2e7, 0.3, 2.6, 4.0, 0.6, 1.9);
+        round_trip(&elements, &elements);
+    }
+
+    #[test]
+    fn parabolic_orbit() {
+        let elements = KeplerianElements::from_semi_latus_rectum(1.5e7, 1.0, 0.4, 2.0, 1.0, 1.2);
+        let (position, velocity) = elements.to_state(MU);
+        assert!((velocity.magnitude() - (2.0 * MU / position.magnitude()).sqrt()).abs() < 1e-6);
+
+        // Eccentricity only survives to rounding, so the rest is compared loosely
+        let recovered = KeplerianElements::from_state(&position, &velocity, MU);
+        assert!((recovered.eccentricity - 1.0).abs() < 1e-12);
+        let (recovered_position, recovered_velocity) = recovered.to_state(MU);
+        assert_close(&recovered_position, &position);
+        assert_close(&recovered_velocity, &velocity);
+    }
+
+    #[test]
+    fn hyperbolic_orbit_keeps_negative_anomalies() {
+        let elements = KeplerianElements::new(-1.5e7, 1.4, 0.5, 0.2, 0.9, -1.5);
+        round_trip(&elements, &elements);
+        assert!(elements.mean_anomaly() < 0.0);
+        assert!((anomaly::mean_to_true_anomaly(elements.mean_anomaly(), 1.4) + 1.5).abs() < 1e-10);
+    }
+}
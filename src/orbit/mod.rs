@@ -0,0 +1,15 @@
+mod anomaly;
+mod keplerian;
+mod equinoctial;
+mod ephemeris;
+mod universal;
+mod tle;
+mod sgp4;
+
+pub use self::anomaly::*;
+pub use self::keplerian::KeplerianElements;
+pub use self::equinoctial::{EquinoctialElements, ModifiedEquinoctialElements};
+pub use self::ephemeris::PlanetaryElements;
+pub use self::universal::{kepler_transition_matrix, propagate_kepler, stumpff_c, stumpff_s};
+pub use self::tle::{parse_tles, read_tles, Tle};
+pub use self::sgp4::{greenwich_mean_sidereal_time, teme_to_ecliptic, Sgp4};
@@ -0,0 +1,921 @@
+use std::f64::consts::PI;
+use crate::geometry::{self, Matrix3, Vector3};
+use crate::time;
+use super::Tle;
+
+// SGP4/SDP4 after Vallado, Crawford, Hujsak and Kelso, "Revisiting Spacetrack Report #3"
+// (AIAA 2006-6753), in its "improved" operation mode with the WGS-72 constants the
+// element sets are fitted with. Internal units are earth radii and minutes.
+const MU: f64 = 398_600.8;                  // km^3/s^2
+const EARTH_RADIUS: f64 = 6378.135;         // km
+const J2: f64 = 0.001_082_616;
+const J3: f64 = -0.000_002_538_81;
+const J4: f64 = -0.000_001_655_97;
+const J3_OVER_J2: f64 = J3 / J2;
+const TWO_THIRDS: f64 = 2.0 / 3.0;
+const DEEP_SPACE_PERIOD: f64 = 225.0;       // min, beyond which SDP4 applies
+const EARTH_ROTATION_RATE: f64 = 4.375_269_088_011_3e-3;    // rad/min
+
+fn xke() -> f64 {
+    60.0 / (EARTH_RADIUS.powi(3) / MU).sqrt()
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Resonance {
+    None,
+    Synchronous,    // one day period
+    HalfDay,        // 12 hour, eccentric
+}
+
+// Lunar and solar periodic coefficients of the deep-space model
+#[derive(Clone, Debug, Default)]
+struct DeepSpacePeriodics {
+    e3: f64, ee2: f64, se2: f64, se3: f64, sgh2: f64, sgh3: f64, sgh4: f64, sh2: f64, sh3: f64,
+    si2: f64, si3: f64, sl2: f64, sl3: f64, sl4: f64, xgh2: f64, xgh3: f64, xgh4: f64, xh2: f64,
+    xh3: f64, xi2: f64, xi3: f64, xl2: f64, xl3: f64, xl4: f64, zmol: f64, zmos: f64,
+}
+
+// Secular and resonance terms of the deep-space model
+#[derive(Clone, Debug)]
+struct DeepSpace {
+    periodics: DeepSpacePeriodics,
+    resonance: Resonance,
+    gsto: f64,
+    dedt: f64, didt: f64, dmdt: f64, dnodt: f64, domdt: f64,
+    d2201: f64, d2211: f64, d3210: f64, d3222: f64, d4410: f64, d4422: f64,
+    d5220: f64, d5232: f64, d5421: f64, d5433: f64,
+    del1: f64, del2: f64, del3: f64,
+    xfact: f64, xlamo: f64,
+}
+
+// Initialised propagator for one element set
+#[derive(Clone, Debug)]
+pub struct Sgp4 {
+    pub tle: Tle,
+    ecco: f64, inclo: f64, nodeo: f64, argpo: f64, mo: f64, no: f64, bstar: f64,
+    simple: bool,
+    aycof: f64, con41: f64, cc1: f64, cc4: f64, cc5: f64, d2: f64, d3: f64, d4: f64,
+    delmo: f64, eta: f64, argpdot: f64, omgcof: f64, sinmao: f64, t2cof: f64, t3cof: f64,
+    t4cof: f64, t5cof: f64, x1mth2: f64, x7thm1: f64, mdot: f64, nodedot: f64, xlcof: f64,
+    xmcof: f64, nodecf: f64,
+    deep_space: Option<DeepSpace>,
+}
+
+// Greenwich mean sidereal time (IAU 1982) in radians at a UT1 Julian date
+pub fn greenwich_mean_sidereal_time(julian_date: f64) -> f64 {
+    let centuries = (julian_date - time::J2000_JULIAN_DATE) / time::DAYS_PER_JULIAN_CENTURY;
+    let seconds = -6.2e-6 * centuries.powi(3) + 0.093_104 * centuries.powi(2)
+        + (876_600.0 * 3600.0 + 8_640_184.812_866) * centuries + 67_310.548_41;
+    (seconds.to_radians() / 240.0).rem_euclid(2.0 * PI)
+}
+
+// Rotation from the true equator, mean equinox frame of SGP4 at `time` to the
+// simulation's J2000 ecliptic frame: IAU 1976 precession and the leading terms of the
+// IAU 1980 nutation (Meeus chapter 22), good to about half an arcsecond
+pub fn teme_to_ecliptic(time: f64) -> Matrix3 {
+    let centuries = time::centuries_since_j2000(time);
+    let arcseconds = |value: f64| (value / 3600.0).to_radians();
+
+    let zeta = arcseconds(2306.2181 * centuries + 0.301_88 * centuries.powi(2) + 0.017_998 * centuries.powi(3));
+    let z = arcseconds(2306.2181 * centuries + 1.094_68 * centuries.powi(2) + 0.018_203 * centuries.powi(3));
+    let theta = arcseconds(2004.3109 * centuries - 0.426_65 * centuries.powi(2) - 0.041_833 * centuries.powi(3));
+    let precession = Matrix3::rotation_z(-z).multiply(&Matrix3::rotation_y(theta)).multiply(&Matrix3::rotation_z(-zeta));
+
+    let node = (125.044_52 - 1_934.136_261 * centuries).to_radians();
+    let sun = (280.4665 + 36_000.769_8 * centuries).to_radians();
+    let moon = (218.3165 + 481_267.881_3 * centuries).to_radians();
+    let longitude = arcseconds(-17.20 * node.sin() - 1.32 * (2.0 * sun).sin() - 0.23 * (2.0 * moon).sin() + 0.21 * (2.0 * node).sin());
+    let obliquity = arcseconds(9.20 * node.cos() + 0.57 * (2.0 * sun).cos() + 0.10 * (2.0 * moon).cos() - 0.09 * (2.0 * node).cos());
+    let mean_obliquity = geometry::J2000_OBLIQUITY - arcseconds(46.8150 * centuries);
+    let nutation = Matrix3::rotation_x(-(mean_obliquity + obliquity))
+        .multiply(&Matrix3::rotation_z(-longitude))
+        .multiply(&Matrix3::rotation_x(mean_obliquity));
+    let equation_of_equinoxes = longitude * mean_obliquity.cos();
+
+    Matrix3::rotation_x(geometry::J2000_OBLIQUITY)
+        .multiply(&precession.transpose())
+        .multiply(&nutation.transpose())
+        .multiply(&Matrix3::rotation_z(-equation_of_equinoxes))
+}
+
+impl Sgp4 {
+    // `None` when the elements describe no valid orbit
+    pub fn new(tle: &Tle) -> Option<Sgp4> {
+        let xke = xke();
+        let ecco = tle.eccentricity;
+        let inclo = tle.inclination;
+        let bstar = tle.bstar;
+        let no_kozai = tle.mean_motion * 2.0 * PI / 1440.0;
+        let epoch = tle.epoch_julian_date - 2_433_281.5;     // days since 1950 January 0
+
+        // Recover the Brouwer mean motion from the Kozai one of the element set
+        let eccsq = ecco * ecco;
+        let omeosq = 1.0 - eccsq;
+        let rteosq = omeosq.sqrt();
+        let cosio = inclo.cos();
+        let cosio2 = cosio * cosio;
+        let ak = (xke / no_kozai).powf(TWO_THIRDS);
+        let d1 = 0.75 * J2 * (3.0 * cosio2 - 1.0) / (rteosq * omeosq);
+        let del = d1 / (ak * ak);
+        let adel = ak * (1.0 - del * del - del * (1.0 / 3.0 + 134.0 * del * del / 81.0));
+        let del = d1 / (adel * adel);
+        let no = no_kozai / (1.0 + del);
+        let ao = (xke / no).powf(TWO_THIRDS);
+        let sinio = inclo.sin();
+        let po = ao * omeosq;
+        let con42 = 1.0 - 5.0 * cosio2;
+        let con41 = -con42 - cosio2 - cosio2;
+        let posq = po * po;
+        let rp = ao * (1.0 - ecco);
+        let gsto = greenwich_mean_sidereal_time(epoch + 2_433_281.5);
+        if !(omeosq > 0.0 && no > 0.0) {
+            return None;
+        }
+
+        // Atmospheric density parameters, lowered for perigees under 156 km
+        let ss = 78.0 / EARTH_RADIUS + 1.0;
+        let qzms2t = ((120.0 - 78.0) / EARTH_RADIUS).powi(4);
+        let mut simple = rp < 220.0 / EARTH_RADIUS + 1.0;
+        let mut sfour = ss;
+        let mut qzms24 = qzms2t;
+        let perigee = (rp - 1.0) * EARTH_RADIUS;
+        if perigee < 156.0 {
+            sfour = if perigee < 98.0 { 20.0 } else { perigee - 78.0 };
+            qzms24 = ((120.0 - sfour) / EARTH_RADIUS).powi(4);
+            sfour = sfour / EARTH_RADIUS + 1.0;
+        }
+
+        let pinvsq = 1.0 / posq;
+        let tsi = 1.0 / (ao - sfour);
+        let eta = ao * ecco * tsi;
+        let etasq = eta * eta;
+        let eeta = ecco * eta;
+        let psisq = (1.0 - etasq).abs();
+        let coef = qzms24 * tsi.powi(4);
+        let coef1 = coef / psisq.powf(3.5);
+        let cc2 = coef1 * no * (ao * (1.0 + 1.5 * etasq + eeta * (4.0 + etasq))
+            + 0.375 * J2 * tsi / psisq * con41 * (8.0 + 3.0 * etasq * (8.0 + etasq)));
+        let cc1 = bstar * cc2;
+        let cc3 = if ecco > 1.0e-4 { -2.0 * coef * tsi * J3_OVER_J2 * no * sinio / ecco } else { 0.0 };
+        let x1mth2 = 1.0 - cosio2;
+        let cc4 = 2.0 * no * coef1 * ao * omeosq * (eta * (2.0 + 0.5 * etasq) + ecco * (0.5 + 2.0 * etasq)
+            - J2 * tsi / (ao * psisq) * (-3.0 * con41 * (1.0 - 2.0 * eeta + etasq * (1.5 - 0.5 * eeta))
+                + 0.75 * x1mth2 * (2.0 * etasq - eeta * (1.0 + etasq)) * (2.0 * tle.argument_of_perigee).cos()));
+        let cc5 = 2.0 * coef1 * ao * omeosq * (1.0 + 2.75 * (etasq + eeta) + eeta * etasq);
+        let cosio4 = cosio2 * cosio2;
+        let temp1 = 1.5 * J2 * pinvsq * no;
+        let temp2 = 0.5 * temp1 * J2 * pinvsq;
+        let temp3 = -0.46875 * J4 * pinvsq * pinvsq * no;
+        let mdot = no + 0.5 * temp1 * rteosq * con41 + 0.0625 * temp2 * rteosq * (13.0 - 78.0 * cosio2 + 137.0 * cosio4);
+        let argpdot = -0.5 * temp1 * con42 + 0.0625 * temp2 * (7.0 - 114.0 * cosio2 + 395.0 * cosio4)
+            + temp3 * (3.0 - 36.0 * cosio2 + 49.0 * cosio4);
+        let xhdot1 = -temp1 * cosio;
+        let nodedot = xhdot1 + (0.5 * temp2 * (4.0 - 19.0 * cosio2) + 2.0 * temp3 * (3.0 - 7.0 * cosio2)) * cosio;
+        let xpidot = argpdot + nodedot;
+        let omgcof = bstar * cc3 * tle.argument_of_perigee.cos();
+        let xmcof = if ecco > 1.0e-4 { -TWO_THIRDS * coef * bstar / eeta } else { 0.0 };
+        let nodecf = 3.5 * omeosq * xhdot1 * cc1;
+        let t2cof = 1.5 * cc1;
+        let xlcof = -0.25 * J3_OVER_J2 * sinio * (3.0 + 5.0 * cosio) / guarded(1.0 + cosio);
+        let aycof = -0.5 * J3_OVER_J2 * sinio;
+        let delmo = (1.0 + eta * tle.mean_anomaly.cos()).powi(3);
+        let sinmao = tle.mean_anomaly.sin();
+        let x7thm1 = 7.0 * cosio2 - 1.0;
+
+        let mut deep_space = None;
+        if 2.0 * PI / no >= DEEP_SPACE_PERIOD {
+            simple = true;
+            let common = deep_space_common(epoch, ecco, tle.argument_of_perigee, 0.0, inclo, tle.raan, no);
+            deep_space = Some(deep_space_init(&common, gsto, ecco, eccsq, inclo, tle.argument_of_perigee, tle.raan, tle.mean_anomaly, no, mdot, nodedot, xpidot));
+        }
+
+        let (mut d2, mut d3, mut d4, mut t3cof, mut t4cof, mut t5cof) = (0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        if !simple {
+            let cc1sq = cc1 * cc1;
+            d2 = 4.0 * ao * tsi * cc1sq;
+            let temp = d2 * tsi * cc1 / 3.0;
+            d3 = (17.0 * ao + sfour) * temp;
+            d4 = 0.5 * temp * ao * tsi * (221.0 * ao + 31.0 * sfour) * cc1;
+            t3cof = d2 + 2.0 * cc1sq;
+            t4cof = 0.25 * (3.0 * d3 + cc1 * (12.0 * d2 + 10.0 * cc1sq));
+            t5cof = 0.2 * (3.0 * d4 + 12.0 * cc1 * d3 + 6.0 * d2 * d2 + 15.0 * cc1sq * (2.0 * d2 + cc1sq));
+        }
+
+        let propagator = Sgp4 {
+            tle: tle.clone(),
+            ecco, inclo, nodeo: tle.raan, argpo: tle.argument_of_perigee, mo: tle.mean_anomaly, no, bstar,
+            simple,
+            aycof, con41, cc1, cc4, cc5, d2, d3, d4, delmo, eta, argpdot, omgcof, sinmao, t2cof, t3cof,
+            t4cof, t5cof, x1mth2, x7thm1, mdot, nodedot, xlcof, xmcof, nodecf,
+            deep_space,
+        };
+        propagator.propagate(0.0)?;
+        Some(propagator)
+    }
+
+    // Position (m) and velocity (m/s) in the TEME frame `minutes` after the element set
+    // epoch. `None` once the orbit decays or the elements go out of range.
+    pub fn propagate(&self, minutes: f64) -> Option<(Vector3, Vector3)> {
+        let xke = xke();
+        let t = minutes;
+
+        // Secular gravity and atmospheric drag
+        let xmdf = self.mo + self.mdot * t;
+        let argpdf = self.argpo + self.argpdot * t;
+        let nodedf = self.nodeo + self.nodedot * t;
+        let mut argpm = argpdf;
+        let mut mm = xmdf;
+        let t2 = t * t;
+        let mut nodem = nodedf + self.nodecf * t2;
+        let mut tempa = 1.0 - self.cc1 * t;
+        let mut tempe = self.bstar * self.cc4 * t;
+        let mut templ = self.t2cof * t2;
+        if !self.simple {
+            let delomg = self.omgcof * t;
+            let delm = self.xmcof * ((1.0 + self.eta * xmdf.cos()).powi(3) - self.delmo);
+            let temp = delomg + delm;
+            mm = xmdf + temp;
+            argpm = argpdf - temp;
+            let t3 = t2 * t;
+            let t4 = t3 * t;
+            tempa = tempa - self.d2 * t2 - self.d3 * t3 - self.d4 * t4;
+            tempe += self.bstar * self.cc5 * (mm.sin() - self.sinmao);
+            templ = templ + self.t3cof * t3 + t4 * (self.t4cof + t * self.t5cof);
+        }
+
+        let mut nm = self.no;
+        let mut em = self.ecco;
+        let mut inclm = self.inclo;
+        if let Some(deep) = &self.deep_space {
+            deep_space_secular(deep, self, t, &mut em, &mut argpm, &mut inclm, &mut mm, &mut nodem, &mut nm);
+        }
+        if nm <= 0.0 {
+            return None;
+        }
+
+        let am = (xke / nm).powf(TWO_THIRDS) * tempa * tempa;
+        nm = xke / am.powf(1.5);
+        em -= tempe;
+        if !(-0.001..1.0).contains(&em) {
+            return None;
+        }
+        em = em.max(1.0e-6);
+        mm += self.no * templ;
+        let xlm = mm + argpm + nodem;
+        nodem %= 2.0 * PI;
+        argpm %= 2.0 * PI;
+        let xlm = xlm % (2.0 * PI);
+        mm = (xlm - argpm - nodem) % (2.0 * PI);
+
+        // Lunar-solar periodics
+        let (mut ep, mut xincp, mut argpp, mut nodep, mut mp) = (em, inclm, argpm, nodem, mm);
+        let (mut aycof, mut xlcof) = (self.aycof, self.xlcof);
+        let (mut con41, mut x1mth2, mut x7thm1) = (self.con41, self.x1mth2, self.x7thm1);
+        if let Some(deep) = &self.deep_space {
+            deep_space_periodics(&deep.periodics, t, &mut ep, &mut xincp, &mut nodep, &mut argpp, &mut mp);
+            if xincp < 0.0 {
+                xincp = -xincp;
+                nodep += PI;
+                argpp -= PI;
+            }
+            if !(0.0..=1.0).contains(&ep) {
+                return None;
+            }
+            let (sinip, cosip) = xincp.sin_cos();
+            aycof = -0.5 * J3_OVER_J2 * sinip;
+            xlcof = -0.25 * J3_OVER_J2 * sinip * (3.0 + 5.0 * cosip) / guarded(1.0 + cosip);
+            let cosisq = cosip * cosip;
+            con41 = 3.0 * cosisq - 1.0;
+            x1mth2 = 1.0 - cosisq;
+            x7thm1 = 7.0 * cosisq - 1.0;
+        }
+        let (sinip, cosip) = xincp.sin_cos();
+
+        // Long-period periodics
+        let axnl = ep * argpp.cos();
+        let temp = 1.0 / (am * (1.0 - ep * ep));
+        let aynl = ep * argpp.sin() + temp * aycof;
+        let xl = mp + argpp + nodep + temp * xlcof * axnl;
+
+        // Kepler's equation in equinoctial form
+        let u = (xl - nodep) % (2.0 * PI);
+        let mut eo1 = u;
+        let (mut sineo1, mut coseo1) = (0.0, 0.0);
+        for _ in 0..10 {
+            (sineo1, coseo1) = eo1.sin_cos();
+            let step = ((u - aynl * coseo1 + axnl * sineo1 - eo1) / (1.0 - coseo1 * axnl - sineo1 * aynl)).clamp(-0.95, 0.95);
+            eo1 += step;
+            if step.abs() < 1.0e-12 {
+                break;
+            }
+        }
+
+        // Short-period periodics
+        let ecose = axnl * coseo1 + aynl * sineo1;
+        let esine = axnl * sineo1 - aynl * coseo1;
+        let el2 = axnl * axnl + aynl * aynl;
+        let pl = am * (1.0 - el2);
+        if pl < 0.0 {
+            return None;
+        }
+        let rl = am * (1.0 - ecose);
+        let rdotl = am.sqrt() * esine / rl;
+        let rvdotl = pl.sqrt() / rl;
+        let betal = (1.0 - el2).sqrt();
+        let temp = esine / (1.0 + betal);
+        let sinu = am / rl * (sineo1 - aynl - axnl * temp);
+        let cosu = am / rl * (coseo1 - axnl + aynl * temp);
+        let su = sinu.atan2(cosu);
+        let sin2u = (cosu + cosu) * sinu;
+        let cos2u = 1.0 - 2.0 * sinu * sinu;
+        let temp = 1.0 / pl;
+        let temp1 = 0.5 * J2 * temp;
+        let temp2 = temp1 * temp;
+
+        let mrt = rl * (1.0 - 1.5 * temp2 * betal * con41) + 0.5 * temp1 * x1mth2 * cos2u;
+        let su = su - 0.25 * temp2 * x7thm1 * sin2u;
+        let xnode = nodep + 1.5 * temp2 * cosip * sin2u;
+        let xinc = xincp + 1.5 * temp2 * cosip * sinip * cos2u;
+        let mvt = rdotl - nm * temp1 * x1mth2 * sin2u / xke;
+        let rvdot = rvdotl + nm * temp1 * (x1mth2 * cos2u + 1.5 * con41) / xke;
+        if mrt < 1.0 {
+            return None;
+        }
+
+        let (sinsu, cossu) = su.sin_cos();
+        let (snod, cnod) = xnode.sin_cos();
+        let (sini, cosi) = xinc.sin_cos();
+        let xmx = -snod * cosi;
+        let xmy = cnod * cosi;
+        let radial = Vector3::new(xmx * sinsu + cnod * cossu, xmy * sinsu + snod * cossu, sini * sinsu);
+        let transverse = Vector3::new(xmx * cossu - cnod * sinsu, xmy * cossu - snod * sinsu, sini * cossu);
+
+        let metres = EARTH_RADIUS * 1000.0;
+        let metres_per_second = metres * xke / 60.0;
+        Some((
+            radial.scale(mrt * metres),
+            radial.scale(mvt).add(&transverse.scale(rvdot)).scale(metres_per_second),
+        ))
+    }
+
+    // TEME state at simulation `time`
+    pub fn state_at(&self, time: f64) -> Option<(Vector3, Vector3)> {
+        self.propagate((time - self.tle.epoch) / 60.0)
+    }
+
+    // Geocentric state in the simulation frame at `time`
+    pub fn ecliptic_state_at(&self, time: f64) -> Option<(Vector3, Vector3)> {
+        let (position, velocity) = self.state_at(time)?;
+        let rotation = teme_to_ecliptic(time);
+        Some((rotation.multiply_vector(&position), rotation.multiply_vector(&velocity)))
+    }
+
+    pub fn is_deep_space(&self) -> bool {
+        self.deep_space.is_some()
+    }
+}
+
+// Keeps 1 + cos(i) away from zero for retrograde equatorial orbits
+fn guarded(value: f64) -> f64 {
+    if value.abs() > 1.5e-12 { value } else { 1.5e-12 }
+}
+
+// Quantities shared by the deep-space initialisation and periodics (dscom)
+struct DeepSpaceCommon {
+    periodics: DeepSpacePeriodics,
+    sinim: f64, cosim: f64, emsq: f64,
+    s1: f64, s2: f64, s3: f64, s4: f64, s5: f64,
+    ss1: f64, ss2: f64, ss3: f64, ss4: f64, ss5: f64,
+    sz1: f64, sz3: f64, sz11: f64, sz13: f64, sz21: f64, sz23: f64, sz31: f64, sz33: f64,
+    z1: f64, z3: f64, z11: f64, z13: f64, z21: f64, z23: f64, z31: f64, z33: f64,
+}
+
+fn deep_space_common(epoch: f64, ep: f64, argpp: f64, tc: f64, inclp: f64, nodep: f64, np: f64) -> DeepSpaceCommon {
+    const ZES: f64 = 0.01675;
+    const ZEL: f64 = 0.05490;
+    const C1SS: f64 = 2.986_479_7e-6;
+    const C1L: f64 = 4.796_806_5e-7;
+    const ZSINIS: f64 = 0.397_854_16;
+    const ZCOSIS: f64 = 0.917_448_67;
+    const ZCOSGS: f64 = 0.194_590_5;
+    const ZSINGS: f64 = -0.980_884_58;
+
+    let nm = np;
+    let em = ep;
+    let (snodm, cnodm) = nodep.sin_cos();
+    let (sinomm, cosomm) = argpp.sin_cos();
+    let (sinim, cosim) = inclp.sin_cos();
+    let emsq = em * em;
+    let betasq = 1.0 - emsq;
+    let rtemsq = betasq.sqrt();
+
+    // Lunar and solar orbit geometry at the epoch
+    let day = epoch + 18_261.5 + tc / 1440.0;
+    let xnodce = (4.523_602_0 - 9.242_202_9e-4 * day) % (2.0 * PI);
+    let (stem, ctem) = xnodce.sin_cos();
+    let zcosil = 0.913_751_64 - 0.035_680_96 * ctem;
+    let zsinil = (1.0 - zcosil * zcosil).sqrt();
+    let zsinhl = 0.089_683_511 * stem / zsinil;
+    let zcoshl = (1.0 - zsinhl * zsinhl).sqrt();
+    let gam = 5.835_151_4 + 0.001_944_368_0 * day;
+    let zx = 0.397_854_16 * stem / zsinil;
+    let zy = zcoshl * ctem + 0.917_448_67 * zsinhl * stem;
+    let zx = gam + zx.atan2(zy) - xnodce;
+    let (zsingl, zcosgl) = zx.sin_cos();
+
+    // First pass solar, second lunar
+    let (mut zcosg, mut zsing, mut zcosi, mut zsini) = (ZCOSGS, ZSINGS, ZCOSIS, ZSINIS);
+    let (mut zcosh, mut zsinh) = (cnodm, snodm);
+    let mut cc = C1SS;
+    let xnoi = 1.0 / nm;
+    let mut solar = [0.0; 7];
+    let mut solar_z = [0.0; 12];
+    let mut lunar = [0.0; 7];
+    let mut lunar_z = [0.0; 12];
+
+    for pass in 0..2 {
+        let a1 = zcosg * zcosh + zsing * zcosi * zsinh;
+        let a3 = -zsing * zcosh + zcosg * zcosi * zsinh;
+        let a7 = -zcosg * zsinh + zsing * zcosi * zcosh;
+        let a8 = zsing * zsini;
+        let a9 = zsing * zsinh + zcosg * zcosi * zcosh;
+        let a10 = zcosg * zsini;
+        let a2 = cosim * a7 + sinim * a8;
+        let a4 = cosim * a9 + sinim * a10;
+        let a5 = -sinim * a7 + cosim * a8;
+        let a6 = -sinim * a9 + cosim * a10;
+
+        let x1 = a1 * cosomm + a2 * sinomm;
+        let x2 = a3 * cosomm + a4 * sinomm;
+        let x3 = -a1 * sinomm + a2 * cosomm;
+        let x4 = -a3 * sinomm + a4 * cosomm;
+        let x5 = a5 * sinomm;
+        let x6 = a6 * sinomm;
+        let x7 = a5 * cosomm;
+        let x8 = a6 * cosomm;
+
+        let z31 = 12.0 * x1 * x1 - 3.0 * x3 * x3;
+        let z32 = 24.0 * x1 * x2 - 6.0 * x3 * x4;
+        let z33 = 12.0 * x2 * x2 - 3.0 * x4 * x4;
+        let mut z1 = 3.0 * (a1 * a1 + a2 * a2) + z31 * emsq;
+        let mut z2 = 6.0 * (a1 * a3 + a2 * a4) + z32 * emsq;
+        let mut z3 = 3.0 * (a3 * a3 + a4 * a4) + z33 * emsq;
+        let z11 = -6.0 * a1 * a5 + emsq * (-24.0 * x1 * x7 - 6.0 * x3 * x5);
+        let z12 = -6.0 * (a1 * a6 + a3 * a5) + emsq * (-24.0 * (x2 * x7 + x1 * x8) - 6.0 * (x3 * x6 + x4 * x5));
+        let z13 = -6.0 * a3 * a6 + emsq * (-24.0 * x2 * x8 - 6.0 * x4 * x6);
+        let z21 = 6.0 * a2 * a5 + emsq * (24.0 * x1 * x5 - 6.0 * x3 * x7);
+        let z22 = 6.0 * (a4 * a5 + a2 * a6) + emsq * (24.0 * (x2 * x5 + x1 * x6) - 6.0 * (x4 * x7 + x3 * x8));
+        let z23 = 6.0 * a4 * a6 + emsq * (24.0 * x2 * x6 - 6.0 * x4 * x8);
+        z1 = z1 + z1 + betasq * z31;
+        z2 = z2 + z2 + betasq * z32;
+        z3 = z3 + z3 + betasq * z33;
+
+        let s3 = cc * xnoi;
+        let s2 = -0.5 * s3 / rtemsq;
+        let s4 = s3 * rtemsq;
+        let s1 = -15.0 * em * s4;
+        let s5 = x1 * x3 + x2 * x4;
+        let s6 = x2 * x3 + x1 * x4;
+        let s7 = x2 * x4 - x1 * x3;
+
+        let (s, z) = if pass == 0 { (&mut solar, &mut solar_z) } else { (&mut lunar, &mut lunar_z) };
+        *s = [s1, s2, s3, s4, s5, s6, s7];
+        *z = [z1, z2, z3, z11, z12, z13, z21, z22, z23, z31, z32, z33];
+
+        if pass == 0 {
+            zcosg = zcosgl;
+            zsing = zsingl;
+            zcosi = zcosil;
+            zsini = zsinil;
+            zcosh = zcoshl * cnodm + zsinhl * snodm;
+            zsinh = snodm * zcoshl - cnodm * zsinhl;
+            cc = C1L;
+        }
+    }
+
+    let [ss1, ss2, ss3, ss4, ss5, ss6, ss7] = solar;
+    let [sz1, sz2, sz3, sz11, sz12, sz13, sz21, sz22, sz23, sz31, sz32, sz33] = solar_z;
+    let [s1, s2, s3, s4, s5, s6, s7] = lunar;
+    let [z1, z2, z3, z11, z12, z13, z21, z22, z23, z31, z32, z33] = lunar_z;
+
+    let periodics = DeepSpacePeriodics {
+        zmol: (4.719_967_2 + 0.229_971_50 * day - gam) % (2.0 * PI),
+        zmos: (6.256_583_7 + 0.017_201_977 * day) % (2.0 * PI),
+        se2: 2.0 * ss1 * ss6,
+        se3: 2.0 * ss1 * ss7,
+        si2: 2.0 * ss2 * sz12,
+        si3: 2.0 * ss2 * (sz13 - sz11),
+        sl2: -2.0 * ss3 * sz2,
+        sl3: -2.0 * ss3 * (sz3 - sz1),
+        sl4: -2.0 * ss3 * (-21.0 - 9.0 * emsq) * ZES,
+        sgh2: 2.0 * ss4 * sz32,
+        sgh3: 2.0 * ss4 * (sz33 - sz31),
+        sgh4: -18.0 * ss4 * ZES,
+        sh2: -2.0 * ss2 * sz22,
+        sh3: -2.0 * ss2 * (sz23 - sz21),
+        ee2: 2.0 * s1 * s6,
+        e3: 2.0 * s1 * s7,
+        xi2: 2.0 * s2 * z12,
+        xi3: 2.0 * s2 * (z13 - z11),
+        xl2: -2.0 * s3 * z2,
+        xl3: -2.0 * s3 * (z3 - z1),
+        xl4: -2.0 * s3 * (-21.0 - 9.0 * emsq) * ZEL,
+        xgh2: 2.0 * s4 * z32,
+        xgh3: 2.0 * s4 * (z33 - z31),
+        xgh4: -18.0 * s4 * ZEL,
+        xh2: -2.0 * s2 * z22,
+        xh3: -2.0 * s2 * (z23 - z21),
+    };
+
+    DeepSpaceCommon {
+        periodics,
+        sinim, cosim, emsq,
+        s1, s2, s3, s4, s5,
+        ss1, ss2, ss3, ss4, ss5,
+        sz1, sz3, sz11, sz13, sz21, sz23, sz31, sz33,
+        z1, z3, z11, z13, z21, z23, z31, z33,
+    }
+}
+
+// Secular rates and resonance coefficients (dsinit)
+#[allow(clippy::too_many_arguments)]
+fn deep_space_init(common: &DeepSpaceCommon, gsto: f64, ecco: f64, eccsq: f64, inclo: f64, argpo: f64, nodeo: f64, mo: f64, no: f64, mdot: f64, nodedot: f64, xpidot: f64) -> DeepSpace {
+    const Q22: f64 = 1.789_167_9e-6;
+    const Q31: f64 = 2.146_074_8e-6;
+    const Q33: f64 = 2.212_301_5e-7;
+    const ROOT22: f64 = 1.789_167_9e-6;
+    const ROOT44: f64 = 7.363_695_3e-9;
+    const ROOT54: f64 = 2.176_580_3e-9;
+    const ROOT32: f64 = 3.739_379_2e-7;
+    const ROOT52: f64 = 1.142_863_9e-7;
+    const ZNL: f64 = 1.583_521_8e-4;
+    const ZNS: f64 = 1.194_59e-5;
+
+    let c = common;
+    let nm = no;
+    let em = ecco;
+    let (sinim, cosim, emsq) = (c.sinim, c.cosim, c.emsq);
+
+    let resonance = if 0.003_490_658_5 < nm && nm < 0.005_235_987_7 {
+        Resonance::Synchronous
+    } else if (8.26e-3..=9.24e-3).contains(&nm) && em >= 0.5 {
+        Resonance::HalfDay
+    } else {
+        Resonance::None
+    };
+
+    // Solar terms
+    let ses = c.ss1 * ZNS * c.ss5;
+    let sis = c.ss2 * ZNS * (c.sz11 + c.sz13);
+    let sls = -ZNS * c.ss3 * (c.sz1 + c.sz3 - 14.0 - 6.0 * emsq);
+    let sghs = c.ss4 * ZNS * (c.sz31 + c.sz33 - 6.0);
+    let near_equatorial = !(5.235_987_7e-2..=PI - 5.235_987_7e-2).contains(&inclo);
+    let mut shs = if near_equatorial { 0.0 } else { -ZNS * c.ss2 * (c.sz21 + c.sz23) };
+    if sinim != 0.0 {
+        shs /= sinim;
+    }
+    let sgs = sghs - cosim * shs;
+
+    // Lunar terms
+    let dedt = ses + c.s1 * ZNL * c.s5;
+    let didt = sis + c.s2 * ZNL * (c.z11 + c.z13);
+    let dmdt = sls - ZNL * c.s3 * (c.z1 + c.z3 - 14.0 - 6.0 * emsq);
+    let sghl = c.s4 * ZNL * (c.z31 + c.z33 - 6.0);
+    let shll = if near_equatorial { 0.0 } else { -ZNL * c.s2 * (c.z21 + c.z23) };
+    let mut domdt = sgs + sghl;
+    let mut dnodt = shs;
+    if sinim != 0.0 {
+        domdt -= cosim / sinim * shll;
+        dnodt += shll / sinim;
+    }
+
+    let mut deep = DeepSpace {
+        periodics: c.periodics.clone(),
+        resonance,
+        gsto,
+        dedt, didt, dmdt, dnodt, domdt,
+        d2201: 0.0, d2211: 0.0, d3210: 0.0, d3222: 0.0, d4410: 0.0, d4422: 0.0,
+        d5220: 0.0, d5232: 0.0, d5421: 0.0, d5433: 0.0,
+        del1: 0.0, del2: 0.0, del3: 0.0,
+        xfact: 0.0, xlamo: 0.0,
+    };
+    let theta = gsto % (2.0 * PI);
+    let aonv = (nm / xke()).powf(TWO_THIRDS);
+
+    match resonance {
+        Resonance::None => {},
+        Resonance::HalfDay => {
+            let cosisq = cosim * cosim;
+            let em = ecco;
+            let emsq = eccsq;
+            let eoc = em * emsq;
+            let g201 = -0.306 - (em - 0.64) * 0.440;
+            let (g211, g310, g322, g410, g422, g520);
+            if em <= 0.65 {
+                g211 = 3.616 - 13.2470 * em + 16.2900 * emsq;
+                g310 = -19.302 + 117.3900 * em - 228.4190 * emsq + 156.5910 * eoc;
+                g322 = -18.9068 + 109.7927 * em - 214.6334 * emsq + 146.5816 * eoc;
+                g410 = -41.122 + 242.6940 * em - 471.0940 * emsq + 313.9530 * eoc;
+                g422 = -146.407 + 841.8800 * em - 1629.014 * emsq + 1083.4350 * eoc;
+                g520 = -532.114 + 3017.977 * em - 5740.032 * emsq + 3708.2760 * eoc;
+            } else {
+                g211 = -72.099 + 331.819 * em - 508.738 * emsq + 266.724 * eoc;
+                g310 = -346.844 + 1582.851 * em - 2415.925 * emsq + 1246.113 * eoc;
+                g322 = -342.585 + 1554.908 * em - 2366.899 * emsq + 1215.972 * eoc;
+                g410 = -1052.797 + 4758.686 * em - 7193.992 * emsq + 3651.957 * eoc;
+                g422 = -3581.690 + 16178.110 * em - 24462.770 * emsq + 12422.520 * eoc;
+                g520 = if em > 0.715 {
+                    -5149.66 + 29936.92 * em - 54087.36 * emsq + 31324.56 * eoc
+                } else {
+                    1464.74 - 4664.75 * em + 3763.64 * emsq
+                };
+            }
+            let (g533, g521, g532) = if em < 0.7 {
+                (
+                    -919.22770 + 4988.6100 * em - 9064.7700 * emsq + 5542.21 * eoc,
+                    -822.71072 + 4568.6173 * em - 8491.4146 * emsq + 5337.524 * eoc,
+                    -853.66600 + 4690.2500 * em - 8624.7700 * emsq + 5341.4 * eoc,
+                )
+            } else {
+                (
+                    -37995.780 + 161616.52 * em - 229838.20 * emsq + 109377.94 * eoc,
+                    -51752.104 + 218913.95 * em - 309468.16 * emsq + 146349.42 * eoc,
+                    -40023.880 + 170470.89 * em - 242699.48 * emsq + 115605.82 * eoc,
+                )
+            };
+
+            let sini2 = sinim * sinim;
+            let f220 = 0.75 * (1.0 + 2.0 * cosim + cosisq);
+            let f221 = 1.5 * sini2;
+            let f321 = 1.875 * sinim * (1.0 - 2.0 * cosim - 3.0 * cosisq);
+            let f322 = -1.875 * sinim * (1.0 + 2.0 * cosim - 3.0 * cosisq);
+            let f441 = 35.0 * sini2 * f220;
+            let f442 = 39.3750 * sini2 * sini2;
+            let f522 = 9.84375 * sinim * (sini2 * (1.0 - 2.0 * cosim - 5.0 * cosisq) + 0.333_333_33 * (-2.0 + 4.0 * cosim + 6.0 * cosisq));
+            let f523 = sinim * (4.921_875_12 * sini2 * (-2.0 - 4.0 * cosim + 10.0 * cosisq) + 6.562_500_12 * (1.0 + 2.0 * cosim - 3.0 * cosisq));
+            let f542 = 29.53125 * sinim * (2.0 - 8.0 * cosim + cosisq * (-12.0 + 8.0 * cosim + 10.0 * cosisq));
+            let f543 = 29.53125 * sinim * (-2.0 - 8.0 * cosim + cosisq * (12.0 + 8.0 * cosim - 10.0 * cosisq));
+
+            let xno2 = nm * nm;
+            let ainv2 = aonv * aonv;
+            let mut temp1 = 3.0 * xno2 * ainv2;
+            let mut temp = temp1 * ROOT22;
+            deep.d2201 = temp * f220 * g201;
+            deep.d2211 = temp * f221 * g211;
+            temp1 *= aonv;
+            temp = temp1 * ROOT32;
+            deep.d3210 = temp * f321 * g310;
+            deep.d3222 = temp * f322 * g322;
+            temp1 *= aonv;
+            temp = 2.0 * temp1 * ROOT44;
+            deep.d4410 = temp * f441 * g410;
+            deep.d4422 = temp * f442 * g422;
+            temp1 *= aonv;
+            temp = temp1 * ROOT52;
+            deep.d5220 = temp * f522 * g520;
+            deep.d5232 = temp * f523 * g532;
+            temp = 2.0 * temp1 * ROOT54;
+            deep.d5421 = temp * f542 * g521;
+            deep.d5433 = temp * f543 * g533;
+            deep.xlamo = (mo + nodeo + nodeo - theta - theta) % (2.0 * PI);
+            deep.xfact = mdot + dmdt + 2.0 * (nodedot + dnodt - EARTH_ROTATION_RATE) - no;
+        },
+        Resonance::Synchronous => {
+            let g200 = 1.0 + emsq * (-2.5 + 0.8125 * emsq);
+            let g310 = 1.0 + 2.0 * emsq;
+            let g300 = 1.0 + emsq * (-6.0 + 6.609_37 * emsq);
+            let f220 = 0.75 * (1.0 + cosim) * (1.0 + cosim);
+            let f311 = 0.9375 * sinim * sinim * (1.0 + 3.0 * cosim) - 0.75 * (1.0 + cosim);
+            let f330 = 1.875 * (1.0 + cosim).powi(3);
+            let del1 = 3.0 * nm * nm * aonv * aonv;
+            deep.del2 = 2.0 * del1 * f220 * g200 * Q22;
+            deep.del3 = 3.0 * del1 * f330 * g300 * Q33 * aonv;
+            deep.del1 = del1 * f311 * g310 * Q31 * aonv;
+            deep.xlamo = (mo + nodeo + argpo - theta) % (2.0 * PI);
+            deep.xfact = mdot + xpidot - EARTH_ROTATION_RATE + dmdt + domdt + dnodt - no;
+        },
+    }
+    deep
+}
+
+// Deep-space secular effects and resonance integration (dspace). The resonance
+// integrator always restarts from the epoch, in steps of half a day.
+#[allow(clippy::too_many_arguments)]
+fn deep_space_secular(deep: &DeepSpace, sgp4: &Sgp4, t: f64, em: &mut f64, argpm: &mut f64, inclm: &mut f64, mm: &mut f64, nodem: &mut f64, nm: &mut f64) {
+    const FASX2: f64 = 0.131_309_08;
+    const FASX4: f64 = 2.884_319_8;
+    const FASX6: f64 = 0.374_480_87;
+    const G22: f64 = 5.768_639_6;
+    const G32: f64 = 0.952_408_98;
+    const G44: f64 = 1.801_499_8;
+    const G52: f64 = 1.050_833_0;
+    const G54: f64 = 4.410_889_8;
+    const STEP: f64 = 720.0;
+    const STEP2: f64 = 259_200.0;
+
+    let theta = (deep.gsto + t * EARTH_ROTATION_RATE) % (2.0 * PI);
+    *em += deep.dedt * t;
+    *inclm += deep.didt * t;
+    *argpm += deep.domdt * t;
+    *nodem += deep.dnodt * t;
+    *mm += deep.dmdt * t;
+
+    if deep.resonance == Resonance::None {
+        return;
+    }
+
+    let delt = if t > 0.0 { STEP } else { -STEP };
+    let mut atime = 0.0;
+    let mut xni = sgp4.no;
+    let mut xli = deep.xlamo;
+    let derivatives = |xli: f64, xni: f64, atime: f64| -> (f64, f64, f64) {
+        let xldot = xni + deep.xfact;
+        let (xndt, xnddt) = if deep.resonance == Resonance::Synchronous {
+            (
+                deep.del1 * (xli - FASX2).sin() + deep.del2 * (2.0 * (xli - FASX4)).sin() + deep.del3 * (3.0 * (xli - FASX6)).sin(),
+                deep.del1 * (xli - FASX2).cos() + 2.0 * deep.del2 * (2.0 * (xli - FASX4)).cos() + 3.0 * deep.del3 * (3.0 * (xli - FASX6)).cos(),
+            )
+        } else {
+            let xomi = sgp4.argpo + sgp4.argpdot * atime;
+            let x2omi = xomi + xomi;
+            let x2li = xli + xli;
+            (
+                deep.d2201 * (x2omi + xli - G22).sin() + deep.d2211 * (xli - G22).sin()
+                    + deep.d3210 * (xomi + xli - G32).sin() + deep.d3222 * (-xomi + xli - G32).sin()
+                    + deep.d4410 * (x2omi + x2li - G44).sin() + deep.d4422 * (x2li - G44).sin()
+                    + deep.d5220 * (xomi + xli - G52).sin() + deep.d5232 * (-xomi + xli - G52).sin()
+                    + deep.d5421 * (xomi + x2li - G54).sin() + deep.d5433 * (-xomi + x2li - G54).sin(),
+                deep.d2201 * (x2omi + xli - G22).cos() + deep.d2211 * (xli - G22).cos()
+                    + deep.d3210 * (xomi + xli - G32).cos() + deep.d3222 * (-xomi + xli - G32).cos()
+                    + deep.d5220 * (xomi + xli - G52).cos() + deep.d5232 * (-xomi + xli - G52).cos()
+                    + 2.0 * (deep.d4410 * (x2omi + x2li - G44).cos() + deep.d4422 * (x2li - G44).cos()
+                        + deep.d5421 * (xomi + x2li - G54).cos() + deep.d5433 * (-xomi + x2li - G54).cos()),
+            )
+        };
+        (xldot, xndt, xnddt * xldot)
+    };
+
+    let (mut xldot, mut xndt, mut xnddt) = derivatives(xli, xni, atime);
+    while (t - atime).abs() >= STEP {
+        xli += xldot * delt + xndt * STEP2;
+        xni += xndt * delt + xnddt * STEP2;
+        atime += delt;
+        (xldot, xndt, xnddt) = derivatives(xli, xni, atime);
+    }
+    let ft = t - atime;
+
+    *nm = xni + xndt * ft + xnddt * ft * ft * 0.5;
+    let xl = xli + xldot * ft + xndt * ft * ft * 0.5;
+    *mm = if deep.resonance == Resonance::Synchronous {
+        xl - *nodem - *argpm + theta
+    } else {
+        xl - 2.0 * *nodem + 2.0 * theta
+    };
+}
+
+// Lunar-solar periodics (dpper), applied with Lyddane's modification at low inclination
+fn deep_space_periodics(p: &DeepSpacePeriodics, t: f64, ep: &mut f64, inclp: &mut f64, nodep: &mut f64, argpp: &mut f64, mp: &mut f64) {
+    const ZNS: f64 = 1.194_59e-5;
+    const ZES: f64 = 0.01675;
+    const ZNL: f64 = 1.583_521_8e-4;
+    const ZEL: f64 = 0.05490;
+
+    let zm = p.zmos + ZNS * t;
+    let zf = zm + 2.0 * ZES * zm.sin();
+    let sinzf = zf.sin();
+    let f2 = 0.5 * sinzf * sinzf - 0.25;
+    let f3 = -0.5 * sinzf * zf.cos();
+    let ses = p.se2 * f2 + p.se3 * f3;
+    let sis = p.si2 * f2 + p.si3 * f3;
+    let sls = p.sl2 * f2 + p.sl3 * f3 + p.sl4 * sinzf;
+    let sghs = p.sgh2 * f2 + p.sgh3 * f3 + p.sgh4 * sinzf;
+    let shs = p.sh2 * f2 + p.sh3 * f3;
+
+    let zm = p.zmol + ZNL * t;
+    let zf = zm + 2.0 * ZEL * zm.sin();
+    let sinzf = zf.sin();
+    let f2 = 0.5 * sinzf * sinzf - 0.25;
+    let f3 = -0.5 * sinzf * zf.cos();
+    let sel = p.ee2 * f2 + p.e3 * f3;
+    let sil = p.xi2 * f2 + p.xi3 * f3;
+    let sll = p.xl2 * f2 + p.xl3 * f3 + p.xl4 * sinzf;
+    let sghl = p.xgh2 * f2 + p.xgh3 * f3 + p.xgh4 * sinzf;
+    let shll = p.xh2 * f2 + p.xh3 * f3;
+
+    let pe = ses + sel;
+    let pinc = sis + sil;
+    let pl = sls + sll;
+    let mut pgh = sghs + sghl;
+    let mut ph = shs + shll;
+
+    *inclp += pinc;
+    *ep += pe;
+    let (sinip, cosip) = inclp.sin_cos();
+    if *inclp >= 0.2 {
+        ph /= sinip;
+        pgh -= cosip * ph;
+        *argpp += pgh;
+        *nodep += ph;
+        *mp += pl;
+    } else {
+        let (sinop, cosop) = nodep.sin_cos();
+        let mut alfdp = sinip * sinop;
+        let mut betdp = sinip * cosop;
+        let dalf = ph * cosop + pinc * cosip * sinop;
+        let dbet = -ph * sinop + pinc * cosip * cosop;
+        alfdp += dalf;
+        betdp += dbet;
+        *nodep %= 2.0 * PI;
+        let mut xls = *mp + *argpp + cosip * *nodep;
+        let dls = pl + pgh - pinc * *nodep * sinip;
+        xls += dls;
+        let xnoh = *nodep;
+        *nodep = alfdp.atan2(betdp);
+        if (xnoh - *nodep).abs() > PI {
+            if *nodep < xnoh { *nodep += 2.0 * PI } else { *nodep -= 2.0 * PI }
+        }
+        *mp += pl;
+        *argpp = xls - *mp - cosip * *nodep;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reference states from Vallado et al. (2006), "Revisiting Spacetrack Report #3",
+    // in km and km/s: minutes since epoch, position, velocity
+    type Reference = (f64, [f64; 3], [f64; 3]);
+
+    fn check(line1: &str, line2: &str, deep_space: bool, references: &[Reference]) {
+        let tle = Tle::parse("", line1, line2).unwrap();
+        let sgp4 = Sgp4::new(&tle).unwrap();
+        assert_eq!(sgp4.is_deep_space(), deep_space);
+        for (minutes, position, velocity) in references {
+            let (r, v) = sgp4.propagate(*minutes).unwrap();
+            let (r, v) = ([r.x / 1e3, r.y / 1e3, r.z / 1e3], [v.x / 1e3, v.y / 1e3, v.z / 1e3]);
+            for k in 0..3 {
+                assert!((r[k] - position[k]).abs() < 1e-5, "t={} position {:?} != {:?}", minutes, r, position);
+                assert!((v[k] - velocity[k]).abs() < 1e-8, "t={} velocity {:?} != {:?}", minutes, v, velocity);
+            }
+        }
+    }
+
+    #[test]
+    fn near_earth_00005() {
+        check(
+            "1 00005U 58002B   00179.78495062  .00000023  00000-0  28098-4 0  4753",
+            "2 00005  34.2682 348.7242 1859667 331.7664  19.3264 10.82419157413667",
+            false,
+            &[
+                (0.0, [7022.46529266, -1400.08296755, 0.03995155], [1.893841015, 6.405893759, 4.534807250]),
+                (360.0, [-7154.03120202, -3783.17682504, -3536.19412294], [4.741887409, -4.151817765, -2.093935425]),
+                (720.0, [-7134.59340119, 6531.68641334, 3260.27186483], [-4.113793027, -2.911922039, -2.557327851]),
+                (1440.0, [-938.55923943, -6268.18748831, -4294.02924751], [7.536105209, -0.427127707, 0.989878080]),
+            ],
+        );
+    }
+
+    #[test]
+    fn near_earth_with_drag_06251() {
+        check(
+            "1 06251U 62025E   06176.82412014  .00008885  00000-0  12808-3 0  3985",
+            "2 06251  58.0579  54.0425 0030035 139.1568 221.1854 15.56387291  6774",
+            false,
+            &[
+                (0.0, [3988.31022699, 5498.96657235, 0.90055879], [-3.290032738, 2.357652820, 6.496623475]),
+                (120.0, [-3935.69800083, 409.10980837, 5471.33577327], [-3.374784183, -6.635211043, -1.942056221]),
+                (360.0, [4993.62642836, 2890.54969900, -3600.40145627], [0.347333429, 5.707031557, 5.070699638]),
+                (1440.0, [-2777.14682335, -5663.16031708, -2462.54889123], [4.915493146, 0.123328992, -5.896495091]),
+            ],
+        );
+    }
+
+    #[test]
+    fn deep_space_11801() {
+        check(
+            "1 11801U          80230.29629788  .01431103  00000-0  14311-1 0    13",
+            "2 11801  46.7916 230.4354 7318036  47.4722  10.4117  2.28537848    13",
+            true,
+            &[
+                (0.0, [7473.37102491, 428.94748312, 5828.74846783], [5.107155391, 6.444680305, -0.186133297]),
+                (360.0, [-3305.22148694, 32410.84323331, -24697.16974954], [-1.301137319, -1.151315600, -0.283335823]),
+            ],
+        );
+    }
+
+    #[test]
+    fn half_day_resonance_08195() {
+        check(
+            "1 08195U 75081A   06176.33215444  .00000099  00000-0  11873-3 0   813",
+            "2 08195  64.1586 279.0717 6877146 264.7651  20.2257  2.00491383225656",
+            true,
+            &[
+                (0.0, [2349.89483350, -14785.93811562, 0.02119378], [2.721488096, -3.256811655, 4.498416672]),
+                (120.0, [15223.91713658, -17852.95881713, 25280.39558224], [1.079041732, 0.875187372, 2.485682813]),
+                (360.0, [19089.29762968, 3107.89495018, 39958.14661370], [-0.410308034, 1.640332277, -0.306873818]),
+                (1440.0, [2890.80638268, -15446.43952300, 948.77010176], [2.654407490, -2.909344895, 4.486437362]),
+            ],
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use std::fs;
+use std::io;
+use crate::time::{self, CalendarDate};
+
+// NORAD two-line element set. Angles are converted to radians; the mean motion and its
+// derivatives stay in the revolutions per day of the format.
+#[derive(Clone, Debug)]
+pub struct Tle {
+    pub name: String,
+    pub catalog_number: u32,
+    pub classification: char,
+    pub international_designator: String,
+    pub epoch_julian_date: f64,     // UTC
+    pub epoch: f64,                 // simulation time, s past J2000
+    pub mean_motion_dot: f64,       // rev/day^2, first derivative over two
+    pub mean_motion_ddot: f64,      // rev/day^3, second derivative over six
+    pub bstar: f64,                 // 1/earth radii
+    pub element_set_number: u32,
+    pub inclination: f64,
+    pub raan: f64,
+    pub eccentricity: f64,
+    pub argument_of_perigee: f64,
+    pub mean_anomaly: f64,
+    pub mean_motion: f64,           // rev/day
+    pub revolution_number: u32,
+}
+
+impl Tle {
+    // Element set from its two data lines. Fields are read by column, so the checksums
+    // are not required to be right. `None` if a field does not parse.
+    pub fn parse(name: &str, line1: &str, line2: &str) -> Option<Tle> {
+        if !line1.starts_with('1') || !line2.starts_with('2') || line1.len() < 63 || line2.len() < 63 {
+            return None;
+        }
+        let number = |line: &str, start: usize, end: usize| field(line, start, end)?.parse::<f64>().ok();
+        let integer = |line: &str, start: usize, end: usize| field(line, start, end).map(|text| text.parse::<u32>().unwrap_or(0));
+
+        let year = field(line1, 19, 20)?.parse::<i64>().ok()?;
+        let year = if year < 57 { 2000 + year } else { 1900 + year };
+        let day_of_year = number(line1, 21, 32)?;
+        let new_year = CalendarDate { year, month: 1, day: 1, hour: 0, minute: 0, second: 0.0 }.to_time();
+        let epoch_julian_date = time::to_julian_date(new_year) + day_of_year - 1.0;
+
+        Some(Tle {
+            name: name.trim().trim_start_matches("0 ").to_string(),
+            catalog_number: field(line1, 3, 7)?.parse().ok()?,
+            classification: line1.chars().nth(7).unwrap_or('U'),
+            international_designator: field(line1, 10, 17)?.to_string(),
+            epoch_julian_date,
+            epoch: time::from_utc_julian_date(epoch_julian_date),
+            mean_motion_dot: number(line1, 34, 43)?,
+            mean_motion_ddot: parse_exponent(field(line1, 45, 52)?)?,
+            bstar: parse_exponent(field(line1, 54, 61)?)?,
+            element_set_number: integer(line1, 65, 68)?,
+            inclination: number(line2, 9, 16)?.to_radians(),
+            raan: number(line2, 18, 25)?.to_radians(),
+            eccentricity: format!("0.{}", field(line2, 27, 33)?).parse().ok()?,
+            argument_of_perigee: number(line2, 35, 42)?.to_radians(),
+            mean_anomaly: number(line2, 44, 51)?.to_radians(),
+            mean_motion: number(line2, 53, 63)?,
+            revolution_number: integer(line2, 64, 68)?,
+        })
+    }
+}
+
+// Trimmed text of the one-based, inclusive column range
+fn field(line: &str, start: usize, end: usize) -> Option<&str> {
+    line.get(start - 1..end.min(line.len())).map(str::trim)
+}
+
+// Fields written with an assumed leading decimal point and a signed exponent, such as
+// " 28098-4" for 0.28098e-4
+fn parse_exponent(text: &str) -> Option<f64> {
+    let text = text.trim();
+    if text.len() < 2 {
+        return text.parse().ok();
+    }
+    let (mantissa, exponent) = text.split_at(text.len() - 2);
+    let exponent: i32 = exponent.trim_start_matches('+').parse().ok()?;
+    let (sign, digits) = match mantissa.strip_prefix('-') {
+        Some(digits) => (-1.0, digits),
+        None => (1.0, mantissa.trim_start_matches('+')),
+    };
+    let mantissa: f64 = format!("0.{}", digits.trim()).parse().ok()?;
+    Some(sign * mantissa * 10f64.powi(exponent))
+}
+
+// Every element set in a file of two- or three-line entries, as distributed by
+// CelesTrak or Space-Track. Sets without a name line are named after their catalog
+// number; anything that does not parse is skipped.
+pub fn parse_tles(text: &str) -> Vec<Tle> {
+    let lines: Vec<&str> = text.lines().map(str::trim_end).filter(|line| !line.trim().is_empty()).collect();
+    let mut tles = Vec::new();
+    let mut n = 0;
+    while n + 1 < lines.len() {
+        let is_data = |line: &str, first: char| line.starts_with(first) && line.chars().nth(1) == Some(' ');
+        if is_data(lines[n], '1') && is_data(lines[n + 1], '2') {
+            let name = if n > 0 && !is_data(lines[n - 1], '2') { lines[n - 1] } else { "" };
+            if let Some(mut tle) = Tle::parse(name, lines[n], lines[n + 1]) {
+                if tle.name.is_empty() {
+                    tle.name = tle.catalog_number.to_string();
+                }
+                tles.push(tle);
+            }
+            n += 2;
+        } else {
+            n += 1;
+        }
+    }
+    tles
+}
+
+pub fn read_tles(path: &str) -> io::Result<Vec<Tle>> {
+    Ok(parse_tles(&fs::read_to_string(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ISS: &str = "ISS (ZARYA)
+1 25544U 98067A   08264.51782528 -.00002182  00000-0 -11606-4 0  2927
+2 25544  51.6416 247.4627 0006703 130.5360 325.0288 15.72125391563537
+";
+
+    #[test]
+    fn parses_every_field() {
+        let lines: Vec<&str> = ISS.lines().collect();
+        let tle = Tle::parse(lines[0], lines[1], lines[2]).unwrap();
+        assert_eq!(tle.name, "ISS (ZARYA)");
+        assert_eq!(tle.catalog_number, 25544);
+        assert_eq!(tle.classification, 'U');
+        assert_eq!(tle.international_designator, "98067A");
+        // 2008 September 20, 12:25:40.1 UTC
+        assert!((tle.epoch_julian_date - 2_454_730.017_825_28).abs() < 1e-8);
+        assert!((tle.mean_motion_dot + 0.000_021_82).abs() < 1e-12);
+        assert_eq!(tle.mean_motion_ddot, 0.0);
+        assert!((tle.bstar + 0.116_06e-4).abs() < 1e-12);
+        assert_eq!(tle.element_set_number, 292);
+        assert!((tle.inclination - 51.6416_f64.to_radians()).abs() < 1e-12);
+        assert!((tle.raan - 247.4627_f64.to_radians()).abs() < 1e-12);
+        assert!((tle.eccentricity - 0.000_670_3).abs() < 1e-12);
+        assert!((tle.argument_of_perigee - 130.536_f64.to_radians()).abs() < 1e-12);
+        assert!((tle.mean_anomaly - 325.0288_f64.to_radians()).abs() < 1e-12);
+        assert!((tle.mean_motion - 15.721_253_91).abs() < 1e-12);
+        assert_eq!(tle.revolution_number, 56353);
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        let lines: Vec<&str> = ISS.lines().collect();
+        assert!(Tle::parse("", lines[2], lines[1]).is_none());
+        assert!(Tle::parse("", &lines[1][..40], lines[2]).is_none());
+        assert!(Tle::parse("", &lines[1].replace("08264.51782528", "08264.5178252x"), lines[2]).is_none());
+    }
+
+    #[test]
+    fn reads_two_and_three_line_sets() {
+        let text = format!("{}\n{}\n{}\n", ISS,
+            "1 00005U 58002B   00179.78495062  .00000023  00000-0  28098-4 0  4753",
+            "2 00005  34.2682 348.7242 1859667 331.7664  19.3264 10.82419157413667");
+        let tles = parse_tles(&text);
+        assert_eq!(tles.len(), 2);
+        assert_eq!(tles[0].name, "ISS (ZARYA)");
+        // Without a name line the catalogue number stands in
+        assert_eq!(tles[1].name, "5");
+        assert!((tles[1].eccentricity - 0.185_966_7).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,314 @@
+use std::f64::consts::PI;
+use crate::geometry::{Matrix, Vector3};
+
+const TOLERANCE: f64 = 1e-13;
+const MAX_ITERATIONS: usize = 100;
+const LAGUERRE_ORDER: f64 = 5.0;
+
+// Stumpff functions C(z) = (1 - cos sqrt z) / z and S(z) = (sqrt z - sin sqrt z) / sqrt z^3,
+// continued through z <= 0 with their hyperbolic forms and near zero with their series
+pub fn stumpff_c(z: f64) -> f64 {
+    if z > 1e-6 {
+        (1.0 - z.sqrt().cos()) / z
+    } else if z < -1e-6 {
+        ((-z).sqrt().cosh() - 1.0) / -z
+    } else {
+        1.0 / 2.0 - z / 24.0 + z * z / 720.0
+    }
+}
+
+pub fn stumpff_s(z: f64) -> f64 {
+    if z > 1e-6 {
+        let root = z.sqrt();
+        (root - root.sin()) / (root * z)
+    } else if z < -1e-6 {
+        let root = (-z).sqrt();
+        (root.sinh() - root) / (root * -z)
+    } else {
+        1.0 / 6.0 - z / 120.0 + z * z / 5040.0
+    }
+}
+
+// Two-body state after `timestep` seconds from `position` and `velocity` relative to a
+// central body with gravitational parameter `mu`. Universal variables make this valid
+// for every conic; Kepler's equation is solved with the Laguerre-Conway iteration,
+// which converges from any starting guess.
+pub fn propagate_kepler(position: &Vector3, velocity: &Vector3, mu: f64, timestep: f64) -> (Vector3, Vector3) {
+    let r0 = position.magnitude();
+    if timestep == 0.0 || r0 == 0.0 || mu <= 0.0 {
+        return (position.add(&velocity.scale(timestep)), velocity.clone());
+    }
+
+    let (chi, timestep) = universal_anomaly(position, velocity, mu, timestep);
+    let root_mu = mu.sqrt();
+    let alpha = 2.0 / r0 - velocity.dot(velocity) / mu;
+    let z = alpha * chi * chi;
+    let (c, s) = (stumpff_c(z), stumpff_s(z));
+    let f = 1.0 - chi * chi / r0 * c;
+    let g = timestep - chi.powi(3) / root_mu * s;
+    let new_position = position.scale(f).add(&velocity.scale(g));
+
+    let r = new_position.magnitude();
+    let f_dot = root_mu / (r * r0) * chi * (z * s - 1.0);
+    let g_dot = 1.0 - chi * chi / r * c;
+    (new_position, position.scale(f_dot).add(&velocity.scale(g_dot)))
+}
+
+// Universal anomaly reached after `timestep`, with the timestep it belongs to: whole
+// revolutions of an ellipse change nothing, so only the remainder is solved
+fn universal_anomaly(position: &Vector3, velocity: &Vector3, mu: f64, timestep: f64) -> (f64, f64) {
+    let r0 = position.magnitude();
+    let root_mu = mu.sqrt();
+    let sigma0 = position.dot(velocity) / root_mu;
+    let alpha = 2.0 / r0 - velocity.dot(velocity) / mu;   // reciprocal semi-major axis
+
+    let timestep = if alpha > 0.0 {
+        let period = 2.0 * PI / (root_mu * alpha.powf(1.5));
+        let remainder = timestep % period;
+        if remainder.abs() > period / 2.0 { remainder - period.copysign(remainder) } else { remainder }
+    } else {
+        timestep
+    };
+
+    // Starting guesses from Vallado, Algorithm 8
+    let mut chi = if alpha > 0.0 {
+        root_mu * timestep * alpha
+    } else if alpha * r0 < -1e-6 {
+        let a = 1.0 / alpha;
+        let direction = timestep.signum();
+        let argument = -2.0 * mu * alpha * timestep
+            / (position.dot(velocity) + direction * (-mu * a).sqrt() * (1.0 - r0 * alpha));
+        if argument > 0.0 { direction * (-a).sqrt() * argument.ln() } else { root_mu * timestep / r0 }
+    } else {
+        root_mu * timestep / r0
+    };
+    let mut z = alpha * chi * chi;
+    for _ in 0..MAX_ITERATIONS {
+        let (c, s) = (stumpff_c(z), stumpff_s(z));
+        let function = sigma0 * chi * chi * c + (1.0 - alpha * r0) * chi.powi(3) * s + r0 * chi - root_mu * timestep;
+        let derivative = chi * chi * c + sigma0 * chi * (1.0 - z * s) + r0 * (1.0 - z * c);
+        let second_derivative = sigma0 * (1.0 - z * c) + (1.0 - alpha * r0) * chi * (1.0 - z * s);
+
+        let n = LAGUERRE_ORDER;
+        let discriminant = ((n - 1.0).powi(2) * derivative * derivative - n * (n - 1.0) * function * second_derivative).abs().sqrt();
+        let correction = n * function / (derivative + discriminant.copysign(derivative));
+        chi -= correction;
+        z = alpha * chi * chi;
+        if correction.abs() <= TOLERANCE * chi.abs().max(1.0) {
+            break;
+        }
+    }
+    (chi, timestep)
+}
+
+// Higher Stumpff functions c4(z) = (1/2 - C(z)) / z and c5(z) = (1/6 - S(z)) / z
+fn stumpff_c4(z: f64) -> f64 {
+    if z.abs() > 1e-3 { (0.5 - stumpff_c(z)) / z } else { 1.0 / 24.0 - z / 720.0 + z * z / 40_320.0 }
+}
+
+fn stumpff_c5(z: f64) -> f64 {
+    if z.abs() > 1e-3 { (1.0 / 6.0 - stumpff_s(z)) / z } else { 1.0 / 120.0 - z / 5040.0 + z * z / 362_880.0 }
+}
+
+// Analytic partials of the state after `timestep` with respect to the initial state
+// (6x6, position then velocity), from Battin (1999) section 9.7. Unlike the states, the
+// partials of an ellipse grow with every revolution, so these use the full timestep.
+pub fn kepler_transition_matrix(position: &Vector3, velocity: &Vector3, mu: f64, timestep: f64) -> Matrix {
+    let r0 = position.magnitude();
+    let mut matrix = Matrix::identity(6);
+    if timestep == 0.0 || r0 == 0.0 || mu <= 0.0 {
+        for k in 0..3 {
+            matrix.rows[k][k + 3] = timestep;
+        }
+        return matrix;
+    }
+
+    let root_mu = mu.sqrt();
+    let alpha = 2.0 / r0 - velocity.dot(velocity) / mu;
+    let (mut chi, remainder) = universal_anomaly(position, velocity, mu, timestep);
+    if alpha > 0.0 {
+        // Each skipped revolution advances the universal anomaly by 2 pi / sqrt(alpha)
+        let period = 2.0 * PI / (root_mu * alpha.powf(1.5));
+        chi += ((timestep - remainder) / period).round() * 2.0 * PI / alpha.sqrt();
+    }
+
+    let z = alpha * chi * chi;
+    let u1 = chi * (1.0 - z * stumpff_s(z));
+    let u2 = chi * chi * stumpff_c(z);
+    let u4 = chi.powi(4) * stumpff_c4(z);
+    let u5 = chi.powi(5) * stumpff_c5(z);
+
+    let (final_position, final_velocity) = propagate_kepler(position, velocity, mu, timestep);
+    let r = final_position.magnitude();
+    let f = 1.0 - u2 / r0;
+    let g_dot = 1.0 - u2 / r;
+    let f_dot = -root_mu * u1 / (r * r0);
+    let g = (r0 * u1 + position.dot(velocity) / root_mu * u2) / root_mu;
+    let c = (3.0 * u5 - chi * u4 - root_mu * timestep * u2) / root_mu;
+
+    let delta_r = final_position.subtract(position);
+    let delta_v = final_velocity.subtract(velocity);
+    let outer = |a: &Vector3, b: &Vector3| [[a.x * b.x, a.x * b.y, a.x * b.z], [a.y * b.x, a.y * b.y, a.y * b.z], [a.z * b.x, a.z * b.y, a.z * b.z]];
+    let terms = |parts: &[(f64, [[f64; 3]; 3])], diagonal: f64| {
+        let mut block = [[0.0; 3]; 3];
+        for (row, values) in block.iter_mut().enumerate() {
+            for (column, value) in values.iter_mut().enumerate() {
+                *value = parts.iter().map(|(scale, term)| scale * term[row][column]).sum::<f64>()
+                    + if row == column { diagonal } else { 0.0 };
+            }
+        }
+        block
+    };
+
+    let position_position = terms(&[
+        (r / mu, outer(&delta_v, &delta_v)),
+        (r0 * (1.0 - f) / r0.powi(3), outer(&final_position, position)),
+        (c / r0.powi(3), outer(&final_velocity, position)),
+    ], f);
+    let position_velocity = terms(&[
+        (r0 / mu * (1.0 - f), outer(&delta_r, velocity)),
+        (-r0 / mu * (1.0 - f), outer(&delta_v, position)),
+        (c / mu, outer(&final_velocity, velocity)),
+    ], g);
+
+    // The last term of the velocity-position block, r (r v^T - v r^T) r dv^T / (mu r)
+    let swirl = final_position.scale(final_position.dot(&final_velocity))
+        .subtract(&final_velocity.scale(r * r))
+        .scale(f_dot / (mu * r));
+    let velocity_position = terms(&[
+        (-1.0 / (r0 * r0), outer(&delta_v, position)),
+        (-1.0 / (r * r), outer(&final_position, &delta_v)),
+        (-f_dot / (r * r), outer(&final_position, &final_position)),
+        (1.0, outer(&swirl, &delta_v)),
+        (-mu * c / (r.powi(3) * r0.powi(3)), outer(&final_position, position)),
+    ], f_dot);
+    let velocity_velocity = terms(&[
+        (r0 / mu, outer(&delta_v, &delta_v)),
+        (r0 * (1.0 - f) / r.powi(3), outer(&final_position, position)),
+        (-c / r.powi(3), outer(&final_position, velocity)),
+    ], g_dot);
+
+    for row in 0..3 {
+        for column in 0..3 {
+            matrix.rows[row][column] = position_position[row][column];
+            matrix.rows[row][column + 3] = position_velocity[row][column];
+            matrix.rows[row + 3][column] = velocity_position[row][column];
+            matrix.rows[row + 3][column + 3] = velocity_velocity[row][column];
+        }
+    }
+    matrix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orbit::KeplerianElements;
+
+    const MU: f64 = 3.986_004_418e14;
+
+    fn assert_close(a: &Vector3, b: &Vector3, tolerance: f64) {
+        let error = a.subtract(b).magnitude();
+        assert!(error <= tolerance * b.magnitude(), "{:?} != {:?} ({} off)", a, b, error);
+    }
+
+    // Planar state on a conic with semi-latus rectum `p` at true anomaly `nu`
+    fn perifocal_state(p: f64, eccentricity: f64, nu: f64) -> (Vector3, Vector3) {
+        let r = p / (1.0 + eccentricity * nu.cos());
+        let speed = (MU / p).sqrt();
+        (
+            Vector3::new(r * nu.cos(), r * nu.sin(), 0.0),
+            Vector3::new(-speed * nu.sin(), speed * (eccentricity + nu.cos()), 0.0),
+        )
+    }
+
+    // Planar hyperbolic state with semi-major axis magnitude `a` at hyperbolic anomaly `h`
+    fn hyperbolic_state(a: f64, eccentricity: f64, h: f64) -> (Vector3, Vector3) {
+        let b = a * (eccentricity * eccentricity - 1.0).sqrt();
+        let rate = (MU / a.powi(3)).sqrt() / (eccentricity * h.cosh() - 1.0);
+        (
+            Vector3::new(a * (eccentricity - h.cosh()), b * h.sinh(), 0.0),
+            Vector3::new(-a * h.sinh() * rate, b * h.cosh() * rate, 0.0),
+        )
+    }
+
+    #[test]
+    fn ellipse_returns_after_whole_periods() {
+        let elements = KeplerianElements::new(2.6e7, 0.7, 1.1, 0.4, 2.0, 0.3);
+        let (position, velocity) = elements.to_state(MU);
+        let period = elements.period(MU).unwrap();
+
+        for revolutions in [1.0, 5.0, -3.0] {
+            let (final_position, final_velocity) = propagate_kepler(&position, &velocity, MU, revolutions * period);
+            assert_close(&final_position, &position, 1e-12);
+            assert_close(&final_velocity, &velocity, 1e-12);
+        }
+    }
+
+    #[test]
+    fn ellipse_matches_kepler_equation() {
+        // Time of flight between eccentric anomalies from M = E - e sin E
+        let (a, eccentricity): (f64, f64) = (2.6e7, 0.7);
+        let p = a * (1.0 - eccentricity * eccentricity);
+        let true_anomaly = |e: f64| 2.0 * (((1.0 + eccentricity) / (1.0 - eccentricity)).sqrt() * (e / 2.0).tan()).atan();
+        let mean_anomaly = |e: f64| e - eccentricity * e.sin();
+        let (start, end) = (-2.5, 1.2);
+        let timestep = (mean_anomaly(end) - mean_anomaly(start)) / (MU / a.powi(3)).sqrt();
+
+        let (position, velocity) = perifocal_state(p, eccentricity, true_anomaly(start));
+        let (expected_position, expected_velocity) = perifocal_state(p, eccentricity, true_anomaly(end));
+        let (final_position, final_velocity) = propagate_kepler(&position, &velocity, MU, timestep);
+        assert_close(&final_position, &expected_position, 1e-11);
+        assert_close(&final_velocity, &expected_velocity, 1e-11);
+    }
+
+    #[test]
+    fn parabola_matches_barker_equation() {
+        // t = sqrt(p^3 / mu) / 2 (D + D^3 / 3) with D = tan(nu / 2)
+        let p: f64 = 1.4e7;
+        let barker = |nu: f64| (nu / 2.0).tan() + (nu / 2.0).tan().powi(3) / 3.0;
+        let (start, end) = (-1.9, 2.2);
+        let timestep = (p.powi(3) / MU).sqrt() / 2.0 * (barker(end) - barker(start));
+
+        let (position, velocity) = perifocal_state(p, 1.0, start);
+        let (expected_position, expected_velocity) = perifocal_state(p, 1.0, end);
+        let (final_position, final_velocity) = propagate_kepler(&position, &velocity, MU, timestep);
+        assert_close(&final_position, &expected_position, 1e-9);
+        assert_close(&final_velocity, &expected_velocity, 1e-9);
+    }
+
+    #[test]
+    fn hyperbola_matches_kepler_equation() {
+        // t = sqrt(a^3 / mu) (e sinh H - H) between hyperbolic anomalies
+        let (a, eccentricity): (f64, f64) = (2.0e7, 1.8);
+        let mean_anomaly = |h: f64| eccentricity * h.sinh() - h;
+        let (start, end) = (-1.0, 2.5);
+        let timestep = (a.powi(3) / MU).sqrt() * (mean_anomaly(end) - mean_anomaly(start));
+
+        let (position, velocity) = hyperbolic_state(a, eccentricity, start);
+        let (expected_position, expected_velocity) = hyperbolic_state(a, eccentricity, end);
+        let (final_position, final_velocity) = propagate_kepler(&position, &velocity, MU, timestep);
+        assert_close(&final_position, &expected_position, 1e-11);
+        assert_close(&final_velocity, &expected_velocity, 1e-11);
+    }
+
+    #[test]
+    fn negative_timesteps_run_backwards() {
+        let (a, eccentricity): (f64, f64) = (2.0e7, 1.8);
+        let mean_anomaly = |h: f64| eccentricity * h.sinh() - h;
+        let timestep = (a.powi(3) / MU).sqrt() * (mean_anomaly(-0.5) - mean_anomaly(1.5));
+        let (position, velocity) = hyperbolic_state(a, eccentricity, 1.5);
+        let (expected_position, expected_velocity) = hyperbolic_state(a, eccentricity, -0.5);
+        let (final_position, final_velocity) = propagate_kepler(&position, &velocity, MU, timestep);
+        assert_close(&final_position, &expected_position, 1e-11);
+        assert_close(&final_velocity, &expected_velocity, 1e-11);
+
+        // Forwards and back again on every kind of conic
+        for (p, eccentricity) in [(1.0e7, 0.3), (1.4e7, 1.0), (1.2e7, 2.5)] {
+            let (position, velocity) = perifocal_state(p, eccentricity, 0.4);
+            let (middle_position, middle_velocity) = propagate_kepler(&position, &velocity, MU, 7000.0);
+            let (final_position, final_velocity) = propagate_kepler(&middle_position, &middle_velocity, MU, -7000.0);
+            assert_close(&final_position, &position, 1e-11);
+            assert_close(&final_velocity, &velocity, 1e-11);
+        }
+    }
+}
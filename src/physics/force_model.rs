@@ -0,0 +1,376 @@
+use crate::body::{self, BodyType, CelestialBody};
+use crate::geometry::{Matrix, Vector3};
+use crate::mission::ThrustArc;
+use super::{forces, nongravitational, potentials, radiation, shadow, tides, Derivatives, State, Variation};
+
+// Per-body physical properties that stay fixed during a single integration step
+#[derive(Clone, Debug)]
+pub struct BodyParameters {
+    pub body_type: BodyType,
+    pub radius: f64,            // m
+    pub area_to_mass: f64,
+    pub reflectivity: f64,
+    pub thrust_arcs: Vec<ThrustArc>,
+    pub dry_mass: f64,
+    pub love_number: f64,
+    pub nongravitational: [f64; 3],
+    pub beta: f64,
+    pub spin: Vector3,          // rad/s, zero for bodies without a rotation model
+    pub primary: Option<usize>,
+}
+
+impl BodyParameters {
+    pub fn from_body(body: &CelestialBody, time: f64, primary: Option<usize>) -> Self {
+        BodyParameters {
+            body_type: body.body_type,
+            radius: body.km_radius * 1000.0,
+            area_to_mass: body.area_to_mass,
+            reflectivity: body.reflectivity,
+            thrust_arcs: body.thrust_arcs.clone(),
+            dry_mass: body.dry_mass,
+            love_number: body.love_number,
+            nongravitational: body.nongravitational,
+            beta: body.beta,
+            spin: body.rotation.as_ref()
+                .map(|rotation| rotation.spin_vector(time))
+                .unwrap_or(Vector3::new(0.0, 0.0, 0.0)),
+            primary,
+        }
+    }
+
+    fn feels_radiation_pressure(&self) -> bool {
+        matches!(self.body_type, BodyType::Satellite | BodyType::Asteroid) && self.area_to_mass > 0.0
+    }
+}
+
+// Tides raised on `deformed` by `perturber`
+#[derive(Clone, Debug)]
+pub struct TidalPair {
+    pub deformed: usize,
+    pub perturber: usize,
+    pub time_lag: f64,          // s
+}
+
+pub struct ForceModel {
+    pub bodies: Vec<BodyParameters>,
+    pub star: Option<usize>,
+    pub shadow_model: shadow::ShadowModel,
+    pub tidal_pairs: Vec<TidalPair>,
+    pub occulters: Vec<usize>,      // bodies casting shadows, see shadow::casts_shadow
+    pub potentials: Vec<(potentials::ExternalPotential, Option<usize>)>,    // with the index of their centre body
+}
+
+impl ForceModel {
+    pub fn new(bodies: &[CelestialBody], time: f64, shadow_model: shadow::ShadowModel, external_potentials: &[potentials::ExternalPotential]) -> Self {
+        // Massless particles without thrust never need their primary, which keeps
+        // large test-particle clouds cheap to set up
+        let parameters: Vec<BodyParameters> = bodies.iter()
+            .enumerate()
+            .map(|(i, body)| {
+                let needs_primary = body.mass > 0.0 || !body.thrust_arcs.is_empty();
+                BodyParameters::from_body(body, time, if needs_primary { body::find_primary(bodies, i) } else { None })
+            })
+            .collect();
+
+        ForceModel {
+            tidal_pairs: find_tidal_pairs(bodies, &parameters),
+            bodies: parameters,
+            star: bodies.iter().position(|body| body.body_type == BodyType::Star),
+            shadow_model,
+            occulters: bodies.iter()
+                .enumerate()
+                .filter(|(_, body)| shadow::casts_shadow(body))
+                .map(|(i, _)| i)
+                .collect(),
+            potentials: external_potentials.iter()
+                .filter_map(|potential| match potential.centre {
+                    potentials::PotentialCentre::Point(_) => Some((potential.clone(), None)),
+                    // Potentials whose body has been merged away are dropped
+                    potentials::PotentialCentre::Body(id) => bodies.iter()
+                        .position(|body| body.id == id)
+                        .map(|index| (potential.clone(), Some(index))),
+                })
+                .collect(),
+        }
+    }
+
+    // Force on the perturber from the bulge it raises on the deformed body
+    fn tidal_force(&self, state: &State, pair: &TidalPair) -> Vector3 {
+        let deformed = &self.bodies[pair.deformed];
+        let position = state.positions[pair.perturber].subtract(&state.positions[pair.deformed]);
+        let velocity = state.velocities[pair.perturber].subtract(&state.velocities[pair.deformed]);
+        tides::calculate_tidal_force(
+            state.masses[pair.perturber],
+            deformed.radius,
+            deformed.love_number,
+            pair.time_lag,
+            &position,
+            &velocity,
+            &deformed.spin,
+        )
+    }
+
+    // Torque the perturbers exert on each deformed body's bulge, with the body's index.
+    // It is the reverse of the orbital torque, so total angular momentum is conserved.
+    pub fn tidal_torques(&self, state: &State) -> Vec<(usize, Vector3)> {
+        self.tidal_pairs.iter()
+            .map(|pair| {
+                let position = state.positions[pair.perturber].subtract(&state.positions[pair.deformed]);
+                (pair.deformed, self.tidal_force(state, pair).cross(&position))
+            })
+            .collect()
+    }
+
+    pub fn calculate_derivatives(&self, state: &State) -> Derivatives {
+        let mut accelerations = self.gravitational_accelerations(state);
+        let mut mass_rates = vec![0.0; state.masses.len()];
+
+        if let Some(star) = self.star {
+            for (i, acceleration) in accelerations.iter_mut().enumerate() {
+                if i != star && self.bodies[i].feels_radiation_pressure() {
+                    *acceleration = acceleration.add(&self.radiation_pressure(state, i, star));
+                }
+                if i != star && self.bodies[i].nongravitational != [0.0; 3] {
+                    *acceleration = acceleration.add(&nongravitational::calculate_nongravitational_acceleration(
+                        &state.positions[i].subtract(&state.positions[star]),
+                        &state.velocities[i].subtract(&state.velocities[star]),
+                        &self.bodies[i].nongravitational,
+                    ));
+                }
+                if i != star && self.bodies[i].beta > 0.0 {
+                    *acceleration = acceleration.add(&radiation::calculate_poynting_robertson(
+                        &state.positions[i].subtract(&state.positions[star]),
+                        &state.velocities[i].subtract(&state.velocities[star]),
+                        state.masses[star],
+                        self.bodies[i].beta,
+                        self.illumination(state, i, star),
+                    ));
+                }
+            }
+        }
+
+        for (potential, centre_index) in &self.potentials {
+            let centre = match (&potential.centre, centre_index) {
+                (_, Some(index)) => state.positions[*index].clone(),
+                (potentials::PotentialCentre::Point(point), None) => point.clone(),
+                (potentials::PotentialCentre::Body(_), None) => continue,
+            };
+            for (i, acceleration) in accelerations.iter_mut().enumerate() {
+                if Some(i) != *centre_index {
+                    *acceleration = acceleration.add(&potential.acceleration(&state.positions[i].subtract(&centre)));
+                }
+            }
+        }
+
+        for pair in &self.tidal_pairs {
+            let force = self.tidal_force(state, pair);
+
+            // Equal and opposite reaction on the deformed body keeps momentum conserved
+            if state.masses[pair.perturber] > 0.0 {
+                accelerations[pair.perturber] = accelerations[pair.perturber].add(&force.scale(1.0 / state.masses[pair.perturber]));
+            }
+            if state.masses[pair.deformed] > 0.0 {
+                accelerations[pair.deformed] = accelerations[pair.deformed].subtract(&force.scale(1.0 / state.masses[pair.deformed]));
+            }
+        }
+
+        for (i, acceleration) in accelerations.iter_mut().enumerate() {
+            if let Some((thrust, mass_rate)) = self.thrust(state, i) {
+                *acceleration = acceleration.add(&thrust);
+                mass_rates[i] = mass_rate;
+            }
+        }
+
+        Derivatives {
+            velocities: state.velocities.clone(),
+            accelerations,
+            mass_rates,
+            variation_rates: state.variations.iter().map(|variation| self.variation_rate(state, variation)).collect(),
+        }
+    }
+
+    // Variational equations d(phi)/dt = A phi with A = [[0, I], [G, 0]], where G is the
+    // point-mass gravity gradient of every other massive body. The other bodies are taken
+    // as given, and the remaining terms of the force model are left out of the partials.
+    fn variation_rate(&self, state: &State, variation: &Variation) -> Matrix {
+        let i = variation.index;
+        let mut gradient = [[0.0; 3]; 3];
+        for j in (0..state.positions.len()).filter(|&j| j != i && state.masses[j] > 0.0) {
+            let term = forces::calculate_gravity_gradient(state.masses[j], &state.positions[j].subtract(&state.positions[i]));
+            for (row, term_row) in gradient.iter_mut().zip(&term.rows) {
+                row.iter_mut().zip(term_row).for_each(|(value, term)| *value += term);
+            }
+        }
+
+        let phi = &variation.matrix.rows;
+        let mut rows = phi[3..].to_vec();
+        for gradient_row in &gradient {
+            rows.push((0..6).map(|column| (0..3).map(|k| gradient_row[k] * phi[k][column]).sum()).collect());
+        }
+        Matrix::new(rows)
+    }
+
+    fn gravitational_accelerations(&self, state: &State) -> Vec<Vector3> {
+        let num_bodies = state.positions.len();
+        let mut accelerations = vec![Vector3::new(0.0, 0.0, 0.0); num_bodies];
+
+        let attractors: Vec<usize> = (0..num_bodies).filter(|&j| state.masses[j] > 0.0).collect();
+
+        for (i, acceleration) in accelerations.iter_mut().enumerate() {
+            for &j in &attractors {
+                if i != j {
+                    let distance = state.positions[j].subtract(&state.positions[i]);
+                    *acceleration = acceleration.add(&forces::calculate_gravitational_acceleration(state.masses[j], &distance));
+                }
+            }
+        }
+        accelerations
+    }
+
+    fn radiation_pressure(&self, state: &State, index: usize, star: usize) -> Vector3 {
+        let body = &self.bodies[index];
+        let illumination = self.illumination(state, index, star);
+        if illumination <= 0.0 {
+            return Vector3::new(0.0, 0.0, 0.0);
+        }
+        radiation::calculate_radiation_pressure(&state.positions[index].subtract(&state.positions[star]), body.area_to_mass, body.reflectivity, illumination)
+    }
+
+    // Fraction of the star's light reaching body `index` past every occulter
+    fn illumination(&self, state: &State, index: usize, star: usize) -> f64 {
+        let mut illumination = 1.0;
+        for &j in &self.occulters {
+            if j != index && j != star {
+                illumination *= shadow::illumination_fraction(
+                    self.shadow_model,
+                    &state.positions[index],
+                    &state.positions[star],
+                    self.bodies[star].radius,
+                    &state.positions[j],
+                    self.bodies[j].radius,
+                );
+            }
+        }
+        illumination
+    }
+
+    // Thrust acceleration and mass flow of the arcs active on body `index`
+    fn thrust(&self, state: &State, index: usize) -> Option<(Vector3, f64)> {
+        let body = &self.bodies[index];
+        let mass = state.masses[index];
+        if body.dry_mass <= 0.0 || mass <= body.dry_mass {
+            return None;
+        }
+
+        let velocity = match body.primary {
+            Some(primary) => state.velocities[index].subtract(&state.velocities[primary]),
+            None => state.velocities[index].clone(),
+        };
+
+        let mut acceleration = Vector3::new(0.0, 0.0, 0.0);
+        let mut mass_rate = 0.0;
+        let mut active = false;
+        for arc in body.thrust_arcs.iter().filter(|arc| arc.is_active(state.time)) {
+            acceleration = acceleration.add(&arc.direction(state.time, &velocity).scale(arc.thrust / mass));
+            mass_rate -= arc.mass_flow_rate();
+            active = true;
+        }
+
+        if active { Some((acceleration, mass_rate)) } else { None }
+    }
+}
+
+// Tidally deformable bodies paired with the bodies orbiting them (or that they orbit).
+// The time lag follows from Q at the current tidal frequency, twice the difference
+// between the body's spin and the orbital mean motion. Bodies need a rotation model,
+// as the bulge of a body with unknown spin would be dragged the wrong way.
+fn find_tidal_pairs(bodies: &[CelestialBody], parameters: &[BodyParameters]) -> Vec<TidalPair> {
+    let mut pairs = Vec::new();
+    for (deformed, body) in bodies.iter().enumerate() {
+        if body.love_number <= 0.0 || body.tidal_q <= 0.0 || body.rotation.is_none() {
+            continue;
+        }
+
+        for (perturber, other) in bodies.iter().enumerate() {
+            let orbiting = parameters[perturber].primary == Some(deformed) || parameters[deformed].primary == Some(perturber);
+            if perturber == deformed || !orbiting {
+                continue;
+            }
+
+            let position = other.position.subtract(&body.position);
+            let velocity = other.velocity.subtract(&body.velocity);
+            let mu = forces::GRAVITATIONAL_CONST * (body.mass + other.mass);
+            let inverse_semi_major_axis = 2.0 / position.magnitude() - velocity.dot(&velocity) / mu;
+            if inverse_semi_major_axis <= 0.0 {
+                continue;
+            }
+
+            let mean_motion = (mu * inverse_semi_major_axis.powi(3)).sqrt();
+            let spin_rate = parameters[deformed].spin.dot(&position.cross(&velocity).norm());
+            pairs.push(TidalPair {
+                deformed,
+                perturber,
+                time_lag: tides::calculate_time_lag(body.tidal_q, 2.0 * (spin_rate - mean_motion).abs()),
+            });
+        }
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUN_MASS: f64 = 1.989e30;
+    const EARTH_MASS: f64 = 5.972e24;
+
+    // Radiation pressure on a satellite 7000 km from an Earth at 1 AU, on the line
+    // through the Sun at `side` = -1 (day side) or +1 (night side), under `shadow_model`
+    fn radiation_acceleration(side: f64, shadow_model: shadow::ShadowModel) -> Vector3 {
+        let sun = CelestialBody::new(
+            "Sun".to_string(), BodyType::Star, Vector3::new(0.0, 0.0, 0.0), 695_700.0, SUN_MASS,
+            Vector3::new(0.0, 0.0, 0.0), [1.0, 1.0, 0.0],
+        );
+        let earth = CelestialBody::new(
+            "Earth".to_string(), BodyType::Planet, Vector3::new(radiation::ASTRONOMICAL_UNIT, 0.0, 0.0), 6378.137, EARTH_MASS,
+            Vector3::new(0.0, 0.0, 0.0), [0.0, 0.0, 1.0],
+        );
+        let satellite = |area_to_mass| CelestialBody::new(
+            "Satellite".to_string(), BodyType::Satellite, Vector3::new(radiation::ASTRONOMICAL_UNIT + side * 7.0e6, 0.0, 0.0), 0.001, 1000.0,
+            Vector3::new(0.0, 0.0, 0.0), [1.0, 1.0, 1.0],
+        ).with_radiation_pressure(area_to_mass, 1.3);
+
+        // Difference from the same satellite without radiation pressure
+        let accelerations: Vec<Vector3> = [0.02, 0.0].iter()
+            .map(|&area_to_mass| {
+                let bodies = vec![sun.clone(), earth.clone(), satellite(area_to_mass)];
+                let state = State::new(
+                    bodies.iter().map(|body| body.position.clone()).collect(),
+                    bodies.iter().map(|body| body.velocity.clone()).collect(),
+                    bodies.iter().map(|body| body.mass).collect(),
+                    0.0,
+                );
+                ForceModel::new(&bodies, 0.0, shadow_model, &[]).calculate_derivatives(&state).accelerations[2].clone()
+            })
+            .collect();
+        accelerations[0].subtract(&accelerations[1])
+    }
+
+    #[test]
+    fn radiation_pressure_is_switched_off_in_shadow() {
+        // 7000 km from Earth's centre the Sun is about 1 AU away, so the lit
+        // acceleration is close to 4.56e-6 * 1.3 * 0.02 = 1.1856e-7 m/s^2
+        for shadow_model in [shadow::ShadowModel::Cylindrical, shadow::ShadowModel::Conical] {
+            let lit = radiation_acceleration(-1.0, shadow_model);
+            assert!((lit.magnitude() - 1.1856e-7).abs() < 1e-9, "{:?}: {:e}", shadow_model, lit.magnitude());
+            assert!(lit.x > 0.0);
+
+            let shadowed = radiation_acceleration(1.0, shadow_model);
+            assert!(shadowed.magnitude() < 1e-20, "{:?}: {:e}", shadow_model, shadowed.magnitude());
+        }
+
+        // Without a shadow model the night side is lit as well
+        let unshadowed = radiation_acceleration(1.0, shadow::ShadowModel::None);
+        assert!((unshadowed.magnitude() - 1.1856e-7).abs() < 1e-9);
+    }
+}
@@ -1,9 +1,9 @@
-use crate::geometry;
+use crate::geometry::{self, Matrix3};
 
-const GRAVITATIONAL_CONST: f64 = 6.6743e-11;
+pub const GRAVITATIONAL_CONST: f64 = 6.6743e-11;
 
 pub fn calculate_force_euler(mass1: f64, mass2: f64, distance: geometry::Vector3) -> geometry::Vector3 {
-    let force_magnitude = GRAVITATIONAL_CONST * mass1 * mass2 / distance.powi(2).magnitude();
+    let force_magnitude = GRAVITATIONAL_CONST * mass1 * mass2 / distance.dot(&distance);
     let force_direction = distance.norm();
 
     force_direction.scale(force_magnitude)
@@ -11,4 +11,27 @@ pub fn calculate_force_euler(mass1: f64, mass2: f64, distance: geometry::Vector3
 
 pub fn calculate_acceleration(force: geometry::Vector3, mass: f64) -> geometry::Vector3 {
     force.scale(1.0 / mass)
-}
\ No newline at end of file
+}
+
+// Acceleration towards an attracting mass, independent of the attracted body's own mass
+// so that massless test particles are handled too
+pub fn calculate_gravitational_acceleration(attractor_mass: f64, distance: &geometry::Vector3) -> geometry::Vector3 {
+    let distance_squared = distance.dot(distance);
+    distance.scale(GRAVITATIONAL_CONST * attractor_mass / (distance_squared * distance_squared.sqrt()))
+}
+
+// Gravity gradient, the partials of `calculate_gravitational_acceleration` with respect
+// to the attracted body's position
+pub fn calculate_gravity_gradient(attractor_mass: f64, distance: &geometry::Vector3) -> Matrix3 {
+    let distance_squared = distance.dot(distance);
+    let factor = GRAVITATIONAL_CONST * attractor_mass / (distance_squared * distance_squared.sqrt());
+    let components = [distance.x, distance.y, distance.z];
+    let mut rows = [[0.0; 3]; 3];
+    for (i, row) in rows.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            let diagonal = if i == j { 1.0 } else { 0.0 };
+            *value = factor * (3.0 * components[i] * components[j] / distance_squared - diagonal);
+        }
+    }
+    Matrix3::new(rows)
+}
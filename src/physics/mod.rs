@@ -1,4 +1,16 @@
 mod state;
 mod forces;
-pub use state::State;
-pub use forces::*;
\ No newline at end of file
+mod force_model;
+mod nongravitational;
+mod potentials;
+mod radiation;
+mod shadow;
+mod tides;
+pub use state::{Derivatives, State, Variation};
+pub use forces::*;
+pub use force_model::{BodyParameters, ForceModel, TidalPair};
+pub use nongravitational::*;
+pub use potentials::*;
+pub use radiation::*;
+pub use shadow::*;
+pub use tides::*;
@@ -0,0 +1,118 @@
+use crate::geometry::Vector3;
+use crate::time::SECONDS_PER_DAY;
+use super::ASTRONOMICAL_UNIT;
+
+// Water-ice sublimation constants of the Marsden-Sekanina g(r) function
+const ALPHA: f64 = 0.111_262_042_6;
+const R0: f64 = 2.808;      // AU
+const M: f64 = 2.15;
+const N: f64 = 5.093;
+const K: f64 = 4.6142;
+
+// Normalised outgassing rate at `distance` AU from the star, g(1 AU) = 1
+pub fn sublimation_g(distance: f64) -> f64 {
+    let ratio = distance / R0;
+    ALPHA * ratio.powf(-M) * (1.0 + ratio.powf(N)).powf(-K)
+}
+
+// Marsden, Sekanina & Yeomans (1973) non-gravitational acceleration. `parameters` holds
+// A1, A2, A3 in AU/day^2 as published; `position` and `velocity` are heliocentric.
+pub fn calculate_nongravitational_acceleration(position: &Vector3, velocity: &Vector3, parameters: &[f64; 3]) -> Vector3 {
+    let distance = position.magnitude();
+    let radial = position.scale(1.0 / distance);
+    let normal = position.cross(velocity).norm();
+    let transverse = normal.cross(&radial);
+
+    let scale = sublimation_g(distance / ASTRONOMICAL_UNIT) * ASTRONOMICAL_UNIT / (SECONDS_PER_DAY * SECONDS_PER_DAY);
+    radial.scale(parameters[0])
+        .add(&transverse.scale(parameters[1]))
+        .add(&normal.scale(parameters[2]))
+        .scale(scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+    use super::*;
+    use crate::body::{BodyType, CelestialBody};
+    use crate::integrators::IntegratorType;
+    use crate::orbit::KeplerianElements;
+    use crate::physics::GRAVITATIONAL_CONST;
+    use crate::solar_system::SolarSystem;
+
+    // 1P/Halley, A1 = +2.7e-10 and A2 = +1.55e-10 AU/day^2 (JPL small-body solution),
+    // with no normal component
+    const HALLEY: [f64; 3] = [2.7e-10, 1.55e-10, 0.0];
+
+    fn acceleration_at(distance: f64) -> Vector3 {
+        let position = Vector3::new(distance * ASTRONOMICAL_UNIT, 0.0, 0.0);
+        let velocity = Vector3::new(0.0, 40_000.0, 0.0);
+        calculate_nongravitational_acceleration(&position, &velocity, &HALLEY)
+    }
+
+    #[test]
+    fn g_is_normalised_at_one_au() {
+        assert!((sublimation_g(1.0) - 1.0).abs() < 1e-8);
+        assert!((sublimation_g(2.0) - 0.108_537).abs() < 1e-6);
+    }
+
+    #[test]
+    fn halley_at_one_au() {
+        // 1 AU/day^2 = 20.040 m/s^2 and g(1) = 1
+        let acceleration = acceleration_at(1.0);
+        assert!((acceleration.x - 5.4108e-9).abs() < 1e-12);
+        assert!((acceleration.y - 3.1062e-9).abs() < 1e-12);
+        assert!(acceleration.z.abs() < 1e-20);
+    }
+
+    #[test]
+    fn halley_at_perihelion() {
+        // q = 0.587 AU, where g = 3.2147
+        let acceleration = acceleration_at(0.587);
+        assert!((acceleration.x - 1.7394e-8).abs() < 1e-12);
+        assert!((acceleration.y - 9.9854e-9).abs() < 1e-12);
+    }
+
+    // Epoch of Halley's next perihelion after one full perihelion passage, integrated from
+    // aphelion with `parameters`. Steps shrink to 0.05 day inside 3 AU, and the time left is
+    // taken from the osculating orbit back at aphelion, where outgassing has stopped.
+    fn next_perihelion(parameters: [f64; 3]) -> f64 {
+        const SUN_MASS: f64 = 1.989e30;
+        const HALLEY_MASS: f64 = 2.2e14;
+        let mu = GRAVITATIONAL_CONST * (SUN_MASS + HALLEY_MASS);
+        let elements = KeplerianElements::from_mean_anomaly(17.834 * ASTRONOMICAL_UNIT, 0.967_14, 2.832, 1.0, 1.9, PI);
+        let (position, velocity) = elements.to_state(mu);
+
+        let mut system = SolarSystem::new(SECONDS_PER_DAY, IntegratorType::RK4(1));
+        system.add_body(CelestialBody::new(
+            "Sun".to_string(), BodyType::Star, Vector3::new(0.0, 0.0, 0.0), 695_700.0, SUN_MASS,
+            Vector3::new(0.0, 0.0, 0.0), [1.0, 1.0, 0.0],
+        ));
+        system.add_body(CelestialBody::new(
+            "Halley".to_string(), BodyType::Comet, position, 5.5, HALLEY_MASS, velocity, [0.8, 0.8, 1.0],
+        ).with_nongravitational(parameters[0], parameters[1], parameters[2]));
+
+        let period = elements.period(mu).unwrap();
+        while system.time < period {
+            let bodies = system.get_bodies();
+            let distance = bodies[1].position.subtract(&bodies[0].position).magnitude() / ASTRONOMICAL_UNIT;
+            system.timestep = if distance < 3.0 { 0.05 * SECONDS_PER_DAY } else { 2.0 * SECONDS_PER_DAY };
+            system.update();
+        }
+
+        let bodies = system.get_bodies();
+        let osculating = KeplerianElements::from_state(
+            &bodies[1].position.subtract(&bodies[0].position),
+            &bodies[1].velocity.subtract(&bodies[0].velocity),
+            mu,
+        );
+        system.time + (2.0 * PI - osculating.mean_anomaly()) / osculating.mean_motion(mu)
+    }
+
+    #[test]
+    fn outgassing_delays_halley_perihelion() {
+        // The JPL solution's transverse term lengthens each revolution by about 4 days
+        let delay = (next_perihelion(HALLEY) - next_perihelion([0.0; 3])) / SECONDS_PER_DAY;
+        assert!((delay - 4.0).abs() < 0.5, "perihelion delayed by {} days", delay);
+    }
+}
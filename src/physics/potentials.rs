@@ -0,0 +1,89 @@
+use std::f64::consts::PI;
+use crate::geometry::{Matrix3, Vector3};
+use super::GRAVITATIONAL_CONST;
+
+// Solar-neighbourhood values for the galactic tide
+pub const LOCAL_DISK_DENSITY: f64 = 6.77e-21;      // kg/m^3 (0.1 solar masses per cubic parsec)
+pub const OORT_A: f64 = 4.80e-16;                  // 1/s (14.8 km/s/kpc)
+pub const OORT_B: f64 = -4.01e-16;                 // 1/s (-12.4 km/s/kpc)
+
+// Rotation from the ecliptic J2000 simulation frame to galactic coordinates
+const ECLIPTIC_TO_GALACTIC: Matrix3 = Matrix3 { rows: [
+    [-0.054_875_560_4, -0.993_821_379_0, -0.096_476_626_3],
+    [ 0.494_109_427_9, -0.110_990_733_6,  0.862_285_875_1],
+    [-0.867_666_149_0, -0.000_351_590_0,  0.497_147_191_7],
+] };
+
+#[derive(Clone, Debug)]
+pub enum PotentialCentre {
+    Point(Vector3),
+    Body(usize),                // id of the body carrying the potential
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PotentialKind {
+    // Heisler & Tremaine (1986) tide of the galactic disk, including the radial Oort terms
+    GalacticTide { density: f64, oort_a: f64, oort_b: f64 },
+    Plummer { mass: f64, scale_radius: f64 },
+    Nfw { density: f64, scale_radius: f64 },
+    // Disk in the plane normal to the simulation z axis
+    MiyamotoNagai { mass: f64, disk_scale: f64, height_scale: f64 },
+}
+
+#[derive(Clone, Debug)]
+pub struct ExternalPotential {
+    pub kind: PotentialKind,
+    pub centre: PotentialCentre,
+}
+
+impl ExternalPotential {
+    pub fn new(kind: PotentialKind, centre: PotentialCentre) -> Self {
+        ExternalPotential { kind, centre }
+    }
+
+    pub fn galactic_tide(centre: PotentialCentre) -> Self {
+        ExternalPotential::new(
+            PotentialKind::GalacticTide { density: LOCAL_DISK_DENSITY, oort_a: OORT_A, oort_b: OORT_B },
+            centre,
+        )
+    }
+
+    // Acceleration at `offset` from the potential's centre
+    pub fn acceleration(&self, offset: &Vector3) -> Vector3 {
+        match self.kind {
+            PotentialKind::GalacticTide { density, oort_a, oort_b } => {
+                let galactic = ECLIPTIC_TO_GALACTIC.multiply_vector(offset);
+                let tide = Vector3::new(
+                    (oort_a - oort_b) * (3.0 * oort_a + oort_b) * galactic.x,
+                    -(oort_a - oort_b).powi(2) * galactic.y,
+                    -(4.0 * PI * GRAVITATIONAL_CONST * density - 2.0 * (oort_b * oort_b - oort_a * oort_a)) * galactic.z,
+                );
+                ECLIPTIC_TO_GALACTIC.transpose().multiply_vector(&tide)
+            },
+            PotentialKind::Plummer { mass, scale_radius } => {
+                let softened = offset.dot(offset) + scale_radius * scale_radius;
+                offset.scale(-GRAVITATIONAL_CONST * mass / (softened * softened.sqrt()))
+            },
+            PotentialKind::Nfw { density, scale_radius } => {
+                let distance = offset.magnitude();
+                if distance == 0.0 {
+                    return Vector3::new(0.0, 0.0, 0.0);
+                }
+                let x = distance / scale_radius;
+                let enclosed_mass = 4.0 * PI * density * scale_radius.powi(3) * ((1.0 + x).ln() - x / (1.0 + x));
+                offset.scale(-GRAVITATIONAL_CONST * enclosed_mass / distance.powi(3))
+            },
+            PotentialKind::MiyamotoNagai { mass, disk_scale, height_scale } => {
+                let vertical = (offset.z * offset.z + height_scale * height_scale).sqrt();
+                let thickness = disk_scale + vertical;
+                let denominator = (offset.x * offset.x + offset.y * offset.y + thickness * thickness).powf(1.5);
+                let factor = -GRAVITATIONAL_CONST * mass / denominator;
+                Vector3::new(
+                    factor * offset.x,
+                    factor * offset.y,
+                    factor * offset.z * thickness / vertical,
+                )
+            },
+        }
+    }
+}
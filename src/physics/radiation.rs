@@ -0,0 +1,79 @@
+use crate::geometry::Vector3;
+use super::GRAVITATIONAL_CONST;
+
+pub const ASTRONOMICAL_UNIT: f64 = 1.495_978_707e11;   // m
+pub const SOLAR_PRESSURE_AU: f64 = 4.56e-6;             // N/m^2 at 1 AU
+pub const SPEED_OF_LIGHT: f64 = 299_792_458.0;          // m/s
+
+// Cannonball solar radiation pressure. `from_star` points from the star to the body,
+// `illumination` is the shadow factor in [0, 1].
+pub fn calculate_radiation_pressure(from_star: &Vector3, area_to_mass: f64, reflectivity: f64, illumination: f64) -> Vector3 {
+    let distance = from_star.magnitude();
+    let pressure = SOLAR_PRESSURE_AU * (ASTRONOMICAL_UNIT / distance).powi(2);
+
+    from_star.scale(illumination * pressure * reflectivity * area_to_mass / distance)
+}
+
+// Radiation pressure plus Poynting-Robertson drag on a dust grain (Burns, Lamy & Soter 1979).
+// `beta` is the ratio of radiation to gravitational force, `position` and `velocity`
+// are relative to the star.
+pub fn calculate_poynting_robertson(position: &Vector3, velocity: &Vector3, star_mass: f64, beta: f64, illumination: f64) -> Vector3 {
+    let distance = position.magnitude();
+    let radial = position.scale(1.0 / distance);
+    let radial_speed = radial.dot(velocity);
+    let magnitude = illumination * beta * GRAVITATIONAL_CONST * star_mass / (distance * distance);
+
+    radial.scale(1.0 - radial_speed / SPEED_OF_LIGHT)
+        .subtract(&velocity.scale(1.0 / SPEED_OF_LIGHT))
+        .scale(magnitude)
+}
+
+// Beta of a spherical grain around the Sun from its radius (m), bulk density (kg/m^3)
+// and radiation pressure efficiency (1 for a perfect absorber)
+pub fn calculate_dust_beta(grain_radius: f64, density: f64, pressure_efficiency: f64) -> f64 {
+    5.7e-4 * pressure_efficiency / (density * grain_radius)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn radiation_pressure_at_one_au() {
+        // A/m = 0.02 m^2/kg and Cr = 1.3 give 4.56e-6 * 1.3 * 0.02 = 1.1856e-7 m/s^2,
+        // pointing away from the star
+        let from_star = Vector3::new(0.6, 0.8, 0.0).scale(ASTRONOMICAL_UNIT);
+        let acceleration = calculate_radiation_pressure(&from_star, 0.02, 1.3, 1.0);
+        assert!((acceleration.magnitude() - 1.1856e-7).abs() < 1e-15);
+        assert!((acceleration.norm().dot(&from_star.norm()) - 1.0).abs() < 1e-12);
+
+        // Inverse-square at 2 AU, and scaled by the shadow factor
+        let half_lit = calculate_radiation_pressure(&from_star.scale(2.0), 0.02, 1.3, 0.5);
+        assert!((half_lit.magnitude() - 1.1856e-7 / 8.0).abs() < 1e-15);
+    }
+
+    #[test]
+    fn poynting_robertson_drag_opposes_the_velocity() {
+        // Circular orbit at 1 AU around the Sun: the radial part is beta times gravity and
+        // the drag is v/c of it, pointing against the motion
+        let position = Vector3::new(ASTRONOMICAL_UNIT, 0.0, 0.0);
+        let velocity = Vector3::new(0.0, 29_780.0, 0.0);
+        let acceleration = calculate_poynting_robertson(&position, &velocity, 1.989e30, 0.1, 1.0);
+        let radial = 0.1 * GRAVITATIONAL_CONST * 1.989e30 / (ASTRONOMICAL_UNIT * ASTRONOMICAL_UNIT);
+
+        assert!((acceleration.x - radial).abs() < 1e-12 * radial);
+        assert!(acceleration.y < 0.0);
+        assert!((acceleration.y + radial * 29_780.0 / SPEED_OF_LIGHT).abs() < 1e-12 * radial);
+        assert!(acceleration.z.abs() < 1e-30);
+
+        // Drag takes energy out of the orbit whatever the direction of motion, and
+        // vanishes in the star's shadow
+        for velocity in [Vector3::new(5000.0, -20_000.0, 3000.0), Vector3::new(-5000.0, 20_000.0, -3000.0)] {
+            let drag = calculate_poynting_robertson(&position, &velocity, 1.989e30, 0.1, 1.0)
+                .subtract(&position.norm().scale(radial));
+            assert!(drag.dot(&velocity) < 0.0);
+        }
+        let shadowed = calculate_poynting_robertson(&position, &velocity, 1.989e30, 0.1, 0.0);
+        assert_eq!(shadowed.magnitude(), 0.0);
+    }
+}
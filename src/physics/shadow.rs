@@ -0,0 +1,256 @@
+use std::f64::consts::PI;
+use crate::body::{BodyType, CelestialBody};
+use crate::geometry::Vector3;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ShadowModel {
+    None,
+    Cylindrical,
+    Conical,
+}
+
+// Bodies that cast shadows in every shadow model. Only planets and moons do, so that
+// spacecraft, asteroids and comets neither shade each other nor slow large particle
+// clouds down; their shadows are negligible next to the star's disc.
+pub fn casts_shadow(body: &CelestialBody) -> bool {
+    matches!(body.body_type, BodyType::Planet | BodyType::Moon) && body.km_radius > 0.0
+}
+
+// Indices of the bodies that cast shadows
+pub fn shadow_casters(bodies: &[CelestialBody]) -> Vec<usize> {
+    (0..bodies.len()).filter(|&i| casts_shadow(&bodies[i])).collect()
+}
+
+// Fraction of the star's light reaching `position` past a single spherical occulter
+// (1.0 = fully lit, 0.0 = umbra). Radii are in meters.
+pub fn illumination_fraction(
+    model: ShadowModel,
+    position: &Vector3,
+    star_position: &Vector3,
+    star_radius: f64,
+    occulter_position: &Vector3,
+    occulter_radius: f64,
+) -> f64 {
+    let to_star = star_position.subtract(position);
+    let to_occulter = occulter_position.subtract(position);
+    let star_distance = to_star.magnitude();
+    let occulter_distance = to_occulter.magnitude();
+
+    // Occulter behind the star, or we are inside the occulter
+    if occulter_distance >= star_distance || occulter_distance <= occulter_radius {
+        return 1.0;
+    }
+
+    match model {
+        ShadowModel::None => 1.0,
+        ShadowModel::Cylindrical => {
+            let sun_direction = to_star.norm();
+            let along = to_occulter.dot(&sun_direction);
+            let perpendicular = to_occulter.subtract(&sun_direction.scale(along));
+            if along > 0.0 && perpendicular.magnitude() < occulter_radius { 0.0 } else { 1.0 }
+        },
+        ShadowModel::Conical => {
+            let (a, b, c) = apparent_discs(&to_star, star_radius, &to_occulter, occulter_radius);
+            1.0 - hidden_fraction(a, b, c)
+        },
+    }
+}
+
+// Angular radii of the star and occulter discs and the separation of their centres
+fn apparent_discs(to_star: &Vector3, star_radius: f64, to_occulter: &Vector3, occulter_radius: f64) -> (f64, f64, f64) {
+    let star_distance = to_star.magnitude();
+    let occulter_distance = to_occulter.magnitude();
+    (
+        (star_radius / star_distance).min(1.0).asin(),
+        (occulter_radius / occulter_distance).min(1.0).asin(),
+        (to_star.dot(to_occulter) / (star_distance * occulter_distance)).clamp(-1.0, 1.0).acos(),
+    )
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EclipseKind {
+    Sunlit,
+    Penumbra,   // star disc partly covered
+    Umbra,      // star disc entirely covered
+    Annular,    // occulter disc inside the star disc
+}
+
+impl EclipseKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EclipseKind::Sunlit => "sunlit",
+            EclipseKind::Penumbra => "penumbra",
+            EclipseKind::Umbra => "umbra",
+            EclipseKind::Annular => "annular",
+        }
+    }
+}
+
+// Fraction of the star's disc hidden by one spherical occulter as seen from `position`,
+// and the kind of eclipse. Radii are in meters.
+pub fn occultation(position: &Vector3, star_position: &Vector3, star_radius: f64, occulter_position: &Vector3, occulter_radius: f64) -> (f64, EclipseKind) {
+    let to_star = star_position.subtract(position);
+    let to_occulter = occulter_position.subtract(position);
+    if to_occulter.magnitude() >= to_star.magnitude() || to_occulter.magnitude() <= occulter_radius {
+        return (0.0, EclipseKind::Sunlit);
+    }
+
+    let (a, b, c) = apparent_discs(&to_star, star_radius, &to_occulter, occulter_radius);
+    let kind = if c >= a + b {
+        EclipseKind::Sunlit
+    } else if c <= b - a {
+        EclipseKind::Umbra
+    } else if c <= a - b {
+        EclipseKind::Annular
+    } else {
+        EclipseKind::Penumbra
+    };
+    (hidden_fraction(a, b, c).clamp(0.0, 1.0), kind)
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Eclipse {
+    pub hidden_fraction: f64,       // of the star's disc, by all occulters together
+    pub kind: EclipseKind,          // cast by the occulter hiding the most
+    pub occulter: Option<usize>,    // index of that occulter
+}
+
+// Eclipse of the first star seen from `position` by the bodies at the `occulters`
+// indices (usually `shadow_casters`), apart from those at the `excluded` ones. Occulters
+// are assumed not to overlap one another, so their shadows combine like the force model's.
+pub fn eclipse(bodies: &[CelestialBody], occulters: &[usize], position: &Vector3, excluded: &[usize]) -> Eclipse {
+    let mut eclipse = Eclipse { hidden_fraction: 0.0, kind: EclipseKind::Sunlit, occulter: None };
+    let Some(star) = bodies.iter().position(|body| body.body_type == BodyType::Star) else {
+        return eclipse;
+    };
+
+    let mut illumination = 1.0;
+    let mut largest = 0.0;
+    for &i in occulters {
+        let body = &bodies[i];
+        if i == star || excluded.contains(&i) {
+            continue;
+        }
+        let (hidden, kind) = occultation(position, &bodies[star].position, bodies[star].km_radius * 1000.0, &body.position, body.km_radius * 1000.0);
+        if kind != EclipseKind::Sunlit && hidden >= largest {
+            largest = hidden;
+            eclipse.kind = kind;
+            eclipse.occulter = Some(i);
+        }
+        illumination *= 1.0 - hidden;
+    }
+    eclipse.hidden_fraction = 1.0 - illumination;
+    eclipse
+}
+
+// Stretch of a propagation spent in one kind of eclipse by one occulter
+#[derive(Clone, Debug)]
+pub struct EclipseInterval {
+    pub kind: EclipseKind,
+    pub occulter: String,
+    pub start: f64,
+    pub end: f64,
+    pub max_hidden_fraction: f64,   // highest sampled
+    pub max_time: f64,
+}
+
+impl EclipseInterval {
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+// Fraction of the star's disc covered by the occulter's. A star of no size is a point
+// that is either hidden or not.
+fn hidden_fraction(a: f64, b: f64, c: f64) -> f64 {
+    if a <= 0.0 {
+        if c < b { 1.0 } else { 0.0 }
+    } else {
+        overlap_area(a, b, c) / (PI * a * a)
+    }
+}
+
+// Overlap area of two discs with angular radii `a` (star) and `b` (occulter) whose
+// centres are separated by `c` (Montenbruck & Gill, 3.4.2)
+fn overlap_area(a: f64, b: f64, c: f64) -> f64 {
+    if c >= a + b {
+        0.0
+    } else if c <= b - a {
+        PI * a * a
+    } else if c <= a - b {
+        PI * b * b
+    } else {
+        let x = (c * c + a * a - b * b) / (2.0 * c);
+        let y = (a * a - x * x).max(0.0).sqrt();
+        a * a * (x / a).clamp(-1.0, 1.0).acos() + b * b * ((c - x) / b).clamp(-1.0, 1.0).acos() - c * y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUN_RADIUS: f64 = 6.957e8;
+    const EARTH_RADIUS: f64 = 6.378e6;
+
+    // Sun at 1 AU along +x from an Earth at the origin
+    fn sun() -> Vector3 {
+        Vector3::new(1.496e11, 0.0, 0.0)
+    }
+
+    fn fractions(position: &Vector3, star_radius: f64, occulter_radius: f64) -> (f64, f64, (f64, EclipseKind)) {
+        let origin = Vector3::new(0.0, 0.0, 0.0);
+        (
+            illumination_fraction(ShadowModel::Cylindrical, position, &sun(), star_radius, &origin, occulter_radius),
+            illumination_fraction(ShadowModel::Conical, position, &sun(), star_radius, &origin, occulter_radius),
+            occultation(position, &sun(), star_radius, &origin, occulter_radius),
+        )
+    }
+
+    #[test]
+    fn nothing_hidden_on_the_day_side_or_beside_the_shadow() {
+        for position in [Vector3::new(7.0e6, 0.0, 0.0), Vector3::new(0.0, 7.0e6, 0.0), Vector3::new(-7.0e6, 0.0, 6.6e6)] {
+            let (cylindrical, conical, (hidden, kind)) = fractions(&position, SUN_RADIUS, EARTH_RADIUS);
+            assert_eq!((cylindrical, conical, hidden, kind), (1.0, 1.0, 0.0, EclipseKind::Sunlit));
+        }
+    }
+
+    #[test]
+    fn nothing_lit_in_the_umbra() {
+        // The umbra reaches about 1.4 million km behind the Earth
+        for position in [Vector3::new(-7.0e6, 0.0, 0.0), Vector3::new(-4.2e7, 1.0e6, 0.0)] {
+            let (cylindrical, conical, (hidden, kind)) = fractions(&position, SUN_RADIUS, EARTH_RADIUS);
+            assert_eq!((cylindrical, conical, hidden, kind), (0.0, 0.0, 1.0, EclipseKind::Umbra));
+        }
+    }
+
+    #[test]
+    fn annular_eclipse_hides_the_ratio_of_the_disc_areas() {
+        // Far beyond the tip of the umbra the Earth's disc sits inside the Sun's, and
+        // hides the square of the ratio of their apparent radii
+        let position = Vector3::new(-3.0e9, 0.0, 0.0);
+        let (cylindrical, conical, (hidden, kind)) = fractions(&position, SUN_RADIUS, EARTH_RADIUS);
+        let star = (SUN_RADIUS / (1.496e11 + 3.0e9)).asin();
+        let occulter = (EARTH_RADIUS / 3.0e9).asin();
+        assert_eq!(kind, EclipseKind::Annular);
+        assert!((hidden - (occulter / star).powi(2)).abs() < 1e-12);
+        assert!((conical - (1.0 - hidden)).abs() < 1e-12);
+        assert_eq!(cylindrical, 0.0);
+
+        // Off centre but still inside, the same area is hidden, up to the slightly larger
+        // distance to the Earth
+        let (_, _, (off_centre, kind)) = fractions(&Vector3::new(-3.0e9, 0.0, 5.0e6), SUN_RADIUS, EARTH_RADIUS);
+        assert_eq!(kind, EclipseKind::Annular);
+        assert!((off_centre - hidden).abs() < 1e-5 * hidden);
+    }
+
+    #[test]
+    fn point_star_is_hidden_or_not() {
+        for (position, expected) in [(Vector3::new(-7.0e6, 0.0, 0.0), 0.0), (Vector3::new(-7.0e6, 0.0, 7.0e6), 1.0), (Vector3::new(7.0e6, 0.0, 0.0), 1.0)] {
+            let (_, conical, (hidden, kind)) = fractions(&position, 0.0, EARTH_RADIUS);
+            assert_eq!(conical, expected);
+            assert_eq!(hidden, 1.0 - expected);
+            assert_eq!(kind, if expected == 0.0 { EclipseKind::Umbra } else { EclipseKind::Sunlit });
+        }
+    }
+}
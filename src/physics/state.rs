@@ -1,15 +1,41 @@
-use crate::geometry::Vector3;
+use crate::geometry::{Matrix, Vector3};
 
 #[derive(Clone)]
 pub struct State {
     pub positions: Vec<Vector3>,
     pub velocities: Vec<Vector3>,
-    pub masses: Vec<f64>
+    pub masses: Vec<f64>,
+    pub time: f64,
+    pub variations: Vec<Variation>,
+}
+
+// State transition matrix of body `index`, the 6x6 partials of its position and
+// velocity with respect to their values when tracking started
+#[derive(Clone, Debug)]
+pub struct Variation {
+    pub index: usize,
+    pub matrix: Matrix,
+}
+
+// Time derivatives of every integrated quantity in a `State`
+#[derive(Clone)]
+pub struct Derivatives {
+    pub velocities: Vec<Vector3>,
+    pub accelerations: Vec<Vector3>,
+    pub mass_rates: Vec<f64>,
+    pub variation_rates: Vec<Matrix>,   // one per variation of the state
 }
 
 impl State {
-    pub fn new(positions: Vec<Vector3>, velocities: Vec<Vector3>, masses: Vec<f64>) -> Self {
-        State {positions, velocities, masses}
+    pub fn new(positions: Vec<Vector3>, velocities: Vec<Vector3>, masses: Vec<f64>, time: f64) -> Self {
+        State {positions, velocities, masses, time, variations: Vec::new()}
+    }
+
+    // Integrates the variational equations of the bodies at `indices`, starting from
+    // identity matrices
+    pub fn with_variations(mut self, indices: &[usize]) -> Self {
+        self.variations = indices.iter().map(|&index| Variation { index, matrix: Matrix::identity(6) }).collect();
+        self
     }
 
     pub fn add(&self, other: State) -> Self {
@@ -23,7 +49,17 @@ impl State {
             .map(|(a,b)| a.add(b))
             .collect();
 
-        State::new(summed_positions, summed_velocities, self.masses.clone())
+        let summed_masses = self.masses.iter()
+            .zip(other.masses.iter())
+            .map(|(a,b)| a + b)
+            .collect();
+
+        let summed_variations = self.variations.iter()
+            .zip(other.variations.iter())
+            .map(|(a, b)| Variation { index: a.index, matrix: a.matrix.add(&b.matrix) })
+            .collect();
+
+        State { variations: summed_variations, ..State::new(summed_positions, summed_velocities, summed_masses, self.time) }
     }
 
     pub fn scale(&self, scalar: f64) -> Self {
@@ -35,20 +71,38 @@ impl State {
             .map(|a| a.scale(scalar))
             .collect();
 
-        State::new(scaled_positions, scaled_velocities, self.masses.clone())
+        let scaled_masses = self.masses.iter()
+            .map(|a| a * scalar)
+            .collect();
+
+        let scaled_variations = self.variations.iter()
+            .map(|a| Variation { index: a.index, matrix: a.matrix.scale(scalar) })
+            .collect();
+
+        State { variations: scaled_variations, ..State::new(scaled_positions, scaled_velocities, scaled_masses, self.time) }
     }
 
-    pub fn advance_by_derivatives(&self, derivatives: (Vec<Vector3>, Vec<Vector3>), timestep: f64) -> State {
+    pub fn advance_by_derivatives(&self, derivatives: &Derivatives, timestep: f64) -> State {
         let new_pos = self.positions.iter()
-            .zip(derivatives.0.iter())
+            .zip(derivatives.velocities.iter())
             .map(|(a, b)| a.add(&b.scale(timestep)))
             .collect();
-        
+
         let new_vel = self.velocities.iter()
-            .zip(derivatives.1.iter())
+            .zip(derivatives.accelerations.iter())
             .map(|(a, b)| a.add(&b.scale(timestep)))
             .collect();
 
-        State::new(new_pos, new_vel, self.masses.clone())
+        let new_mass = self.masses.iter()
+            .zip(derivatives.mass_rates.iter())
+            .map(|(a, b)| a + b * timestep)
+            .collect();
+
+        let new_variations = self.variations.iter()
+            .zip(derivatives.variation_rates.iter())
+            .map(|(a, b)| Variation { index: a.index, matrix: a.matrix.add(&b.scale(timestep)) })
+            .collect();
+
+        State { variations: new_variations, ..State::new(new_pos, new_vel, new_mass, self.time + timestep) }
     }
-}
\ No newline at end of file
+}
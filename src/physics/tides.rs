@@ -0,0 +1,34 @@
+use crate::geometry::Vector3;
+use super::GRAVITATIONAL_CONST;
+
+// Constant time lag for a body with quality factor `q` forced at `tidal_frequency` (rad/s),
+// from 1/Q = tidal_frequency * time_lag
+pub fn calculate_time_lag(q: f64, tidal_frequency: f64) -> f64 {
+    if q <= 0.0 || tidal_frequency <= 0.0 {
+        return 0.0;
+    }
+    1.0 / (q * tidal_frequency)
+}
+
+// Force on a perturber of mass `perturber_mass` from the tidal bulge it raises on a
+// body of radius `radius` spinning at `spin` rad/s (Mignard 1979). `position` and
+// `velocity` are those of the perturber relative to the deformed body. The conservative
+// part is radial, the lagged part drags the perturber along or against its motion.
+pub fn calculate_tidal_force(
+    perturber_mass: f64,
+    radius: f64,
+    love_number: f64,
+    time_lag: f64,
+    position: &Vector3,
+    velocity: &Vector3,
+    spin: &Vector3,
+) -> Vector3 {
+    let distance_squared = position.dot(position);
+    let coefficient = -3.0 * love_number * GRAVITATIONAL_CONST * perturber_mass * perturber_mass * radius.powi(5)
+        / distance_squared.powi(4);
+
+    let lag = position.scale(2.0 * position.dot(velocity) / distance_squared)
+        .add(&position.cross(spin))
+        .add(velocity);
+    position.add(&lag.scale(time_lag)).scale(coefficient)
+}
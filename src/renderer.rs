@@ -2,22 +2,32 @@ use kiss3d::window::Window;
 use kiss3d::light::Light;
 use kiss3d::scene::SceneNode;
 use kiss3d::camera::ArcBall;
-use kiss3d::nalgebra::{Point3, Translation3, UnitQuaternion, Vector3};
+use kiss3d::nalgebra::{Matrix3, Point3, Quaternion, Rotation3, Translation3, UnitQuaternion, Vector3};
 use kiss3d::event::{Key, Action};
 use kiss3d::resource::MaterialManager;
 use std::time::SystemTime;
 use rand::Rng;
-use crate::{solar_system, body::{self, BodyType}};
+use crate::{geodesy, geometry, physics, solar_system, body::{self, BodyType}, rotation::RotationModel};
 
 const DISPLAY_SCALE: f32 = 1e-9;
 const NUM_STARS: usize = 1000;
+const SATELLITE_SIZE: f32 = 0.2;
+const SATELLITE_ORBIT_SCALE: f64 = 1000.0;     // exaggeration of element set orbits about Earth
+const GROUND_TRACK_LENGTH: usize = 600;
+const GROUND_TRACK_HEIGHT: f32 = 1.06;          // display radius of ground tracks over Earth's sphere
+const ECLIPSE_DARKENING: f32 = 0.85;            // share of the colour lost with the star fully hidden
+
+type TrailSegment = (Point3<f32>, Point3<f32>, [f32; 3], f32);
 
 pub struct BodyVisuals {
+    id: usize,
+    display_size: f32,
     main_body: SceneNode,
     effects: Vec<SceneNode>,
     atmosphere: Option<SceneNode>,
     rotation_speed: f32,
     trail: Vec<Point3<f32>>,
+    ground_track: Vec<geodesy::Geodetic>,
 }
 
 pub struct Starfield {
@@ -33,6 +43,12 @@ pub struct Renderer {
     trail_interpolation_points: usize,
 }
 
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Renderer {
     pub fn new() -> Self {
         let mut rng = rand::thread_rng();
@@ -81,11 +97,14 @@ impl Renderer {
         }
 
         BodyVisuals {
+            id: body.id,
+            display_size: body.calculate_display_size(),
             main_body,
             effects,
             atmosphere: None,
             rotation_speed: 0.001,
             trail: Vec::new(),
+            ground_track: Vec::new(),
         }
     }
 
@@ -112,11 +131,51 @@ impl Renderer {
         let rotation_speed = 0.005 + rng.gen::<f32>() * 0.002;
 
         BodyVisuals {
+            id: body.id,
+            display_size: body.calculate_display_size(),
             main_body,
             effects: Vec::new(),
             atmosphere,
             rotation_speed,
             trail: Vec::new(),
+            ground_track: Vec::new(),
+        }
+    }
+
+    // Box-shaped bus with two solar panels along the body y axis, so that the
+    // attitude is visible
+    fn create_satellite_visuals(&mut self, body: &body::CelestialBody) -> BodyVisuals {
+        let mut main_body = self.window.add_group();
+        let mut bus = main_body.add_cube(SATELLITE_SIZE, SATELLITE_SIZE, SATELLITE_SIZE);
+        bus.set_color(body.color[0], body.color[1], body.color[2]);
+        for side in [-1.0, 1.0] {
+            let mut panel = main_body.add_cube(SATELLITE_SIZE * 0.8, SATELLITE_SIZE * 2.0, SATELLITE_SIZE * 0.05);
+            panel.set_color(0.1, 0.2, 0.6);
+            panel.set_local_translation(Translation3::new(0.0, side * SATELLITE_SIZE * 1.6, 0.0));
+        }
+
+        BodyVisuals {
+            id: body.id,
+            display_size: body.calculate_display_size(),
+            main_body,
+            effects: Vec::new(),
+            atmosphere: None,
+            rotation_speed: 0.0,
+            trail: Vec::new(),
+            ground_track: Vec::new(),
+        }
+    }
+
+    fn create_dust_visuals(&mut self, body: &body::CelestialBody) -> BodyVisuals {
+        BodyVisuals {
+            id: body.id,
+            display_size: 0.0,
+            main_body: self.window.add_group(),
+            effects: Vec::new(),
+            atmosphere: None,
+            rotation_speed: 0.0,
+            trail: Vec::new(),
+            ground_track: Vec::new(),
         }
     }
 
@@ -142,7 +201,11 @@ impl Renderer {
             BodyType::Star => self.create_star_visuals(body),
             BodyType::Planet => self.create_planet_visuals(body),
             BodyType::Moon => self.create_planet_visuals(body),
-            BodyType::Satellite => self.create_planet_visuals(body),
+            BodyType::Satellite if body.sgp4.is_some() => self.create_dust_visuals(body),
+            BodyType::Satellite => self.create_satellite_visuals(body),
+            BodyType::Asteroid => self.create_planet_visuals(body),
+            BodyType::Comet => self.create_planet_visuals(body),
+            BodyType::Dust => self.create_dust_visuals(body),
         };
 
         let scaled_pos = body.position.scale(DISPLAY_SCALE.into());
@@ -160,7 +223,39 @@ impl Renderer {
         self.bodies.push(visuals);
     }
 
-    fn handle_camera_input(&mut self, camera: &mut ArcBall, bodies: &Vec<body::CelestialBody>) {
+    // Keeps the visuals in step with the simulation when bodies merge, fragment or are added
+    fn sync_bodies(&mut self, bodies: &[body::CelestialBody]) {
+        let in_sync = self.bodies.len() == bodies.len()
+            && self.bodies.iter().zip(bodies.iter()).all(|(visuals, body)| visuals.id == body.id);
+        if in_sync {
+            return;
+        }
+
+        let mut index = 0;
+        while index < self.bodies.len() {
+            if bodies.iter().any(|body| body.id == self.bodies[index].id) {
+                index += 1;
+            } else {
+                let mut visuals = self.bodies.remove(index);
+                visuals.main_body.unlink();
+                for effect in &mut visuals.effects {
+                    effect.unlink();
+                }
+                if let Some(ref mut atmosphere) = visuals.atmosphere {
+                    atmosphere.unlink();
+                }
+            }
+        }
+
+        for body in bodies {
+            if !self.bodies.iter().any(|visuals| visuals.id == body.id) {
+                self.add_body(body);
+            }
+        }
+        self.bodies.sort_by_key(|visuals| bodies.iter().position(|body| body.id == visuals.id));
+    }
+
+    fn handle_camera_input(&mut self, camera: &mut ArcBall, bodies: &[body::CelestialBody]) {
         let movement_speed = 10.0;
         let zoom_speed = 1.05;
     
@@ -226,31 +321,123 @@ impl Renderer {
         }
     }
 
-    pub fn update_positions(&mut self, bodies: &Vec<body::CelestialBody>) {
+    // Orientation of a body's sphere from its IAU rotation model. The sphere mesh has
+    // its pole along y, so it is first turned onto the body-fixed z axis.
+    fn body_orientation(rotation: &RotationModel, simulation_time: f64) -> UnitQuaternion<f32> {
+        let body_to_inertial = rotation.inertial_to_body_fixed(simulation_time).transpose();
+        let r = body_to_inertial.rows.map(|row| row.map(|value| value as f32));
+        let matrix = Matrix3::new(
+            r[0][0], r[0][1], r[0][2],
+            r[1][0], r[1][1], r[1][2],
+            r[2][0], r[2][1], r[2][2],
+        );
+        let pole_up = UnitQuaternion::from_axis_angle(&Vector3::x_axis(), std::f32::consts::FRAC_PI_2);
+        UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(matrix)) * pole_up
+    }
+
+    pub fn update_positions(&mut self, bodies: &[body::CelestialBody], simulation_time: f64) {
         let time = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap()
             .as_secs_f32();
 
+        self.sync_bodies(bodies);
+
         // Render starfield first
         self.render_starfield();
 
-        let mut trails_to_draw: Vec<(Point3<f32>, Point3<f32>, [f32; 3], f32)> = Vec::new();
+        let mut trails_to_draw: Vec<TrailSegment> = Vec::new();
+        let mut dust_to_draw: Vec<(Point3<f32>, [f32; 3])> = Vec::new();
+        let earth = bodies.iter().find(|body| body.name == "Earth");
+
+        // Light reaching each body drawn shaded, worked out once for the frame
+        let occulters = physics::shadow_casters(bodies);
+        let light: Vec<f32> = bodies.iter()
+            .enumerate()
+            .map(|(index, body)| {
+                let shaded = body.sgp4.is_some() || matches!(body.body_type, BodyType::Planet | BodyType::Moon | BodyType::Asteroid | BodyType::Comet);
+                if shaded && !occulters.is_empty() {
+                    1.0 - ECLIPSE_DARKENING * physics::eclipse(bodies, &occulters, &body.position, &[index]).hidden_fraction as f32
+                } else {
+                    1.0
+                }
+            })
+            .collect();
+
+        for (index, (visuals, body)) in self.bodies.iter_mut().zip(bodies.iter()).enumerate() {
+            let shade = |color: [f32; 3]| color.map(|component| component * light[index]);
+
+            // Sub-satellite points are kept body-fixed and drawn on Earth's sphere as it turns
+            if let (BodyType::Satellite, Some(earth)) = (body.body_type, earth) {
+                if let Some(rotation) = &earth.rotation {
+                    let position = body.position.subtract(&earth.position);
+                    visuals.ground_track.push(geodesy::sub_satellite_point(&position, rotation, simulation_time));
+                    if visuals.ground_track.len() > GROUND_TRACK_LENGTH {
+                        visuals.ground_track.remove(0);
+                    }
+                    let centre = earth.position.scale(DISPLAY_SCALE.into());
+                    let radius = (earth.calculate_display_size().abs() * GROUND_TRACK_HEIGHT) as f64;
+                    let points: Vec<Point3<f32>> = visuals.ground_track.iter()
+                        .map(|ground| {
+                            let (sin_latitude, cos_latitude) = ground.latitude.sin_cos();
+                            let (sin_longitude, cos_longitude) = ground.longitude.sin_cos();
+                            let direction = geometry::Vector3::new(cos_latitude * cos_longitude, cos_latitude * sin_longitude, sin_latitude);
+                            let point = centre.add(&rotation.from_body_fixed(&direction, simulation_time).scale(radius));
+                            Point3::new(point.x as f32, point.y as f32, point.z as f32)
+                        })
+                        .collect();
+                    for (n, pair) in points.windows(2).enumerate() {
+                        let fade = ((n + 1) as f32 / points.len() as f32).powf(0.5);
+                        trails_to_draw.push((pair[0], pair[1], body.color, fade));
+                    }
+                }
+            }
+
+            // Satellites flown from element sets would sit inside Earth's sphere at the
+            // display scale, so their offset from Earth is blown up and they become points
+            if let (Some(_), Some(earth)) = (&body.sgp4, earth) {
+                let offset = body.position.subtract(&earth.position).scale(SATELLITE_ORBIT_SCALE);
+                let scaled_pos = earth.position.add(&offset).scale(DISPLAY_SCALE.into());
+                dust_to_draw.push((Point3::new(scaled_pos.x as f32, scaled_pos.y as f32, scaled_pos.z as f32), shade(body.color)));
+                continue;
+            }
 
-        for (visuals, body) in self.bodies.iter_mut().zip(bodies.iter()) {
             let scaled_pos = body.position.scale(DISPLAY_SCALE.into());
             let point = Point3::new(
                 scaled_pos.x as f32,
                 scaled_pos.y as f32,
                 scaled_pos.z as f32
             );
+
+            // Dust is drawn as single points, too numerous for meshes and trails
+            if body.body_type == BodyType::Dust {
+                dust_to_draw.push((point, body.color));
+                continue;
+            }
             
-            // Update rotation
-            let rotation = UnitQuaternion::from_axis_angle(
-                &Vector3::y_axis(),
-                visuals.rotation_speed * time
-            );
+            // Update rotation, from the attitude or physical rotation model when the body has one
+            let rotation = match (&body.attitude, &body.rotation) {
+                (Some(attitude), _) => {
+                    let q = &attitude.orientation;
+                    UnitQuaternion::from_quaternion(Quaternion::new(q.w as f32, q.x as f32, q.y as f32, q.z as f32))
+                },
+                (None, Some(model)) => Self::body_orientation(model, simulation_time),
+                (None, None) => UnitQuaternion::from_axis_angle(
+                    &Vector3::y_axis(),
+                    visuals.rotation_speed * time
+                ),
+            };
             visuals.main_body.set_local_rotation(rotation);
+            if matches!(body.body_type, BodyType::Planet | BodyType::Moon | BodyType::Asteroid | BodyType::Comet) {
+                let [red, green, blue] = shade(body.color);
+                visuals.main_body.set_color(red, green, blue);
+            }
+
+            // Bodies grow when they absorb others
+            let size_ratio = body.calculate_display_size() / visuals.display_size;
+            if size_ratio.is_finite() {
+                visuals.main_body.set_local_scale(size_ratio, size_ratio, size_ratio);
+            }
             
             match body.body_type {
                 BodyType::Star => {
@@ -274,7 +461,7 @@ impl Renderer {
                         atmosphere.set_local_translation(Translation3::from(point.coords));
                         atmosphere.set_local_rotation(rotation);
                         
-                        let atm_pulse = (time + body.position.magnitude() as f32).sin() * 0.02 + 1.0;
+                        let atm_pulse = ((time + body.position.magnitude() as f32).sin() * 0.02 + 1.0) * size_ratio;
                         atmosphere.set_local_scale(atm_pulse, atm_pulse, atm_pulse);
                     }
                     
@@ -326,6 +513,10 @@ impl Renderer {
             }
         }
 
+        for (point, color) in dust_to_draw {
+            self.window.draw_point(&point, &Point3::new(color[0], color[1], color[2]));
+        }
+
         // Draw trails with improved alpha blending
         for (start, end, color, fade) in trails_to_draw {
             self.window.draw_line(
@@ -355,14 +546,14 @@ impl Renderer {
             self.handle_camera_input(&mut camera, solar_system.get_bodies());
 
             if self.window.get_key(Key::Up) == Action::Press {
-                solar_system.timestep = solar_system.timestep * 1.1;
+                solar_system.timestep *= 1.1;
             }
             if self.window.get_key(Key::Down) == Action::Press {
-                solar_system.timestep = solar_system.timestep * 0.9;
+                solar_system.timestep *= 0.9;
             }
 
             solar_system.update();
-            self.update_positions(solar_system.get_bodies());
+            self.update_positions(solar_system.get_bodies(), solar_system.time);
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,109 @@
+use std::f64::consts::PI;
+use crate::geometry::{self, Matrix3, Vector3};
+use crate::time;
+
+// IAU WGCCRE rotation model (Archinal et al. 2018) without the periodic terms.
+// Angles in degrees, rates per Julian century (pole) and per day (prime meridian).
+#[derive(Clone, Debug)]
+pub struct RotationModel {
+    pub pole_ra: f64,
+    pub pole_ra_rate: f64,
+    pub pole_dec: f64,
+    pub pole_dec_rate: f64,
+    pub prime_meridian: f64,
+    pub rotation_rate: f64,     // negative for retrograde rotators
+}
+
+impl RotationModel {
+    pub fn new(pole_ra: f64, pole_ra_rate: f64, pole_dec: f64, pole_dec_rate: f64, prime_meridian: f64, rotation_rate: f64) -> Self {
+        RotationModel { pole_ra, pole_ra_rate, pole_dec, pole_dec_rate, prime_meridian, rotation_rate }
+    }
+
+    pub fn sun() -> Self { RotationModel::new(286.13, 0.0, 63.87, 0.0, 84.176, 14.184_4) }
+    pub fn mercury() -> Self { RotationModel::new(281.0103, -0.0328, 61.4155, -0.0049, 329.5988, 6.138_510_8) }
+    pub fn venus() -> Self { RotationModel::new(272.76, 0.0, 67.16, 0.0, 160.20, -1.481_368_8) }
+    pub fn earth() -> Self { RotationModel::new(0.0, -0.641, 90.0, -0.557, 190.147, 360.985_623_5) }
+    pub fn moon() -> Self { RotationModel::new(269.9949, 0.0031, 66.5392, 0.0130, 38.3213, 13.176_358_15) }
+    pub fn mars() -> Self { RotationModel::new(317.269_202, -0.109_275_47, 54.432_516, -0.058_271_05, 176.049_863, 350.891_982_443_297) }
+    pub fn jupiter() -> Self { RotationModel::new(268.056_595, -0.006_499, 64.495_303, 0.002_413, 284.95, 870.536) }
+    pub fn saturn() -> Self { RotationModel::new(40.589, -0.036, 83.537, -0.004, 38.90, 810.793_902_4) }
+    pub fn uranus() -> Self { RotationModel::new(257.311, 0.0, -15.175, 0.0, 203.81, -501.160_092_8) }
+    pub fn neptune() -> Self { RotationModel::new(299.36, 0.0, 43.46, 0.0, 249.978, 541.139_775_7) }
+
+    pub fn for_body(name: &str) -> Option<Self> {
+        match name {
+            "Sun" => Some(RotationModel::sun()),
+            "Mercury" => Some(RotationModel::mercury()),
+            "Venus" => Some(RotationModel::venus()),
+            "Earth" => Some(RotationModel::earth()),
+            "Moon" => Some(RotationModel::moon()),
+            "Mars" => Some(RotationModel::mars()),
+            "Jupiter" => Some(RotationModel::jupiter()),
+            "Saturn" => Some(RotationModel::saturn()),
+            "Uranus" => Some(RotationModel::uranus()),
+            "Neptune" => Some(RotationModel::neptune()),
+            _ => None,
+        }
+    }
+
+    // Right ascension and declination of the north pole in radians
+    pub fn pole_ra_dec(&self, time: f64) -> (f64, f64) {
+        let centuries = time::centuries_since_j2000(time);
+        (
+            (self.pole_ra + self.pole_ra_rate * centuries).to_radians(),
+            (self.pole_dec + self.pole_dec_rate * centuries).to_radians(),
+        )
+    }
+
+    // Prime meridian angle W in radians, wrapped to [0, 2pi)
+    pub fn prime_meridian_angle(&self, time: f64) -> f64 {
+        (self.prime_meridian + self.rotation_rate * time::days_since_j2000(time)).to_radians().rem_euclid(2.0 * PI)
+    }
+
+    // Adds `change` rad/s to the spin rate from `time` on, keeping the prime meridian
+    // angle continuous there
+    pub fn change_spin_rate(&mut self, time: f64, change: f64) {
+        let rate_change = change.to_degrees() * time::SECONDS_PER_DAY;
+        self.prime_meridian -= rate_change * time::days_since_j2000(time);
+        self.rotation_rate += rate_change;
+    }
+
+    // Sidereal rotation period in seconds
+    pub fn spin_period(&self) -> f64 {
+        360.0 / self.rotation_rate.abs() * time::SECONDS_PER_DAY
+    }
+
+    // North pole unit vector in the simulation (ecliptic) frame
+    pub fn pole(&self, time: f64) -> Vector3 {
+        let (ra, dec) = self.pole_ra_dec(time);
+        geometry::equatorial_to_ecliptic(&Vector3::new(dec.cos() * ra.cos(), dec.cos() * ra.sin(), dec.sin()))
+    }
+
+    // Angular velocity in rad/s, along the pole for prograde rotators
+    pub fn spin_vector(&self, time: f64) -> Vector3 {
+        self.pole(time).scale(self.rotation_rate.to_radians() / time::SECONDS_PER_DAY)
+    }
+
+    // Angle between the spin axis and the normal of the body's orbit, above 90 degrees
+    // for retrograde rotators such as Venus and Uranus
+    pub fn obliquity(&self, time: f64, orbit_normal: &Vector3) -> f64 {
+        self.spin_vector(time).norm().dot(&orbit_normal.norm()).clamp(-1.0, 1.0).acos()
+    }
+
+    // Rotation taking simulation-frame vectors into the body-fixed frame
+    pub fn inertial_to_body_fixed(&self, time: f64) -> Matrix3 {
+        let (ra, dec) = self.pole_ra_dec(time);
+        Matrix3::rotation_z(self.prime_meridian_angle(time))
+            .multiply(&Matrix3::rotation_x(PI / 2.0 - dec))
+            .multiply(&Matrix3::rotation_z(PI / 2.0 + ra))
+            .multiply(&Matrix3::rotation_x(-geometry::J2000_OBLIQUITY))
+    }
+
+    pub fn to_body_fixed(&self, vector: &Vector3, time: f64) -> Vector3 {
+        self.inertial_to_body_fixed(time).multiply_vector(vector)
+    }
+
+    pub fn from_body_fixed(&self, vector: &Vector3, time: f64) -> Vector3 {
+        self.inertial_to_body_fixed(time).transpose().multiply_vector(vector)
+    }
+}
@@ -1,12 +1,28 @@
+use std::io;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::attitude;
 use crate::body::{self, BodyType};
+use crate::collision::{self, CollisionEvent, CollisionPolicy};
+use crate::estimation;
+use crate::geodesy;
 use crate::geometry;
 use crate::integrators::{self, Integrator};
+use crate::mission;
+use crate::orbit;
 use crate::physics;
+use crate::rotation::RotationModel;
 
 pub struct SimulationParameters {
     pub time_multiplier: f64,
 }
 
+impl Default for SimulationParameters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SimulationParameters {
     pub fn new() -> Self {
         SimulationParameters {
@@ -15,10 +31,19 @@ impl SimulationParameters {
     }
 }
 
+#[derive(Clone)]
 pub struct SolarSystem {
     pub bodies: Vec<body::CelestialBody>, 
     pub timestep: f64,
+    pub time: f64,              // seconds past J2000, see `time`
+    pub shadow_model: physics::ShadowModel,
+    pub potentials: Vec<physics::ExternalPotential>,
+    pub collision_policy: CollisionPolicy,
+    pub collision_log: Vec<CollisionEvent>,
+    pub flyby_log: Vec<mission::Flyby>,
+    pub transition_matrices: Vec<(usize, geometry::Matrix)>,   // body id, see `track_variations`
     integrator_type: integrators::IntegratorType,
+    next_id: usize,
 }
 
 impl SolarSystem {
@@ -26,36 +51,122 @@ impl SolarSystem {
         SolarSystem {
             bodies: Vec::new(),
             timestep,
-            integrator_type: integrator
+            time: 0.0,
+            shadow_model: physics::ShadowModel::Conical,
+            potentials: Vec::new(),
+            collision_policy: CollisionPolicy::Merge,
+            collision_log: Vec::new(),
+            flyby_log: Vec::new(),
+            transition_matrices: Vec::new(),
+            integrator_type: integrator,
+            next_id: 0,
         }
     }
 
-    pub fn add_body(&mut self, body: body::CelestialBody) {
+    pub fn add_body(&mut self, mut body: body::CelestialBody) {
+        body.id = self.next_id;
+        self.next_id += 1;
         self.bodies.push(body);
     }
 
     pub fn update(&mut self) {
+        let end_time = self.time + self.timestep;
+
+        // Burns falling inside this step with the id of their body, since collisions
+        // in the sub-steps before a burn can shift or remove bodies
+        let mut burns: Vec<(usize, mission::ImpulsiveBurn)> = Vec::new();
+        for body in &self.bodies {
+            for burn in &body.burns {
+                if burn.epoch >= self.time && burn.epoch < end_time {
+                    burns.push((body.id, burn.clone()));
+                }
+            }
+        }
+
+        // Split the step at burn epochs and thrust arc boundaries so that every
+        // burn happens exactly at its epoch and thrust never switches mid-step
+        let mut splits: Vec<f64> = burns.iter().map(|(_, burn)| burn.epoch).collect();
+        for body in &self.bodies {
+            for arc in &body.thrust_arcs {
+                splits.extend([arc.start, arc.end].into_iter().filter(|&t| t > self.time && t < end_time));
+            }
+        }
+        splits.sort_by(|a, b| a.total_cmp(b));
+        splits.dedup();
+
+        for epoch in splits {
+            self.advance(epoch - self.time);
+            for (id, burn) in burns.iter().filter(|(_, burn)| burn.epoch == epoch) {
+                if let Some(index) = self.bodies.iter().position(|body| body.id == *id) {
+                    self.apply_burn(index, burn);
+                }
+            }
+        }
+        self.advance(end_time - self.time);
+        self.time = end_time;
+    }
+
+    fn advance(&mut self, timestep: f64) {
+        if timestep <= 0.0 {
+            return;
+        }
+
+        // Propellant running out inside the step splits it at burnout, where the mass is
+        // set to exactly the dry mass
+        if let Some((index, burnout)) = self.next_burnout(timestep) {
+            let id = self.bodies[index].id;
+            self.advance(burnout);
+            if let Some(body) = self.bodies.iter_mut().find(|body| body.id == id) {
+                body.mass = body.dry_mass;
+            }
+            self.advance(timestep - burnout);
+            return;
+        }
 
         let positions: Vec<geometry::Vector3> = self.bodies.iter()
-        .map(|body| body.position.clone())
-        .collect();
+            .map(|body| body.position.clone())
+            .collect();
+        let start_positions = positions.clone();
         
         let velocities: Vec<geometry::Vector3> = self.bodies.iter()
             .map(|body| body.velocity.clone())
             .collect();
+        let start_velocities = velocities.clone();
             
         let masses: Vec<f64> = self.bodies.iter()
             .map(|body| body.mass)
             .collect();
             
-        let mut state = physics::State::new(positions, velocities, masses);
+        // Tracked transition matrices carry on from their current value; bodies with a
+        // covariance get a fresh one for this step to map it forward
+        let mut state = physics::State::new(positions, velocities, masses, self.time);
+        state.variations = self.transition_matrices.iter()
+            .filter_map(|(id, matrix)| {
+                let index = self.bodies.iter().position(|body| body.id == *id)?;
+                Some(physics::Variation { index, matrix: matrix.clone() })
+            })
+            .collect();
+        let tracked = state.variations.len();
+        state.variations.extend(self.bodies.iter()
+            .enumerate()
+            .filter(|(_, body)| body.covariance.is_some())
+            .map(|(index, _)| physics::Variation { index, matrix: geometry::Matrix::identity(6) }));
+        let model = physics::ForceModel::new(&self.bodies, self.time, self.shadow_model, &self.potentials);
+        let tidal_torques = model.tidal_torques(&state);
 
         match self.integrator_type {
             integrators::IntegratorType::Euler => {
-                integrators::EulerIntegrator.step(&mut state, self.timestep);
+                integrators::EulerIntegrator.step(&mut state, &model, timestep);
             },
             integrators::IntegratorType::RK4(substeps) => {  // Extract the substeps parameter
-                integrators::RK4Integrator::new(substeps).step(&mut state, self.timestep);
+                integrators::RK4Integrator::new(substeps).step(&mut state, &model, timestep);
+            },
+            integrators::IntegratorType::Kepler(central_id) => {
+                // Falls back to numerical integration once the central body is gone
+                match self.bodies.iter().position(|body| body.id == central_id) {
+                    Some(central) => integrators::KeplerIntegrator::new(central).step(&mut state, &model, timestep),
+                    None => integrators::RK4Integrator::new(1).step(&mut state, &model, timestep),
+                }
             }
         }
         
@@ -63,16 +174,1237 @@ impl SolarSystem {
         for (i, body) in self.bodies.iter_mut().enumerate() {
             body.position = state.positions[i].clone();
             body.velocity = state.velocities[i].clone();
+            // Integration error must not take a thrusting body below its dry mass
+            body.mass = if state.masses[i] < body.mass { state.masses[i].max(body.dry_mass) } else { state.masses[i] };
         }
+        for (n, variation) in state.variations.into_iter().enumerate() {
+            let body = &mut self.bodies[variation.index];
+            if n >= tracked {
+                if let Some(covariance) = &body.covariance {
+                    body.covariance = Some(variation.matrix.multiply(covariance).multiply(&variation.matrix.transpose()));
+                }
+            } else if let Some(entry) = self.transition_matrices.iter_mut().find(|(id, _)| *id == body.id) {
+                entry.1 = variation.matrix;
+            }
+        }
+        self.propagate_attitudes(&start_positions, &start_velocities, timestep);
+        self.apply_tidal_torques(&tidal_torques, timestep);
+        self.time += timestep;
+        self.apply_element_sets();
+        self.detect_flybys(&start_positions, &start_velocities);
+
+        if self.collision_policy != CollisionPolicy::Ignore {
+            self.resolve_collisions(start_positions);
+        }
+    }
+
+    // Earliest time into a step of `timestep` at which a thrusting body reaches its dry
+    // mass, with that body's index. Arcs only switch at step boundaries, so the mass
+    // flow is constant in between.
+    fn next_burnout(&self, timestep: f64) -> Option<(usize, f64)> {
+        self.bodies.iter()
+            .enumerate()
+            .filter(|(_, body)| body.dry_mass > 0.0 && body.mass > body.dry_mass)
+            .filter_map(|(i, body)| {
+                let flow: f64 = body.thrust_arcs.iter()
+                    .filter(|arc| arc.is_active(self.time))
+                    .map(|arc| arc.mass_flow_rate())
+                    .sum();
+                let burnout = (body.mass - body.dry_mass) / flow;
+                (flow > 0.0 && burnout < timestep).then_some((i, burnout))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    // Spins tidally deformed bodies up or down by the torque on their bulge, taken as
+    // constant over the step. Only the component along the pole changes the rotation
+    // model; the pole itself stays on its IAU track.
+    fn apply_tidal_torques(&mut self, torques: &[(usize, geometry::Vector3)], timestep: f64) {
+        let end_time = self.time + timestep;
+        for (index, torque) in torques {
+            let body = &mut self.bodies[*index];
+            let moment_of_inertia = body.inertia_factor * body.mass * (body.km_radius * 1000.0).powi(2);
+            if let (Some(rotation), true) = (body.rotation.as_mut(), moment_of_inertia > 0.0) {
+                let pole = rotation.pole(self.time);
+                rotation.change_spin_rate(end_time, torque.dot(&pole) * timestep / moment_of_inertia);
+            }
+        }
+    }
+
+    // Rigid-body attitude of bodies that carry one, under gravity-gradient and control
+    // torques, over the orbit step that just finished
+    fn propagate_attitudes(&mut self, start_positions: &[geometry::Vector3], start_velocities: &[geometry::Vector3], timestep: f64) {
+        for index in 0..self.bodies.len() {
+            if self.bodies[index].attitude.is_none() {
+                continue;
+            }
 
+            let primary = self.find_primary(index);
+            let (primary_mu, position, velocity) = match primary {
+                Some(p) => (
+                    physics::GRAVITATIONAL_CONST * self.bodies[p].mass,
+                    start_positions[index].subtract(&start_positions[p]),
+                    start_velocities[index].subtract(&start_velocities[p]),
+                ),
+                None => (0.0, start_positions[index].clone(), start_velocities[index].clone()),
+            };
+
+            let body = &mut self.bodies[index];
+            let environment = attitude::AttitudeEnvironment {
+                primary_mu,
+                position: &position,
+                velocity: &velocity,
+                control: body.attitude_control.as_ref(),
+            };
+            if let Some(state) = body.attitude.as_mut() {
+                state.propagate(self.time, timestep, &environment);
+            }
+        }
+    }
+
+    // Logs spacecraft, asteroids and comets that passed periapsis on a hyperbolic orbit
+    // inside the sphere of influence of a planet or moon during the last step
+    fn detect_flybys(&mut self, start_positions: &[geometry::Vector3], start_velocities: &[geometry::Vector3]) {
+        let attractors: Vec<(usize, f64)> = self.bodies.iter()
+            .enumerate()
+            .filter(|(_, body)| matches!(body.body_type, BodyType::Planet | BodyType::Moon) && body.mass > 0.0)
+            .filter_map(|(i, body)| {
+                let primary = &self.bodies[self.central_body(i)?];
+                let distance = body.position.subtract(&primary.position).magnitude();
+                Some((i, mission::sphere_of_influence(distance, body.mass, primary.mass)))
+            })
+            .collect();
+
+        for (i, flyer) in self.bodies.iter().enumerate() {
+            if !matches!(flyer.body_type, BodyType::Satellite | BodyType::Asteroid | BodyType::Comet) {
+                continue;
+            }
+            for &(p, radius) in &attractors {
+                let body = &self.bodies[p];
+                let position = flyer.position.subtract(&body.position);
+                if p == i || position.magnitude() > radius {
+                    continue;
+                }
+                let velocity = flyer.velocity.subtract(&body.velocity);
+                let start_position = start_positions[i].subtract(&start_positions[p]);
+                let start_velocity = start_velocities[i].subtract(&start_velocities[p]);
+                if start_position.dot(&start_velocity) >= 0.0 || position.dot(&velocity) < 0.0 {
+                    continue;
+                }
+
+                let mu = physics::GRAVITATIONAL_CONST * (body.mass + flyer.mass);
+                if let Some(flyby) = mission::flyby_from_state(&position, &velocity, mu, self.time, &flyer.name, &body.name) {
+                    self.flyby_log.push(flyby);
+                }
+            }
+        }
+    }
+
+    fn resolve_collisions(&mut self, mut start_positions: Vec<geometry::Vector3>) {
+        // Fragments created in this step are appended after every older body and only
+        // take part in collision checks from the next step on
+        let first_new_id = self.next_id;
+        loop {
+            let settled = self.bodies.iter().take_while(|body| body.id < first_new_id).count();
+            let (i, j) = match collision::find_collision(&self.bodies[..settled], &start_positions[..settled]) {
+                Some(pair) => pair,
+                None => break,
+            };
+            let relative_speed = self.bodies[j].velocity.subtract(&self.bodies[i].velocity).magnitude();
+            self.collision_log.push(CollisionEvent {
+                time: self.time,
+                first: self.bodies[i].name.clone(),
+                second: self.bodies[j].name.clone(),
+                policy: self.collision_policy,
+                position: self.bodies[i].position.clone(),
+                relative_speed,
+            });
+
+            match self.collision_policy {
+                CollisionPolicy::Ignore => break,
+                CollisionPolicy::Merge => {
+                    let other = self.bodies.remove(j);
+                    start_positions.remove(j);
+                    collision::merge(&mut self.bodies[i], other);
+                    start_positions[i] = self.bodies[i].position.clone();
+                },
+                CollisionPolicy::Bounce { restitution } => {
+                    let (first, second) = self.bodies.split_at_mut(j);
+                    collision::bounce(&mut first[i], &mut second[0], restitution);
+                    start_positions[i] = self.bodies[i].position.clone();
+                    start_positions[j] = self.bodies[j].position.clone();
+                },
+                CollisionPolicy::Fragment { count } => {
+                    let other = self.bodies.remove(j);
+                    start_positions.remove(j);
+                    let fragments = collision::fragment(&mut self.bodies[i], other, count);
+                    start_positions[i] = self.bodies[i].position.clone();
+                    for fragment in fragments {
+                        self.add_body(fragment);
+                    }
+                },
+            }
+        }
+    }
+
+    fn apply_burn(&mut self, index: usize, burn: &mission::ImpulsiveBurn) {
+        let (position, velocity) = match self.find_primary(index) {
+            Some(primary) => (
+                self.bodies[index].position.subtract(&self.bodies[primary].position),
+                self.bodies[index].velocity.subtract(&self.bodies[primary].velocity),
+            ),
+            None => (self.bodies[index].position.clone(), self.bodies[index].velocity.clone()),
+        };
+
+        let delta_v = burn.inertial_delta_v(&position, &velocity);
+        let body = &mut self.bodies[index];
+        body.velocity = body.velocity.add(&delta_v);
+    }
+
+    pub fn add_potential(&mut self, potential: physics::ExternalPotential) {
+        self.potentials.push(potential);
+    }
+
+    pub fn find_primary(&self, index: usize) -> Option<usize> {
+        body::find_primary(&self.bodies, index)
     }
 
     pub fn get_bodies(&self) -> &Vec<body::CelestialBody> {
         &self.bodies
     }
 
+    // State of `bodies[index]` at `epoch` assuming every body follows a two-body orbit
+    // about the body it orbits, and stars coast in a straight line
+    pub fn predict_state(&self, index: usize, epoch: f64) -> (geometry::Vector3, geometry::Vector3) {
+        let body = &self.bodies[index];
+        let timestep = epoch - self.time;
+        match self.central_body(index) {
+            Some(central) => {
+                let (central_position, central_velocity) = self.predict_state(central, epoch);
+                let other = &self.bodies[central];
+                let mu = physics::GRAVITATIONAL_CONST * (other.mass + body.mass);
+                let (position, velocity) = orbit::propagate_kepler(
+                    &body.position.subtract(&other.position),
+                    &body.velocity.subtract(&other.velocity),
+                    mu,
+                    timestep,
+                );
+                (central_position.add(&position), central_velocity.add(&velocity))
+            },
+            None => (body.position.add(&body.velocity.scale(timestep)), body.velocity.clone()),
+        }
+    }
+
+    // Like `find_primary`, but never for stars and only towards heavier bodies, so that
+    // following it always ends
+    fn central_body(&self, index: usize) -> Option<usize> {
+        if self.bodies[index].body_type == BodyType::Star {
+            return None;
+        }
+        self.find_primary(index).filter(|&primary| self.bodies[primary].mass > self.bodies[index].mass)
+    }
+
+    // Lambert transfers from `bodies[from]` leaving at `departure` to `bodies[to]` at
+    // `arrival`, about the body `from` orbits, with up to `max_revolutions` revolutions
+    pub fn plan_transfer(&self, from: usize, to: usize, departure: f64, arrival: f64, max_revolutions: usize) -> Vec<mission::Transfer> {
+        let Some(central) = self.central_body(from) else {
+            return Vec::new();
+        };
+        let mu = physics::GRAVITATIONAL_CONST * self.bodies[central].mass;
+
+        let relative_state = |index: usize, epoch: f64| {
+            let (position, velocity) = self.predict_state(index, epoch);
+            let (central_position, central_velocity) = self.predict_state(central, epoch);
+            (position.subtract(&central_position), velocity.subtract(&central_velocity))
+        };
+        let (departure_position, departure_velocity) = relative_state(from, departure);
+        let (arrival_position, arrival_velocity) = relative_state(to, arrival);
+
+        mission::solve_lambert(&departure_position, &arrival_position, arrival - departure, mu, true, max_revolutions)
+            .into_iter()
+            .map(|solution| mission::Transfer {
+                departure_body: self.bodies[from].id,
+                arrival_body: self.bodies[to].id,
+                central_body: self.bodies[central].id,
+                departure_epoch: departure,
+                arrival_epoch: arrival,
+                departure_position: departure_position.clone(),
+                arrival_position: arrival_position.clone(),
+                departure_delta_v: solution.departure_velocity.subtract(&departure_velocity),
+                arrival_delta_v: arrival_velocity.subtract(&solution.arrival_velocity),
+                solution,
+                mu,
+            })
+            .collect()
+    }
+
+    // Hohmann or bi-elliptic transfer for `bodies[index]` from its current orbit about
+    // its primary to `target`, starting no earlier than now
+    pub fn plan_maneuver(&self, index: usize, target: &mission::ManeuverTarget, kind: mission::ManeuverKind) -> Option<mission::ManeuverPlan> {
+        let primary = self.find_primary(index)?;
+        let (spacecraft, central) = (&self.bodies[index], &self.bodies[primary]);
+        let position = spacecraft.position.subtract(&central.position);
+        let velocity = spacecraft.velocity.subtract(&central.velocity);
+        let mu = physics::GRAVITATIONAL_CONST * (central.mass + spacecraft.mass);
+
+        let (radius, normal) = match target {
+            mission::ManeuverTarget::Circular { radius, normal } => {
+                (*radius, normal.clone().unwrap_or_else(|| position.cross(&velocity)))
+            },
+            mission::ManeuverTarget::Body(id) => {
+                let other = self.bodies.iter().find(|body| body.id == *id)?;
+                let other_position = other.position.subtract(&central.position);
+                (other_position.magnitude(), other_position.cross(&other.velocity.subtract(&central.velocity)))
+            },
+        };
+        mission::plan_maneuver(&position, &velocity, mu, self.time, radius, &normal, kind)
+    }
+
+    pub fn schedule_maneuver(&mut self, index: usize, plan: &mission::ManeuverPlan) {
+        for burn in &plan.burns {
+            self.bodies[index].schedule_burn(burn.clone());
+        }
+    }
+
+    // Integrates with the full force model up to exactly `epoch`
+    pub fn run_until(&mut self, epoch: f64) {
+        let timestep = self.timestep;
+        while self.time < epoch {
+            self.timestep = timestep.min(epoch - self.time);
+            self.update();
+        }
+        self.timestep = timestep;
+    }
+
+    // Flyby of `bodies[body]` by `bodies[spacecraft]` seen after integrating a copy of
+    // the system to `encounter`, from the osculating hyperbola at that moment
+    pub fn predict_flyby(&self, spacecraft: usize, body: usize, encounter: f64) -> Option<mission::Flyby> {
+        let (spacecraft_id, body_id) = (self.bodies[spacecraft].id, self.bodies[body].id);
+        let mut copy = self.clone();
+        copy.run_until(encounter);
+
+        let spacecraft = copy.bodies.iter().find(|b| b.id == spacecraft_id)?;
+        let body = copy.bodies.iter().find(|b| b.id == body_id)?;
+        let mu = physics::GRAVITATIONAL_CONST * (body.mass + spacecraft.mass);
+        mission::flyby_from_state(
+            &spacecraft.position.subtract(&body.position),
+            &spacecraft.velocity.subtract(&body.velocity),
+            mu,
+            copy.time,
+            &spacecraft.name,
+            &body.name,
+        )
+    }
+
+    // Differential corrector: adjusts the delta-v of `bodies[spacecraft].burns[burn]` so
+    // that the approach to `bodies[body]` around `encounter` passes through the target
+    // B-plane point, and at the target periapsis time if one is set. Each iteration
+    // integrates the full system once per burn component to build the sensitivity
+    // matrix and takes a Newton step, minimum-norm when only the B-plane is targeted.
+    // The burn keeps its last value whether or not the corrector converges.
+    pub fn target_b_plane(&mut self, spacecraft: usize, burn: usize, body: usize, encounter: f64, target: &mission::BPlaneTarget) -> mission::TargetingResult {
+        let evaluate = |system: &SolarSystem, delta_v: &geometry::Vector3| {
+            let mut copy = system.clone();
+            copy.bodies[spacecraft].burns[burn].delta_v = delta_v.clone();
+            copy.predict_flyby(spacecraft, body, encounter)
+        };
+        // Miss in metres; a periapsis time error counts as the distance flown at v-infinity
+        let residual = |flyby: &mission::Flyby| {
+            let timing = target.periapsis_time.map_or(0.0, |time| (flyby.time - time) * flyby.v_infinity_in.magnitude());
+            [flyby.b_plane.b_dot_t - target.b_dot_t, flyby.b_plane.b_dot_r - target.b_dot_r, timing]
+        };
+
+        let mut delta_v = self.bodies[spacecraft].burns[burn].delta_v.clone();
+        let mut achieved = evaluate(self, &delta_v);
+        let mut iterations = 0;
+        let mut converged = false;
+
+        while let Some(current) = achieved.clone() {
+            let error = residual(&current);
+            if (error[0] * error[0] + error[1] * error[1] + error[2] * error[2]).sqrt() <= target.tolerance {
+                converged = true;
+                break;
+            }
+            if iterations == target.max_iterations {
+                break;
+            }
+            iterations += 1;
+
+            // Finite-difference columns for each delta-v component
+            let step = 1e-3 * delta_v.magnitude().max(1.0);
+            let mut jacobian = [[0.0; 3]; 3];
+            for (k, offset) in [
+                geometry::Vector3::new(step, 0.0, 0.0),
+                geometry::Vector3::new(0.0, step, 0.0),
+                geometry::Vector3::new(0.0, 0.0, step),
+            ].iter().enumerate() {
+                let Some(perturbed) = evaluate(self, &delta_v.add(offset)) else {
+                    continue;
+                };
+                let perturbed_error = residual(&perturbed);
+                for row in 0..3 {
+                    jacobian[row][k] = (perturbed_error[row] - error[row]) / step;
+                }
+            }
+
+            let correction = if target.periapsis_time.is_some() {
+                // Singular relative to the product of the row lengths, its largest possible value
+                let matrix = geometry::Matrix3::new(jacobian);
+                let scale: f64 = jacobian.iter().map(|row| (row[0] * row[0] + row[1] * row[1] + row[2] * row[2]).sqrt()).product();
+                if matrix.determinant().abs() <= f64::EPSILON * scale {
+                    break;
+                }
+                matrix.inverse().multiply_vector(&geometry::Vector3::new(error[0], error[1], error[2]))
+            } else {
+                // Minimum-norm correction: J^T (J J^T)^-1 residual
+                let row_dot = |a: &[f64; 3], b: &[f64; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+                let (a, b, d) = (row_dot(&jacobian[0], &jacobian[0]), row_dot(&jacobian[0], &jacobian[1]), row_dot(&jacobian[1], &jacobian[1]));
+                let determinant = a * d - b * b;
+                if determinant.abs() <= f64::EPSILON * a * d {
+                    break;
+                }
+                let y = [(d * error[0] - b * error[1]) / determinant, (a * error[1] - b * error[0]) / determinant];
+                geometry::Vector3::new(
+                    jacobian[0][0] * y[0] + jacobian[1][0] * y[1],
+                    jacobian[0][1] * y[0] + jacobian[1][1] * y[1],
+                    jacobian[0][2] * y[0] + jacobian[1][2] * y[1],
+                )
+            };
+            delta_v = delta_v.subtract(&correction);
+            achieved = evaluate(self, &delta_v);
+        }
+
+        self.bodies[spacecraft].burns[burn].delta_v = delta_v.clone();
+        mission::TargetingResult {
+            delta_v,
+            b_plane: achieved.as_ref().map(|flyby| flyby.b_plane.clone()),
+            periapsis_time: achieved.map(|flyby| flyby.time),
+            iterations,
+            converged,
+        }
+    }
+
+    // Multiple gravity assist tour through `sequence` (body indices) about the star the
+    // first body orbits, see `mission::optimize_mga`
+    pub fn optimize_mga(&self, sequence: &[usize], options: &mission::MgaOptions) -> Option<mission::MgaSolution> {
+        let star = self.central_body(*sequence.first()?)?;
+        let bodies: Vec<mission::MgaBody> = sequence.iter()
+            .map(|&index| mission::MgaBody {
+                id: self.bodies[index].id,
+                name: self.bodies[index].name.clone(),
+                mu: physics::GRAVITATIONAL_CONST * self.bodies[index].mass,
+                radius: self.bodies[index].km_radius * 1000.0,
+            })
+            .collect();
+
+        let ephemeris = |n: usize, epoch: f64| {
+            let (position, velocity) = self.predict_state(sequence[n], epoch);
+            let (star_position, star_velocity) = self.predict_state(star, epoch);
+            (position.subtract(&star_position), velocity.subtract(&star_velocity))
+        };
+        let mu = physics::GRAVITATIONAL_CONST * self.bodies[star].mass;
+        mission::optimize_mga(&bodies, mu, &ephemeris, options)
+    }
+
+    // Flies a tour in the full N-body model: injects `spacecraft` on the first leg, then
+    // for every flyby schedules a correction burn early in the leg before it (see
+    // `correction_epoch`), starts it on the Lambert arc to the planet, targets it onto the
+    // planned B-plane point and periapsis time, and schedules the powered-flyby burn along
+    // the velocity at periapsis. The last leg gets a Lambert correction aimed at the
+    // centre of the target. Returns the targeting result of each flyby; running the
+    // system afterwards and reading `flyby_log` verifies the tour.
+    pub fn inject_mga(&mut self, solution: &mission::MgaSolution, spacecraft: body::CelestialBody) -> Vec<mission::TargetingResult> {
+        let index_of = |system: &SolarSystem, id: usize| system.bodies.iter().position(|body| body.id == id);
+        let (Some(from), Some(to)) = (index_of(self, solution.body_ids[0]), index_of(self, solution.body_ids[1])) else {
+            return Vec::new();
+        };
+        let Some(transfer) = self.plan_transfer(from, to, solution.epochs[0], solution.epochs[1], 0).into_iter().next() else {
+            return Vec::new();
+        };
+        self.inject_on_transfer(&transfer, spacecraft);
+        let craft = self.bodies.len() - 1;
+
+        let mut results = Vec::new();
+        for (n, flyby) in solution.flybys.iter().enumerate() {
+            let Some(planet) = index_of(self, solution.body_ids[n + 1]) else {
+                break;
+            };
+            let epoch = self.correction_epoch(solution, n).max(self.time);
+            let guess = self.aim_at(craft, planet, epoch, flyby.epoch).unwrap_or(geometry::Vector3::new(0.0, 0.0, 0.0));
+            self.bodies[craft].schedule_burn(mission::ImpulsiveBurn::new(epoch, guess, mission::BurnFrame::Inertial));
+            let burn = self.bodies[craft].burns.len() - 1;
+            let target = mission::BPlaneTarget::new(flyby.b_plane.b_dot_t, flyby.b_plane.b_dot_r).with_periapsis_time(flyby.epoch);
+            results.push(self.target_b_plane(craft, burn, planet, flyby.epoch, &target));
+
+            let sign = if flyby.v_infinity_out.magnitude() >= flyby.v_infinity_in.magnitude() { 1.0 } else { -1.0 };
+            self.bodies[craft].schedule_burn(mission::ImpulsiveBurn::new(
+                flyby.epoch,
+                geometry::Vector3::new(sign * flyby.delta_v, 0.0, 0.0),
+                mission::BurnFrame::VNB,
+            ));
+        }
+
+        // The last leg only gets its Lambert correction, aimed at the centre of the target
+        let last = solution.epochs.len() - 1;
+        if let Some(target) = index_of(self, solution.body_ids[last]) {
+            let epoch = self.correction_epoch(solution, last - 1).max(self.time);
+            if let Some(guess) = self.aim_at(craft, target, epoch, solution.epochs[last]) {
+                self.bodies[craft].schedule_burn(mission::ImpulsiveBurn::new(epoch, guess, mission::BurnFrame::Inertial));
+            }
+        }
+        results
+    }
+
+    // Epoch of the correction burn on leg `n` of a tour: the first of a tenth, a fifth
+    // and so on up to half way along the planned arc that is more than 30 degrees short of
+    // half a revolution from the flyby, or the furthest from it. Close to half a
+    // revolution the arc to the flyby barely depends on its plane, so small misses there
+    // take kilometres per second to correct.
+    fn correction_epoch(&self, solution: &mission::MgaSolution, n: usize) -> f64 {
+        let (start, end) = (solution.epochs[n], solution.epochs[n + 1]);
+        let fallback = start + (end - start) / 10.0;
+        let index_of = |id: usize| self.bodies.iter().position(|body| body.id == id);
+        let (Some(from), Some(to)) = (index_of(solution.body_ids[n]), index_of(solution.body_ids[n + 1])) else {
+            return fallback;
+        };
+        let Some(star) = self.central_body(from) else {
+            return fallback;
+        };
+        let relative_position = |index: usize, epoch: f64| self.predict_state(index, epoch).0.subtract(&self.predict_state(star, epoch).0);
+        let (departure, arrival) = (relative_position(from, start), relative_position(to, end));
+        let mu = physics::GRAVITATIONAL_CONST * self.bodies[star].mass;
+
+        let mut best = (fallback, 0.0);
+        for tenths in 1..=5 {
+            let epoch = start + (end - start) * tenths as f64 / 10.0;
+            let (position, _) = orbit::propagate_kepler(&departure, &solution.departure_velocities[n], mu, epoch - start);
+            let angle = (position.dot(&arrival) / (position.magnitude() * arrival.magnitude())).clamp(-1.0, 1.0).acos();
+            let margin = std::f64::consts::PI - angle;
+            if margin > 30f64.to_radians() {
+                return epoch;
+            }
+            if margin > best.1 {
+                best = (epoch, margin);
+            }
+        }
+        best.0
+    }
+
+    // Inertial delta-v at `epoch` that puts `bodies[spacecraft]` on the zero-revolution
+    // Lambert arc to the predicted centre of `bodies[body]` at `encounter`, about their
+    // star. Starting the B-plane corrector from it keeps its first steps small: the
+    // injection at the edge of the departure sphere of influence alone can put the
+    // uncorrected approach a million kilometres and days off.
+    fn aim_at(&self, spacecraft: usize, body: usize, epoch: f64, encounter: f64) -> Option<geometry::Vector3> {
+        let star = self.central_body(body)?;
+        let (spacecraft_id, star_id) = (self.bodies[spacecraft].id, self.bodies[star].id);
+        let mut copy = self.clone();
+        copy.run_until(epoch);
+
+        let spacecraft = copy.bodies.iter().find(|b| b.id == spacecraft_id)?;
+        let star = copy.bodies.iter().find(|b| b.id == star_id)?;
+        let (target, _) = self.predict_state(body, encounter);
+        let (star_position, _) = self.predict_state(self.bodies.iter().position(|b| b.id == star_id)?, encounter);
+        let mu = physics::GRAVITATIONAL_CONST * star.mass;
+        let arc = mission::solve_lambert(
+            &spacecraft.position.subtract(&star.position),
+            &target.subtract(&star_position),
+            encounter - epoch,
+            mu,
+            true,
+            0,
+        ).into_iter().next()?;
+        Some(arc.departure_velocity.subtract(&spacecraft.velocity.subtract(&star.velocity)))
+    }
+
+    // Zero-revolution transfers from `bodies[from]` to `bodies[to]` for every pair of
+    // departure and arrival epochs, for porkchop plots
+    pub fn porkchop(&self, from: usize, to: usize, departures: &[f64], arrivals: &[f64]) -> mission::PorkchopGrid {
+        let transfers = departures.iter()
+            .map(|&departure| {
+                arrivals.iter()
+                    .map(|&arrival| {
+                        if arrival <= departure {
+                            return None;
+                        }
+                        self.plan_transfer(from, to, departure, arrival, 0).into_iter().next()
+                    })
+                    .collect()
+            })
+            .collect();
+
+        mission::PorkchopGrid {
+            departure_body: self.bodies[from].name.clone(),
+            arrival_body: self.bodies[to].name.clone(),
+            departures: departures.to_vec(),
+            arrivals: arrivals.to_vec(),
+            transfers,
+        }
+    }
+
+    // Adds `spacecraft` on the transfer arc at the current time. The arc starts at the
+    // centre of the departure body, so while still inside its sphere of influence the
+    // spacecraft is placed on its edge instead, leaving along the departure asymptote.
+    pub fn inject_on_transfer(&mut self, transfer: &mission::Transfer, mut spacecraft: body::CelestialBody) {
+        let Some(central) = self.bodies.iter().position(|body| body.id == transfer.central_body) else {
+            return;
+        };
+        let (position, velocity) = transfer.state_at(self.time);
+        spacecraft.position = self.bodies[central].position.add(&position);
+        spacecraft.velocity = self.bodies[central].velocity.add(&velocity);
+
+        if let Some(departure) = self.bodies.iter().find(|body| body.id == transfer.departure_body) {
+            let radius = mission::sphere_of_influence(
+                departure.position.subtract(&self.bodies[central].position).magnitude(),
+                departure.mass,
+                self.bodies[central].mass,
+            );
+            if spacecraft.position.subtract(&departure.position).magnitude() < radius {
+                spacecraft.position = departure.position.add(&transfer.departure_delta_v.norm().scale(radius));
+                spacecraft.velocity = departure.velocity.add(&transfer.departure_delta_v);
+            }
+        }
+        self.add_body(spacecraft);
+    }
+
+    // Inertial state of an observer at the current time. Stations turn with the body's
+    // rotation model, or keep a fixed inertial offset when it has none.
+    pub fn observer_state(&self, observer: &estimation::Observer) -> Option<(geometry::Vector3, geometry::Vector3)> {
+        match observer {
+            estimation::Observer::Body(id) => {
+                let body = self.bodies.iter().find(|body| body.id == *id)?;
+                Some((body.position.clone(), body.velocity.clone()))
+            },
+            estimation::Observer::Station(station) => {
+                let body = self.bodies.iter().find(|body| body.id == station.body)?;
+                let Some(rotation) = &body.rotation else {
+                    return Some((body.position.add(&station.position), body.velocity.clone()));
+                };
+                let offset = rotation.from_body_fixed(&station.position, self.time);
+                let velocity = rotation.spin_vector(self.time).cross(&offset);
+                Some((body.position.add(&offset), body.velocity.add(&velocity)))
+            },
+        }
+    }
+
+    // Modelled observation of `bodies[target]` now, with its partials with respect to the
+    // target's state
+    pub fn observe(&self, target: usize, observer: &estimation::Observer, kind: estimation::ObservationKind) -> Option<(Vec<f64>, Vec<[f64; 6]>)> {
+        let (position, velocity) = self.observer_state(observer)?;
+        let body = &self.bodies[target];
+        Some(kind.model(&body.position.subtract(&position), &body.velocity.subtract(&velocity)))
+    }
+
+    // Observations of `bodies[target]` at `times` made by integrating a copy of the
+    // system, with Gaussian noise of standard deviation `sigmas` (one per value)
+    pub fn simulate_observations(&self, target: usize, observer: &estimation::Observer, kind: estimation::ObservationKind, times: &[f64], sigmas: &[f64], seed: u64) -> Vec<estimation::Observation> {
+        let target_id = self.bodies[target].id;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut times = times.to_vec();
+        times.sort_by(|a, b| a.total_cmp(b));
+
+        let mut copy = self.clone();
+        let mut observations = Vec::new();
+        for time in times.into_iter().filter(|&time| time >= self.time) {
+            copy.run_until(time);
+            let Some(index) = copy.bodies.iter().position(|body| body.id == target_id) else {
+                break;
+            };
+            let Some((values, _)) = copy.observe(index, observer, kind) else {
+                continue;
+            };
+            let values = values.iter()
+                .zip(sigmas)
+                .map(|(value, sigma)| {
+                    // Box-Muller transform
+                    let (u, v): (f64, f64) = (1.0 - rng.gen::<f64>(), rng.gen());
+                    value + sigma * (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+                })
+                .collect();
+            observations.push(estimation::Observation::new(time, kind, observer.clone(), values, sigmas.to_vec()));
+        }
+        observations
+    }
+
+    // Starts integrating the variational equations of `bodies[index]` from the identity,
+    // restarting them if they were already tracked
+    pub fn track_variations(&mut self, index: usize) {
+        let id = self.bodies[index].id;
+        self.transition_matrices.retain(|(tracked, _)| *tracked != id);
+        self.transition_matrices.push((id, geometry::Matrix::identity(6)));
+    }
+
+    pub fn stop_tracking_variations(&mut self, index: usize) {
+        let id = self.bodies[index].id;
+        self.transition_matrices.retain(|(tracked, _)| *tracked != id);
+    }
+
+    // State transition matrix of `bodies[index]` since `track_variations` was called
+    pub fn state_transition_matrix(&self, index: usize) -> Option<&geometry::Matrix> {
+        let id = self.bodies[index].id;
+        self.transition_matrices.iter().find(|(tracked, _)| *tracked == id).map(|(_, matrix)| matrix)
+    }
+
+    // Partials of the state of `bodies[target]` at each of `epochs` (ascending, not
+    // before now) with respect to its current state, from the variational equations
+    // integrated on a copy of the system
+    pub fn state_transition_matrices(&self, target: usize, epochs: &[f64]) -> Vec<geometry::Matrix> {
+        let target_id = self.bodies[target].id;
+        let mut copy = self.clone();
+        copy.track_variations(target);
+
+        let mut matrices = Vec::new();
+        for &epoch in epochs {
+            copy.run_until(epoch);
+            let matrix = copy.bodies.iter()
+                .position(|body| body.id == target_id)
+                .and_then(|index| copy.state_transition_matrix(index));
+            matrices.push(matrix.cloned().unwrap_or_else(|| geometry::Matrix::zeros(6, 6)));
+        }
+        matrices
+    }
+
+    // Same partials as `state_transition_matrices` by central differences of the full
+    // integration, so including every force. Steps are a millionth of the distance and
+    // speed relative to the body it orbits; twelve integrations instead of one.
+    pub fn finite_difference_transition_matrices(&self, target: usize, epochs: &[f64]) -> Vec<geometry::Matrix> {
+        let target_id = self.bodies[target].id;
+        let (position_step, velocity_step) = match self.find_primary(target) {
+            Some(primary) => (
+                1e-6 * self.bodies[target].position.subtract(&self.bodies[primary].position).magnitude(),
+                1e-6 * self.bodies[target].velocity.subtract(&self.bodies[primary].velocity).magnitude(),
+            ),
+            None => (1e-6 * self.bodies[target].position.magnitude(), 1e-6 * self.bodies[target].velocity.magnitude()),
+        };
+
+        // Final states of the target, one list per perturbed run
+        let run = |component: usize, step: f64| -> Vec<[f64; 6]> {
+            let mut copy = self.clone();
+            let body = &mut copy.bodies[target];
+            let mut state = body_state(body);
+            state[component] += step;
+            set_body_state(body, &state);
+
+            let mut states = Vec::new();
+            for &epoch in epochs {
+                copy.run_until(epoch);
+                match copy.bodies.iter().find(|body| body.id == target_id) {
+                    Some(body) => states.push(body_state(body)),
+                    None => states.push([f64::NAN; 6]),
+                }
+            }
+            states
+        };
+
+        let mut matrices = vec![geometry::Matrix::zeros(6, 6); epochs.len()];
+        for column in 0..6 {
+            let step = if column < 3 { position_step } else { velocity_step }.max(f64::EPSILON);
+            let (plus, minus) = (run(column, step), run(column, -step));
+            for (matrix, (plus, minus)) in matrices.iter_mut().zip(plus.iter().zip(&minus)) {
+                for row in 0..6 {
+                    matrix.rows[row][column] = (plus[row] - minus[row]) / (2.0 * step);
+                }
+            }
+        }
+        matrices
+    }
+
+    // Batch least-squares estimate of the current state of `bodies[target]` from
+    // `observations` made after now. Each iteration integrates the system from the
+    // current estimate, linearises the measurements through the state transition matrix
+    // and solves the weighted normal equations; it ends once the weighted RMS of the
+    // residuals stops changing. The system itself is left untouched. `None` if the
+    // observations cannot determine the state.
+    pub fn fit_orbit(&self, target: usize, observations: &[estimation::Observation], options: &estimation::BatchOptions) -> Option<estimation::BatchSolution> {
+        let target_id = self.bodies[target].id;
+        let mut observations: Vec<&estimation::Observation> = observations.iter().filter(|observation| observation.time >= self.time).collect();
+        observations.sort_by(|a, b| a.time.total_cmp(&b.time));
+        let mut epochs: Vec<f64> = observations.iter().map(|observation| observation.time).collect();
+        epochs.dedup();
+
+        let initial = body_state(&self.bodies[target]);
+        let mut state = initial;
+        let mut previous_rms = f64::INFINITY;
+        let mut iterations = 0;
+
+        loop {
+            iterations += 1;
+            let mut system = self.clone();
+            set_body_state(&mut system.bodies[target], &state);
+            let matrices = system.state_transition_matrices(target, &epochs);
+
+            let mut equations = estimation::NormalEquations::new(6);
+            if let Some(covariance) = &options.a_priori_covariance {
+                let deviation: Vec<f64> = (0..6).map(|n| initial[n] - state[n]).collect();
+                equations.add_a_priori(covariance, &deviation)?;
+            }
+
+            let mut residuals = Vec::new();
+            let mut copy = system.clone();
+            for observation in &observations {
+                copy.run_until(observation.time);
+                let index = copy.bodies.iter().position(|body| body.id == target_id)?;
+                let (computed, partials) = copy.observe(index, &observation.observer, observation.kind)?;
+                let matrix = &matrices[epochs.iter().position(|&epoch| epoch == observation.time)?];
+
+                let values = observation.kind.residual(&observation.values, &computed);
+                for ((partial, value), sigma) in partials.iter().zip(&values).zip(&observation.sigmas) {
+                    let row: Vec<f64> = (0..6).map(|column| (0..6).map(|k| partial[k] * matrix.rows[k][column]).sum()).collect();
+                    equations.add(&row, *value, *sigma);
+                }
+                residuals.push(estimation::Residual {
+                    time: observation.time,
+                    kind: observation.kind,
+                    normalized: values.iter().zip(&observation.sigmas).map(|(value, sigma)| value / sigma).collect(),
+                    values,
+                });
+            }
+
+            let count: usize = residuals.iter().map(|residual| residual.normalized.len()).sum();
+            let rms = (residuals.iter().flat_map(|residual| &residual.normalized).map(|value| value * value).sum::<f64>() / count.max(1) as f64).sqrt();
+            let (correction, covariance) = equations.solve()?;
+            let converged = (previous_rms - rms).abs() <= options.tolerance * rms;
+
+            if converged || iterations == options.max_iterations {
+                return Some(estimation::BatchSolution {
+                    epoch: self.time,
+                    position: geometry::Vector3::new(state[0], state[1], state[2]),
+                    velocity: geometry::Vector3::new(state[3], state[4], state[5]),
+                    covariance,
+                    residuals,
+                    rms,
+                    iterations,
+                    converged,
+                });
+            }
+            for (value, delta) in state.iter_mut().zip(correction) {
+                *value += delta;
+            }
+            previous_rms = rms;
+        }
+    }
+
+    // Sequential estimation: integrates the system up to `observation.time` and updates
+    // the state and covariance of `bodies[target]` with the measurement, unless it lies
+    // beyond the rejection threshold. The body needs a covariance to start from. The
+    // extended filter lets the simulation map the covariance through the variational
+    // equations; the unscented one integrates a copy of the system per sigma point.
+    // `None`, with the system untouched, if the observation is in the past, the body has
+    // no covariance or the update is singular.
+    pub fn process_observation(&mut self, target: usize, observation: &estimation::Observation, options: &estimation::FilterOptions) -> Option<estimation::FilterUpdate> {
+        let covariance = self.bodies[target].covariance.clone()?;
+        if observation.time < self.time {
+            return None;
+        }
+        let target_id = self.bodies[target].id;
+        let kind = observation.kind;
+        let process_noise = estimation::process_noise(options.process_noise, observation.time - self.time);
+        let measurement_noise = geometry::Matrix::diagonal(&observation.sigmas.iter().map(|sigma| sigma * sigma).collect::<Vec<f64>>());
+
+        // Everything is worked out on a copy that replaces the system only once the
+        // update has succeeded, so a failure leaves the time, state and covariance as they were
+        let mut system = self.clone();
+
+        // Predicted state, prefit residuals, innovation covariance, gain and updated covariance
+        let (index, state, prefit, innovation, gain, updated) = match options.kind {
+            estimation::FilterKind::Extended => {
+                system.run_until(observation.time);
+                let index = system.bodies.iter().position(|body| body.id == target_id)?;
+                let covariance = system.bodies[index].covariance.as_ref()?.add(&process_noise);
+                let (computed, partials) = system.observe(index, &observation.observer, kind)?;
+                let partials = geometry::Matrix::new(partials.iter().map(|row| row.to_vec()).collect());
+                let prefit = kind.residual(&observation.values, &computed);
+
+                let (gain, updated, innovation) = estimation::extended_update(&covariance, &partials, &measurement_noise)?;
+                system.bodies[index].covariance = Some(covariance);
+                (index, body_state(&system.bodies[index]), prefit, innovation, gain, updated)
+            },
+            estimation::FilterKind::Unscented { alpha, beta, kappa } => {
+                let mean = body_state(&system.bodies[target]);
+                let estimation::SigmaPoints { points, mean_weights, covariance_weights } = estimation::sigma_points(&mean, &covariance, alpha, beta, kappa)?;
+
+                // The target's covariance is left out of the integrations, it is replaced anyway
+                system.bodies[target].covariance = None;
+                let mut states = Vec::new();
+                let mut measurements = Vec::new();
+                for point in &points {
+                    let mut copy = system.clone();
+                    set_body_state(&mut copy.bodies[target], &[point[0], point[1], point[2], point[3], point[4], point[5]]);
+                    copy.run_until(observation.time);
+                    let index = copy.bodies.iter().position(|body| body.id == target_id)?;
+                    states.push(body_state(&copy.bodies[index]).to_vec());
+                    measurements.push(copy.observe(index, &observation.observer, kind)?.0);
+                }
+                system.run_until(observation.time);
+                let index = system.bodies.iter().position(|body| body.id == target_id)?;
+
+                let weighted_mean = |deviations: &[Vec<f64>]| -> Vec<f64> {
+                    (0..deviations[0].len()).map(|k| deviations.iter().zip(&mean_weights).map(|(deviation, weight)| weight * deviation[k]).sum()).collect()
+                };
+                let state = weighted_mean(&states);
+                let state_deviations: Vec<Vec<f64>> = states.iter()
+                    .map(|point| point.iter().zip(&state).map(|(value, mean)| value - mean).collect())
+                    .collect();
+                // Measurement deviations are taken from the central point first so that
+                // right ascensions wrap correctly
+                let offsets: Vec<Vec<f64>> = measurements.iter().map(|measurement| kind.residual(measurement, &measurements[0])).collect();
+                let offset = weighted_mean(&offsets);
+                let measurement_deviations: Vec<Vec<f64>> = offsets.iter()
+                    .map(|deviation| deviation.iter().zip(&offset).map(|(value, mean)| value - mean).collect())
+                    .collect();
+                let predicted: Vec<f64> = measurements[0].iter().zip(&offset).map(|(value, offset)| value + offset).collect();
+                let prefit = kind.residual(&observation.values, &predicted);
+
+                let covariance = estimation::weighted_covariance(&state_deviations, &state_deviations, &covariance_weights).add(&process_noise);
+                let innovation = estimation::weighted_covariance(&measurement_deviations, &measurement_deviations, &covariance_weights).add(&measurement_noise);
+                let cross_covariance = estimation::weighted_covariance(&state_deviations, &measurement_deviations, &covariance_weights);
+                let gain = estimation::kalman_gain(&cross_covariance, &innovation)?;
+                let updated = covariance.subtract(&gain.multiply(&innovation).multiply(&gain.transpose()));
+
+                set_body_state(&mut system.bodies[index], &[state[0], state[1], state[2], state[3], state[4], state[5]]);
+                system.bodies[index].covariance = Some(covariance);
+                (index, [state[0], state[1], state[2], state[3], state[4], state[5]], prefit, innovation, gain, updated)
+            },
+        };
+
+        let innovation_sigmas: Vec<f64> = (0..prefit.len()).map(|k| innovation.rows[k][k].sqrt()).collect();
+        let accepted = prefit.iter().zip(&innovation_sigmas).all(|(residual, sigma)| residual.abs() <= options.rejection_threshold * sigma);
+        if accepted {
+            let correction = gain.multiply_vector(&prefit);
+            let mut corrected = state;
+            corrected.iter_mut().zip(&correction).for_each(|(value, delta)| *value += delta);
+            set_body_state(&mut system.bodies[index], &corrected);
+            system.bodies[index].covariance = Some(updated);
+        }
+
+        let postfit = system.observe(index, &observation.observer, kind)
+            .map_or(prefit.clone(), |(computed, _)| kind.residual(&observation.values, &computed));
+        let covariance = system.bodies[index].covariance.as_ref()?;
+        let update = estimation::FilterUpdate {
+            time: observation.time,
+            kind,
+            postfit_residuals: postfit,
+            prefit_residuals: prefit,
+            innovation_sigmas,
+            accepted,
+            position_sigma: (covariance.rows[0][0] + covariance.rows[1][1] + covariance.rows[2][2]).max(0.0).sqrt(),
+        };
+        *self = system;
+        Some(update)
+    }
+
+    // Runs the filter through `observations` in time order, skipping any that cannot be
+    // processed, and leaves the system at the time of the last one
+    pub fn run_filter(&mut self, target: usize, observations: &[estimation::Observation], options: &estimation::FilterOptions) -> Vec<estimation::FilterUpdate> {
+        let target_id = self.bodies[target].id;
+        let mut observations: Vec<&estimation::Observation> = observations.iter().collect();
+        observations.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        let mut updates = Vec::new();
+        for observation in observations {
+            let Some(index) = self.bodies.iter().position(|body| body.id == target_id) else {
+                break;
+            };
+            updates.extend(self.process_observation(index, observation, options));
+        }
+        updates
+    }
+
+    // Moves satellites flown from element sets to their SGP4 state about Earth. One whose
+    // elements stop propagating, for instance after decay, keeps its last state and goes
+    // on under the integrator.
+    fn apply_element_sets(&mut self) {
+        let Some(earth) = self.bodies.iter().position(|body| body.name == "Earth") else {
+            return;
+        };
+        let (earth_position, earth_velocity) = (self.bodies[earth].position.clone(), self.bodies[earth].velocity.clone());
+        for body in &mut self.bodies {
+            let Some(sgp4) = &body.sgp4 else {
+                continue;
+            };
+            match sgp4.ecliptic_state_at(self.time) {
+                Some((position, velocity)) => {
+                    body.position = earth_position.add(&position);
+                    body.velocity = earth_velocity.add(&velocity);
+                },
+                None => body.sgp4 = None,
+            }
+        }
+    }
+
+    // Adds a massless satellite flown by SGP4 from `tle` and returns its index. `None`
+    // without an Earth or when the elements do not propagate to the current time.
+    pub fn add_satellite_from_tle(&mut self, tle: &orbit::Tle) -> Option<usize> {
+        let earth = self.bodies.iter().position(|body| body.name == "Earth")?;
+        let sgp4 = orbit::Sgp4::new(tle)?;
+        let (position, velocity) = sgp4.ecliptic_state_at(self.time)?;
+        let satellite = body::CelestialBody::new(
+            tle.name.clone(),
+            BodyType::Satellite,
+            self.bodies[earth].position.add(&position),
+            0.01,
+            0.0,
+            self.bodies[earth].velocity.add(&velocity),
+            [0.9, 0.9, 0.9],
+        ).with_sgp4(sgp4);
+        self.add_body(satellite);
+        Some(self.bodies.len() - 1)
+    }
+
+    // Adds every element set of a two- or three-line file, returning how many made it in
+    pub fn load_tles(&mut self, path: &str) -> io::Result<usize> {
+        let tles = orbit::read_tles(path)?;
+        Ok(tles.iter().filter(|tle| self.add_satellite_from_tle(tle).is_some()).count())
+    }
+
+    // Geodetic point beneath `bodies[index]` now, on the WGS84 ellipsoid of an Earth that
+    // turns with its rotation model. `None` without such an Earth.
+    pub fn sub_satellite_point(&self, index: usize) -> Option<geodesy::Geodetic> {
+        let earth = self.bodies.iter().find(|body| body.name == "Earth")?;
+        let position = self.bodies[index].position.subtract(&earth.position);
+        Some(geodesy::sub_satellite_point(&position, earth.rotation.as_ref()?, self.time))
+    }
+
+    // Ground tracks of every satellite at `epochs`, made by integrating a copy of the
+    // system. Satellites lost on the way end their track early.
+    pub fn ground_tracks(&self, epochs: &[f64]) -> Vec<geodesy::GroundTrack> {
+        let mut tracks: Vec<(usize, geodesy::GroundTrack)> = self.bodies.iter()
+            .filter(|body| body.body_type == BodyType::Satellite)
+            .map(|body| (body.id, geodesy::GroundTrack::new(&body.name)))
+            .collect();
+        let mut epochs = epochs.to_vec();
+        epochs.sort_by(|a, b| a.total_cmp(b));
+
+        let mut copy = self.clone();
+        for epoch in epochs.into_iter().filter(|&epoch| epoch >= self.time) {
+            copy.run_until(epoch);
+            for (id, track) in &mut tracks {
+                let Some(index) = copy.bodies.iter().position(|body| body.id == *id) else {
+                    continue;
+                };
+                if let Some(point) = copy.sub_satellite_point(index) {
+                    track.points.push(geodesy::GroundPoint { time: copy.time, point });
+                }
+            }
+        }
+        tracks.into_iter().map(|(_, track)| track).collect()
+    }
+
+    // Azimuth, elevation, range and range rate of `bodies[target]` from a ground station
+    // now. `None` if the station's body is gone.
+    pub fn look_angles(&self, station: &geodesy::GroundStation, target: usize) -> Option<geodesy::LookAngles> {
+        let (position, velocity) = self.observer_state(&estimation::Observer::Station(station.station()))?;
+        let body = self.bodies.iter().find(|body| body.id == station.body)?;
+        let line_of_sight = self.bodies[target].position.subtract(&position);
+        let range_rate = line_of_sight.dot(&self.bodies[target].velocity.subtract(&velocity)) / line_of_sight.magnitude();
+        let line_of_sight = match &body.rotation {
+            Some(rotation) => rotation.to_body_fixed(&line_of_sight, self.time),
+            None => line_of_sight,
+        };
+        Some(station.look_angles(&line_of_sight, range_rate))
+    }
+
+    // Passes of each target over each station, above the station's elevation mask, from
+    // now until `end`. A copy of the system is integrated and sampled every `step`
+    // seconds; rise and set times are then refined by bisection to within a second, so
+    // passes shorter than a step can be missed. Empty unless `step` is positive and `end`
+    // finite, as the search would never finish otherwise.
+    pub fn access_windows(&self, stations: &[geodesy::GroundStation], targets: &[usize], end: f64, step: f64) -> Vec<geodesy::AccessWindow> {
+        if step.is_nan() || step <= 0.0 || !end.is_finite() {
+            return Vec::new();
+        }
+        let target_ids: Vec<usize> = targets.iter().map(|&target| self.bodies[target].id).collect();
+        let elevation = |system: &SolarSystem, station: &geodesy::GroundStation, id: usize| {
+            let index = system.bodies.iter().position(|body| body.id == id)?;
+            Some(system.look_angles(station, index)?.elevation - station.elevation_mask)
+        };
+
+        // Open windows, by station and target: rise time, highest elevation and its time
+        let mut open: Vec<Option<(f64, f64, f64)>> = vec![None; stations.len() * target_ids.len()];
+        let mut windows = Vec::new();
+        let mut copy = self.clone();
+        let mut previous: Option<SolarSystem> = None;
+        loop {
+            for (s, station) in stations.iter().enumerate() {
+                for (t, &id) in target_ids.iter().enumerate() {
+                    let height = elevation(&copy, station, id);
+                    let window = &mut open[s * target_ids.len() + t];
+                    match (height, window.as_mut()) {
+                        (Some(height), None) if height >= 0.0 => {
+                            let rise = match &previous {
+                                Some(previous) => refine_crossing(previous, copy.time, |system| elevation(system, station, id).is_some_and(|height| height >= 0.0)),
+                                None => copy.time,
+                            };
+                            *window = Some((rise, height + station.elevation_mask, copy.time));
+                        },
+                        (Some(height), Some(pass)) if height >= 0.0 && height + station.elevation_mask > pass.1 => {
+                            *pass = (pass.0, height + station.elevation_mask, copy.time);
+                        },
+                        (Some(height), Some(_)) if height >= 0.0 => {},
+                        (_, Some(&mut (rise, max_elevation, max_elevation_time))) => {
+                            let set = match (&previous, height) {
+                                (Some(previous), Some(_)) => refine_crossing(previous, copy.time, |system| elevation(system, station, id).is_some_and(|height| height < 0.0)),
+                                _ => copy.time,
+                            };
+                            windows.push(geodesy::AccessWindow {
+                                station: station.name.clone(),
+                                target: self.bodies[targets[t]].name.clone(),
+                                rise, set, max_elevation, max_elevation_time,
+                            });
+                            *window = None;
+                        },
+                        _ => {},
+                    }
+                }
+            }
+            if copy.time >= end {
+                break;
+            }
+            previous = Some(copy.clone());
+            copy.run_until((copy.time + step).min(end));
+        }
+
+        for (n, window) in open.into_iter().enumerate() {
+            if let Some((rise, max_elevation, max_elevation_time)) = window {
+                windows.push(geodesy::AccessWindow {
+                    station: stations[n / target_ids.len()].name.clone(),
+                    target: self.bodies[targets[n % target_ids.len()]].name.clone(),
+                    rise, set: copy.time, max_elevation, max_elevation_time,
+                });
+            }
+        }
+        windows.sort_by(|a, b| a.rise.total_cmp(&b.rise));
+        windows
+    }
+
+    // Eclipse of the star seen by an observer now. The observer's own body never counts
+    // as an occulter, so a station sees solar eclipses rather than night.
+    pub fn eclipse(&self, observer: &estimation::Observer) -> Option<physics::Eclipse> {
+        let (position, _) = self.observer_state(observer)?;
+        let own = match observer {
+            estimation::Observer::Body(id) => *id,
+            estimation::Observer::Station(station) => station.body,
+        };
+        let excluded: Vec<usize> = self.bodies.iter().position(|body| body.id == own).into_iter().collect();
+        Some(physics::eclipse(&self.bodies, &physics::shadow_casters(&self.bodies), &position, &excluded))
+    }
+
+    // Umbra, penumbra and annular intervals seen by an observer from now until `end`. A
+    // copy of the system is integrated and sampled every `step` seconds. Each change is
+    // refined by bisection to within a second, and phases passed through between two
+    // samples, such as the penumbra before an umbra, are picked up on the way. Empty
+    // unless `step` is positive and `end` finite.
+    pub fn eclipse_intervals(&self, observer: &estimation::Observer, end: f64, step: f64) -> Vec<physics::EclipseInterval> {
+        if step.is_nan() || step <= 0.0 || !end.is_finite() {
+            return Vec::new();
+        }
+        // Kind and occulter id, which together mark a change of interval, and the
+        // fraction hidden
+        let phase = |system: &SolarSystem| {
+            system.eclipse(observer).map(|eclipse| ((eclipse.kind, eclipse.occulter.map(|index| system.bodies[index].id)), eclipse.hidden_fraction))
+        };
+
+        let mut intervals = Vec::new();
+        let mut current: Option<physics::EclipseInterval> = None;
+        let mut current_phase = None;
+        let mut copy = self.clone();
+        let mut previous = copy.clone();
+        while let Some((sampled_phase, hidden)) = phase(&copy) {
+            while current_phase != Some(sampled_phase) {
+                // State just after the next change, or the sample itself at the start
+                let changed = match current_phase {
+                    Some(last) if previous.time < copy.time => {
+                        let time = refine_crossing(&previous, copy.time, |system| phase(system).is_some_and(|(phase, _)| phase != last));
+                        let mut changed = previous.clone();
+                        changed.run_until(time);
+                        changed
+                    },
+                    _ => copy.clone(),
+                };
+                let Some(((kind, occulter), changed_hidden)) = phase(&changed) else {
+                    break;
+                };
+                if let Some(mut interval) = current.take() {
+                    interval.end = changed.time;
+                    intervals.push(interval);
+                }
+                current = occulter.filter(|_| kind != physics::EclipseKind::Sunlit).map(|id| physics::EclipseInterval {
+                    kind,
+                    occulter: changed.bodies.iter().find(|body| body.id == id).map_or(String::new(), |body| body.name.clone()),
+                    start: changed.time,
+                    end: changed.time,
+                    max_hidden_fraction: changed_hidden,
+                    max_time: changed.time,
+                });
+                current_phase = Some((kind, occulter));
+                previous = changed;
+            }
+            if let Some(interval) = current.as_mut().filter(|interval| hidden > interval.max_hidden_fraction) {
+                interval.max_hidden_fraction = hidden;
+                interval.max_time = copy.time;
+            }
+            if copy.time >= end {
+                break;
+            }
+            previous = copy.clone();
+            copy.run_until((copy.time + step).min(end));
+        }
+
+        if let Some(mut interval) = current {
+            interval.end = copy.time;
+            intervals.push(interval);
+        }
+        intervals
+    }
+
+    // Puts every body with tabulated elements on its heliocentric orbit at `epoch`
+    pub fn place_planets(&mut self, epoch: f64) {
+        let Some(sun) = self.bodies.iter().position(|body| body.body_type == BodyType::Star) else {
+            return;
+        };
+        let (sun_position, sun_velocity, sun_mass) = (self.bodies[sun].position.clone(), self.bodies[sun].velocity.clone(), self.bodies[sun].mass);
+
+        for body in &mut self.bodies {
+            if let Some(elements) = orbit::PlanetaryElements::for_body(&body.name) {
+                let mu = physics::GRAVITATIONAL_CONST * (sun_mass + body.mass);
+                let (position, velocity) = elements.at(epoch).to_state(mu);
+                body.position = sun_position.add(&position);
+                body.velocity = sun_velocity.add(&velocity);
+            }
+        }
+    }
+
+    // Shifts all bodies so the barycentre sits at the origin with zero momentum
+    pub fn remove_net_momentum(&mut self) {
+        let total_mass: f64 = self.bodies.iter().map(|body| body.mass).sum();
+        if total_mass <= 0.0 {
+            return;
+        }
+
+        let mut barycentre = geometry::Vector3::new(0.0, 0.0, 0.0);
+        let mut momentum = geometry::Vector3::new(0.0, 0.0, 0.0);
+        for body in &self.bodies {
+            barycentre = barycentre.add(&body.position.scale(body.mass));
+            momentum = momentum.add(&body.velocity.scale(body.mass));
+        }
+        let barycentre = barycentre.scale(1.0 / total_mass);
+        let velocity = momentum.scale(1.0 / total_mass);
+
+        for body in &mut self.bodies {
+            body.position = body.position.subtract(&barycentre);
+            body.velocity = body.velocity.subtract(&velocity);
+        }
+    }
+
     pub fn initialize_standard() -> Self {
+        SolarSystem::initialize_at_epoch(0.0)
+    }
+
+    // Sun and planets at their positions at `epoch` (seconds past J2000) from the JPL
+    // approximate elements, with the barycentre at rest at the origin
+    pub fn initialize_at_epoch(epoch: f64) -> Self {
         let mut system = SolarSystem::new(3600.0, integrators::IntegratorType::RK4(30));  // 1 hour timestep
+        system.time = epoch;
 
         // Sun
         system.add_body(body::CelestialBody::new(
@@ -85,14 +1417,16 @@ impl SolarSystem {
             [1.0, 1.0, 0.0]          // yellow
         ));
 
+        // Planet states are filled in from their orbital elements below
+
         // Mercury
         system.add_body(body::CelestialBody::new(
             String::from("Mercury"),
             BodyType::Planet,
-            geometry::Vector3::new(57.9e9, 0.0, 0.0),
+            geometry::Vector3::new(0.0, 0.0, 0.0),
             2_439.7,
             3.285e23,
-            geometry::Vector3::new(0.0, 47360.0, 0.0),
+            geometry::Vector3::new(0.0, 0.0, 0.0),
             [0.7, 0.7, 0.7]          // grey
         ));
 
@@ -100,10 +1434,10 @@ impl SolarSystem {
         system.add_body(body::CelestialBody::new(
             String::from("Venus"),
             BodyType::Planet,
-            geometry::Vector3::new(108.2e9, 0.0, 0.0),
+            geometry::Vector3::new(0.0, 0.0, 0.0),
             6_051.8,
             4.867e24,
-            geometry::Vector3::new(0.0, 35020.0, 0.0),
+            geometry::Vector3::new(0.0, 0.0, 0.0),
             [0.9, 0.7, 0.5]          // pale yellow
         ));
 
@@ -111,10 +1445,10 @@ impl SolarSystem {
         system.add_body(body::CelestialBody::new(
             String::from("Earth"),
             BodyType::Planet,
-            geometry::Vector3::new(149.6e9, 0.0, 0.0),
+            geometry::Vector3::new(0.0, 0.0, 0.0),
             6_371.0,
             5.972e24,
-            geometry::Vector3::new(0.0, 29780.0, 0.0),
+            geometry::Vector3::new(0.0, 0.0, 0.0),
             [0.2, 0.5, 1.0]          // blue
         ));
 
@@ -122,10 +1456,10 @@ impl SolarSystem {
         system.add_body(body::CelestialBody::new(
             String::from("Mars"),
             BodyType::Planet,
-            geometry::Vector3::new(227.9e9, 0.0, 0.0),
+            geometry::Vector3::new(0.0, 0.0, 0.0),
             3_389.5,
             6.39e23,
-            geometry::Vector3::new(0.0, 24080.0, 0.0),
+            geometry::Vector3::new(0.0, 0.0, 0.0),
             [1.0, 0.3, 0.0]          // red
         ));
 
@@ -133,10 +1467,10 @@ impl SolarSystem {
         system.add_body(body::CelestialBody::new(
             String::from("Jupiter"),
             BodyType::Planet,
-            geometry::Vector3::new(778.5e9, 0.0, 0.0),
+            geometry::Vector3::new(0.0, 0.0, 0.0),
             69_911.0,
             1.898e27,
-            geometry::Vector3::new(0.0, 13070.0, 0.0),
+            geometry::Vector3::new(0.0, 0.0, 0.0),
             [0.8, 0.6, 0.4]          // orange-brown
         ));
 
@@ -144,10 +1478,10 @@ impl SolarSystem {
         system.add_body(body::CelestialBody::new(
             String::from("Saturn"),
             BodyType::Planet,
-            geometry::Vector3::new(1.434e12, 0.0, 0.0),
+            geometry::Vector3::new(0.0, 0.0, 0.0),
             58_232.0,
             5.683e26,
-            geometry::Vector3::new(0.0, 9680.0, 0.0),
+            geometry::Vector3::new(0.0, 0.0, 0.0),
             [0.9, 0.8, 0.5]          // pale gold
         ));
 
@@ -155,10 +1489,10 @@ impl SolarSystem {
         system.add_body(body::CelestialBody::new(
             String::from("Uranus"),
             BodyType::Planet,
-            geometry::Vector3::new(2.871e12, 0.0, 0.0),
+            geometry::Vector3::new(0.0, 0.0, 0.0),
             25_362.0,
             8.681e25,
-            geometry::Vector3::new(0.0, 6800.0, 0.0),
+            geometry::Vector3::new(0.0, 0.0, 0.0),
             [0.5, 0.8, 0.9]          // pale blue
         ));
 
@@ -166,13 +1500,252 @@ impl SolarSystem {
         system.add_body(body::CelestialBody::new(
             String::from("Neptune"),
             BodyType::Planet,
-            geometry::Vector3::new(4.495e12, 0.0, 0.0),
+            geometry::Vector3::new(0.0, 0.0, 0.0),
             24_622.0,
             1.024e26,
-            geometry::Vector3::new(0.0, 5430.0, 0.0),
+            geometry::Vector3::new(0.0, 0.0, 0.0),
             [0.0, 0.0, 0.8]          // deep blue
         ));
 
+        for body in &mut system.bodies {
+            body.rotation = RotationModel::for_body(&body.name);
+        }
+        system.place_planets(epoch);
+        system.remove_net_momentum();
+
+        system
+    }
+}
+
+// Earliest time after `start.time` and by `end` at which `condition` holds, assuming it
+// switches once in between, to within a second
+fn refine_crossing(start: &SolarSystem, end: f64, condition: impl Fn(&SolarSystem) -> bool) -> f64 {
+    let (mut before, mut after) = (start.time, end);
+    while after - before > 1.0 {
+        let middle = 0.5 * (before + after);
+        let mut copy = start.clone();
+        copy.run_until(middle);
+        if condition(&copy) {
+            after = middle;
+        } else {
+            before = middle;
+        }
+    }
+    after
+}
+
+fn body_state(body: &body::CelestialBody) -> [f64; 6] {
+    [body.position.x, body.position.y, body.position.z, body.velocity.x, body.velocity.y, body.velocity.z]
+}
+
+fn set_body_state(body: &mut body::CelestialBody, state: &[f64; 6]) {
+    body.position = geometry::Vector3::new(state[0], state[1], state[2]);
+    body.velocity = geometry::Vector3::new(state[3], state[4], state[5]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EARTH_MASS: f64 = 5.972e24;
+
+    // Rotating Earth with a satellite in a 7000 km orbit inclined by 0.5 rad
+    fn low_earth_orbit(timestep: f64, integrator: integrators::IntegratorType) -> SolarSystem {
+        let mut system = SolarSystem::new(timestep, integrator);
+        system.add_body(body::CelestialBody::new(
+            "Earth".to_string(), BodyType::Planet, geometry::Vector3::new(0.0, 0.0, 0.0), 6378.137, EARTH_MASS,
+            geometry::Vector3::new(0.0, 0.0, 0.0), [0.0, 0.0, 1.0],
+        ).with_rotation(RotationModel::earth()));
+        let (position, velocity) = orbit::KeplerianElements::new(7.0e6, 0.01, 0.5, 0.3, 0.2, 0.1)
+            .to_state(physics::GRAVITATIONAL_CONST * EARTH_MASS);
+        system.add_body(body::CelestialBody::new(
+            "Satellite".to_string(), BodyType::Satellite, position, 0.001, 1000.0, velocity, [1.0, 1.0, 1.0],
+        ));
         system
     }
-}
\ No newline at end of file
+
+    fn station(name: &str, latitude: f64, longitude: f64) -> estimation::Observer {
+        estimation::Observer::Station(estimation::Station::from_spherical(name, 0, 6.378137e6, latitude, longitude, 0.0))
+    }
+
+    // Largest difference between the variational and finite-difference transition
+    // matrices over 3000 s of a low orbit, relative to the largest element of each block
+    fn transition_matrix_error(integrator: integrators::IntegratorType) -> f64 {
+        let system = low_earth_orbit(30.0, integrator);
+        let epochs = [1000.0, 3000.0];
+        let variational = system.state_transition_matrices(1, &epochs);
+        let differenced = system.finite_difference_transition_matrices(1, &epochs);
+
+        let mut worst: f64 = 0.0;
+        for (a, b) in variational.iter().zip(&differenced) {
+            for (rows, columns) in [(0..3, 0..3), (0..3, 3..6), (3..6, 0..3), (3..6, 3..6)] {
+                let scale = rows.clone()
+                    .flat_map(|row| columns.clone().map(move |column| (row, column)))
+                    .map(|(row, column)| b.rows[row][column].abs())
+                    .fold(0.0, f64::max);
+                for row in rows.clone() {
+                    for column in columns.clone() {
+                        worst = worst.max((a.rows[row][column] - b.rows[row][column]).abs() / scale);
+                    }
+                }
+            }
+        }
+        worst
+    }
+
+    #[test]
+    fn variational_matrices_match_finite_differences() {
+        for integrator in [integrators::IntegratorType::RK4(1), integrators::IntegratorType::Euler, integrators::IntegratorType::Kepler(0)] {
+            let error = transition_matrix_error(integrator);
+            assert!(error < 2.5e-8, "{:?} off by {:e}", integrator, error);
+        }
+    }
+
+    #[test]
+    fn failed_filter_update_leaves_the_system_untouched() {
+        let mut system = low_earth_orbit(30.0, integrators::IntegratorType::RK4(1));
+        system.bodies[1].covariance = Some(geometry::Matrix::diagonal(&[1e6, 1e6, 1e6, 1.0, 1.0, 1.0]));
+        let state = body_state(&system.bodies[1]);
+
+        // Observed from a body that does not exist, so the measurement cannot be modelled
+        let observation = estimation::Observation::new(600.0, estimation::ObservationKind::Range, estimation::Observer::Body(99), vec![1e6], vec![10.0]);
+        let kinds = [estimation::FilterKind::Extended, estimation::FilterKind::Unscented { alpha: 1e-3, beta: 2.0, kappa: 0.0 }];
+        for kind in kinds {
+            assert!(system.process_observation(1, &observation, &estimation::FilterOptions::new(kind)).is_none());
+            assert_eq!(system.time, 0.0);
+            assert_eq!(body_state(&system.bodies[1]), state);
+            assert_eq!(system.bodies[1].covariance.as_ref().unwrap().rows[0][0], 1e6);
+        }
+    }
+
+    #[test]
+    fn batch_fit_recovers_a_perturbed_orbit() {
+        let truth = low_earth_orbit(30.0, integrators::IntegratorType::RK4(1));
+        let times: Vec<f64> = (1..=120).map(|n| n as f64 * 60.0).collect();
+        let ranging = station("Ranging", 0.6, 0.0);
+        let optical = station("Optical", -0.4, 2.0);
+
+        let mut observations = truth.simulate_observations(1, &ranging, estimation::ObservationKind::Range, &times, &[10.0], 1);
+        observations.extend(truth.simulate_observations(1, &ranging, estimation::ObservationKind::RangeRate, &times, &[0.01], 2));
+        observations.extend(truth.simulate_observations(1, &optical, estimation::ObservationKind::RightAscensionDeclination, &times, &[1e-5, 1e-5], 3));
+
+        let mut guess = truth.clone();
+        guess.bodies[1].position = guess.bodies[1].position.add(&geometry::Vector3::new(2000.0, -1500.0, 1000.0));
+        guess.bodies[1].velocity = guess.bodies[1].velocity.add(&geometry::Vector3::new(-1.0, 2.0, 1.5));
+
+        let solution = guess.fit_orbit(1, &observations, &estimation::BatchOptions::new()).unwrap();
+        assert!(solution.converged);
+        assert!(solution.rms < 1.5, "rms {}", solution.rms);
+
+        let expected = body_state(&truth.bodies[1]);
+        let estimate = [solution.position.x, solution.position.y, solution.position.z, solution.velocity.x, solution.velocity.y, solution.velocity.z];
+        for (n, sigma) in solution.sigmas().iter().enumerate() {
+            assert!((estimate[n] - expected[n]).abs() < 4.0 * sigma, "component {} off by {} with sigma {}", n, estimate[n] - expected[n], sigma);
+        }
+        assert!(solution.position_uncertainty() < 100.0);
+    }
+
+    #[test]
+    fn burns_follow_their_body_through_collisions() {
+        // Two boulders meet 10 s into the step and merge, which moves the satellite
+        // behind them down the body list before its burn at 50 s
+        let mut system = SolarSystem::new(100.0, integrators::IntegratorType::RK4(1));
+        for (name, x, speed) in [("First", -2000.0, 100.0), ("Second", 2000.0, -100.0)] {
+            system.add_body(body::CelestialBody::new(
+                name.to_string(), BodyType::Asteroid, geometry::Vector3::new(x, 0.0, 0.0), 1.0, 1000.0,
+                geometry::Vector3::new(speed, 0.0, 0.0), [0.5, 0.5, 0.5],
+            ));
+        }
+        let mut satellite = body::CelestialBody::new(
+            "Satellite".to_string(), BodyType::Satellite, geometry::Vector3::new(0.0, 1.0e9, 0.0), 0.001, 1000.0,
+            geometry::Vector3::new(0.0, 0.0, 0.0), [1.0, 1.0, 1.0],
+        );
+        satellite.schedule_burn(mission::ImpulsiveBurn::new(50.0, geometry::Vector3::new(10.0, 0.0, 0.0), mission::BurnFrame::Inertial));
+        system.add_body(satellite);
+
+        system.update();
+
+        assert_eq!(system.collision_log.len(), 1);
+        assert_eq!(system.bodies.len(), 2);
+        assert!(system.bodies[0].velocity.magnitude() < 1e-6);
+        let satellite = &system.bodies[1];
+        assert_eq!(satellite.name, "Satellite");
+        assert!((satellite.velocity.x - 10.0).abs() < 1e-9);
+        assert!((satellite.position.x - 500.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn circular_orbit_pass_over_an_equatorial_station() {
+        // Non-rotating Earth and a satellite 500 km up in its equatorial plane, starting
+        // 60 degrees before the station. It is in view while within the horizon angle
+        // acos(R / r) of the station, so the pass lasts 2 acos(R / r) / n.
+        let radius: f64 = 6.378137e6;
+        let orbit_radius = radius + 500_000.0;
+        let mu = physics::GRAVITATIONAL_CONST * EARTH_MASS;
+        let mean_motion = (mu / orbit_radius.powi(3)).sqrt();
+        let start = -60f64.to_radians();
+        let mut system = SolarSystem::new(10.0, integrators::IntegratorType::RK4(1));
+        system.add_body(body::CelestialBody::new(
+            "Earth".to_string(), BodyType::Planet, geometry::Vector3::new(0.0, 0.0, 0.0), 6378.137, EARTH_MASS,
+            geometry::Vector3::new(0.0, 0.0, 0.0), [0.0, 0.0, 1.0],
+        ));
+        system.add_body(body::CelestialBody::new(
+            "Satellite".to_string(), BodyType::Satellite,
+            geometry::Vector3::new(start.cos(), start.sin(), 0.0).scale(orbit_radius), 0.001, 1.0,
+            geometry::Vector3::new(-start.sin(), start.cos(), 0.0).scale(orbit_radius * mean_motion), [1.0, 1.0, 1.0],
+        ));
+        let earth_id = system.bodies[0].id;
+        let station = geodesy::GroundStation::new("Equator", earth_id, geodesy::Geodetic::new(0.0, 0.0, 0.0));
+
+        let windows = system.access_windows(&[station], &[1], 3000.0, 60.0);
+        assert_eq!(windows.len(), 1);
+        let horizon_angle = (radius / orbit_radius).acos();
+        let window = &windows[0];
+        assert!((window.rise - (-start - horizon_angle) / mean_motion).abs() < 2.0);
+        assert!((window.duration() - 2.0 * horizon_angle / mean_motion).abs() < 2.0);
+        // The highest sample is within a step of the zenith crossing
+        assert!((window.max_elevation_time + start / mean_motion).abs() < 60.0);
+    }
+
+    #[test]
+    fn low_orbit_shadow_matches_a_cylinder() {
+        // Satellite 7000 km from the centre of the Earth in the ecliptic, starting a
+        // quarter orbit before the anti-Sun direction. A cylindrical shadow holds it for
+        // 2 asin(R / r) / n; the conical umbra is a few seconds shorter and the penumbra a
+        // few seconds either side of it.
+        let radius: f64 = 6.378137e6;
+        let orbit_radius: f64 = 7.0e6;
+        let mu = physics::GRAVITATIONAL_CONST * EARTH_MASS;
+        let mean_motion = (mu / orbit_radius.powi(3)).sqrt();
+        let earth_position = geometry::Vector3::new(physics::ASTRONOMICAL_UNIT, 0.0, 0.0);
+        let earth_velocity = geometry::Vector3::new(0.0, 29_780.0, 0.0);
+        let mut system = SolarSystem::new(10.0, integrators::IntegratorType::RK4(1));
+        system.add_body(body::CelestialBody::new(
+            "Sun".to_string(), BodyType::Star, geometry::Vector3::new(0.0, 0.0, 0.0), 695_700.0, 1.989e30,
+            geometry::Vector3::new(0.0, 0.0, 0.0), [1.0, 1.0, 0.0],
+        ));
+        system.add_body(body::CelestialBody::new(
+            "Earth".to_string(), BodyType::Planet, earth_position.clone(), 6378.137, EARTH_MASS,
+            earth_velocity.clone(), [0.0, 0.0, 1.0],
+        ));
+        system.add_body(body::CelestialBody::new(
+            "Satellite".to_string(), BodyType::Satellite, earth_position.add(&geometry::Vector3::new(0.0, -orbit_radius, 0.0)), 0.001, 1.0,
+            earth_velocity.add(&geometry::Vector3::new(orbit_radius * mean_motion, 0.0, 0.0)), [1.0, 1.0, 1.0],
+        ));
+        let observer = estimation::Observer::Body(system.bodies[2].id);
+
+        let intervals = system.eclipse_intervals(&observer, 3000.0, 30.0);
+        let kinds: Vec<physics::EclipseKind> = intervals.iter().map(|interval| interval.kind).collect();
+        assert_eq!(kinds, [physics::EclipseKind::Penumbra, physics::EclipseKind::Umbra, physics::EclipseKind::Penumbra]);
+        assert!(intervals.iter().all(|interval| interval.occulter == "Earth"));
+
+        let half_angle = (radius / orbit_radius).asin();
+        let (entry, exit) = ((std::f64::consts::FRAC_PI_2 - half_angle) / mean_motion, (std::f64::consts::FRAC_PI_2 + half_angle) / mean_motion);
+        let umbra = &intervals[1];
+        assert!(umbra.duration() < exit - entry && umbra.duration() > exit - entry - 10.0);
+        assert!((umbra.start + umbra.end - entry - exit).abs() < 5.0);
+        assert!(intervals[0].start < entry && intervals[2].end > exit);
+        assert!(intervals[0].duration() < 15.0 && intervals[2].duration() < 15.0);
+        assert_eq!(umbra.max_hidden_fraction, 1.0);
+    }
+}
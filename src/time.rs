@@ -0,0 +1,125 @@
+// Simulation time is measured in seconds past the J2000 epoch (2000-01-01 12:00 TDB)
+
+use std::fmt;
+
+pub const SECONDS_PER_DAY: f64 = 86_400.0;
+pub const DAYS_PER_JULIAN_CENTURY: f64 = 36_525.0;
+pub const J2000_JULIAN_DATE: f64 = 2_451_545.0;
+
+pub fn days_since_j2000(time: f64) -> f64 {
+    time / SECONDS_PER_DAY
+}
+
+pub fn centuries_since_j2000(time: f64) -> f64 {
+    days_since_j2000(time) / DAYS_PER_JULIAN_CENTURY
+}
+
+pub fn to_julian_date(time: f64) -> f64 {
+    J2000_JULIAN_DATE + days_since_j2000(time)
+}
+
+pub fn from_julian_date(julian_date: f64) -> f64 {
+    (julian_date - J2000_JULIAN_DATE) * SECONDS_PER_DAY
+}
+
+// TT - TDB never exceeds 2 ms, so TT is used for TDB when converting to civil time
+const TT_MINUS_TAI: f64 = 32.184;
+
+// (Julian date in UTC from which it applies, TAI - UTC in seconds)
+const LEAP_SECONDS: [(f64, f64); 28] = [
+    (2_441_317.5, 10.0), (2_441_499.5, 11.0), (2_441_683.5, 12.0), (2_442_048.5, 13.0),
+    (2_442_413.5, 14.0), (2_442_778.5, 15.0), (2_443_144.5, 16.0), (2_443_509.5, 17.0),
+    (2_443_874.5, 18.0), (2_444_239.5, 19.0), (2_444_786.5, 20.0), (2_445_151.5, 21.0),
+    (2_445_516.5, 22.0), (2_446_247.5, 23.0), (2_447_161.5, 24.0), (2_447_892.5, 25.0),
+    (2_448_257.5, 26.0), (2_448_804.5, 27.0), (2_449_169.5, 28.0), (2_449_534.5, 29.0),
+    (2_450_083.5, 30.0), (2_450_630.5, 31.0), (2_451_179.5, 32.0), (2_453_736.5, 33.0),
+    (2_454_832.5, 34.0), (2_456_109.5, 35.0), (2_457_204.5, 36.0), (2_457_754.5, 37.0),
+];
+
+// TAI - UTC at a UTC Julian date; 10 s before 1972, when UTC was not yet in whole seconds
+pub fn leap_seconds(julian_date_utc: f64) -> f64 {
+    LEAP_SECONDS.iter()
+        .rev()
+        .find(|(start, _)| julian_date_utc >= *start)
+        .map_or(LEAP_SECONDS[0].1, |(_, seconds)| *seconds)
+}
+
+pub fn from_utc_julian_date(julian_date_utc: f64) -> f64 {
+    from_julian_date(julian_date_utc) + leap_seconds(julian_date_utc) + TT_MINUS_TAI
+}
+
+pub fn to_utc_julian_date(time: f64) -> f64 {
+    let approximate = to_julian_date(time - leap_seconds(to_julian_date(time)) - TT_MINUS_TAI);
+    to_julian_date(time - leap_seconds(approximate) - TT_MINUS_TAI)
+}
+
+// UTC calendar date of a simulation time, to the nearest second. Times inside a leap
+// second come out as the first second of the next minute.
+pub fn utc_calendar_date(time: f64) -> CalendarDate {
+    // Half a second in keeps the floor below clear of rounding in the conversion
+    let whole_seconds = from_julian_date(to_utc_julian_date(time)).round();
+    let mut date = CalendarDate::from_time(whole_seconds + 0.5);
+    date.second = date.second.floor();
+    date
+}
+
+// Gregorian calendar date on the simulation time scale. No leap seconds are applied,
+// so this is the TDB date rather than UTC (they differ by about a minute).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CalendarDate {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: f64,
+}
+
+impl CalendarDate {
+    // Meeus, Astronomical Algorithms, chapter 7
+    pub fn from_time(time: f64) -> Self {
+        let julian_date = to_julian_date(time) + 0.5;
+        let z = julian_date.floor();
+        let fraction = julian_date - z;
+        let a = if z < 2_299_161.0 {
+            z
+        } else {
+            let alpha = ((z - 1_867_216.25) / 36_524.25).floor();
+            z + 1.0 + alpha - (alpha / 4.0).floor()
+        };
+        let b = a + 1524.0;
+        let c = ((b - 122.1) / 365.25).floor();
+        let d = (365.25 * c).floor();
+        let e = ((b - d) / 30.6001).floor();
+
+        let day = (b - d - (30.6001 * e).floor()) as u32;
+        let month = if e < 14.0 { e - 1.0 } else { e - 13.0 } as u32;
+        let year = if month > 2 { c - 4716.0 } else { c - 4715.0 } as i64;
+
+        let seconds = fraction * SECONDS_PER_DAY;
+        let hour = (seconds / 3600.0).floor() as u32;
+        let minute = ((seconds - hour as f64 * 3600.0) / 60.0).floor() as u32;
+        let second = seconds - hour as f64 * 3600.0 - minute as f64 * 60.0;
+        CalendarDate { year, month, day, hour, minute, second }
+    }
+
+    pub fn to_time(&self) -> f64 {
+        let (year, month) = if self.month > 2 { (self.year, self.month) } else { (self.year - 1, self.month + 12) };
+        let a = (year as f64 / 100.0).floor();
+        let b = 2.0 - a + (a / 4.0).floor();
+        let day = self.day as f64 + (self.hour as f64 * 3600.0 + self.minute as f64 * 60.0 + self.second) / SECONDS_PER_DAY;
+        let julian_date = (365.25 * (year as f64 + 4716.0)).floor() + (30.6001 * (month as f64 + 1.0)).floor() + day + b - 1524.5;
+        from_julian_date(julian_date)
+    }
+
+    // YYYY-MM-DD
+    pub fn date_string(&self) -> String {
+        format!("{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl fmt::Display for CalendarDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}T{:02}:{:02}:{:06.3}", self.date_string(), self.hour, self.minute, self.second)
+    }
+}